@@ -0,0 +1,156 @@
+//! RFC 3164 and RFC 5424 formatters, writing the frames into a `String`
+use alloc::string::String;
+use core::fmt::{self, Display, Write};
+
+use crate::{encode_priority, Facility, Severity};
+
+const HOSTNAME_MAX: usize = 255;
+const APP_NAME_MAX: usize = 48;
+
+/// Writes messages as frames
+pub trait Format {
+    /// appends the frame of `message` at `severity` to `frame`
+    fn format<T: Display>(&self, frame: &mut String, severity: Severity, message: T)
+        -> fmt::Result;
+}
+
+/// RFC 3164 frames without TIMESTAMP and HOSTNAME, which relays add on reception:
+/// `<14>main[7]: message`
+#[derive(Clone, Debug)]
+pub struct Formatter3164 {
+    pub facility: Facility,
+    pub process: String,
+    pub pid: Option<u32>,
+}
+
+impl Default for Formatter3164 {
+    /// Returns a `Formatter3164` with `LOG_USER` as facility, `main` as process, and no pid
+    fn default() -> Self {
+        Formatter3164 {
+            facility: Facility::LOG_USER,
+            process: "main".into(),
+            pid: None,
+        }
+    }
+}
+
+impl Format for Formatter3164 {
+    fn format<T: Display>(
+        &self,
+        frame: &mut String,
+        severity: Severity,
+        message: T,
+    ) -> fmt::Result {
+        write!(
+            frame,
+            "<{}>{}",
+            encode_priority(severity, self.facility),
+            self.process
+        )?;
+        if let Some(pid) = self.pid {
+            write!(frame, "[{}]", pid)?;
+        }
+        write!(frame, ": {}", message)
+    }
+}
+
+/// RFC 5424 frames with a NILVALUE TIMESTAMP, MSGID and STRUCTURED-DATA:
+/// `<14>1 - esp32 main 7 - - message`
+#[derive(Clone, Debug)]
+pub struct Formatter5424 {
+    pub facility: Facility,
+    /// NILVALUE when `None`
+    pub hostname: Option<String>,
+    pub process: String,
+    /// NILVALUE when `None`
+    pub pid: Option<u32>,
+}
+
+impl Default for Formatter5424 {
+    /// Returns a `Formatter5424` with `LOG_USER` as facility, `main` as process, and no
+    /// hostname or pid
+    fn default() -> Self {
+        Formatter5424 {
+            facility: Facility::LOG_USER,
+            hostname: None,
+            process: "main".into(),
+            pid: None,
+        }
+    }
+}
+
+impl Format for Formatter5424 {
+    fn format<T: Display>(
+        &self,
+        frame: &mut String,
+        severity: Severity,
+        message: T,
+    ) -> fmt::Result {
+        write!(
+            frame,
+            "<{}>1 - {} {} ",
+            encode_priority(severity, self.facility),
+            HeaderField(self.hostname.as_deref().unwrap_or(""), HOSTNAME_MAX),
+            HeaderField(&self.process, APP_NAME_MAX)
+        )?;
+        match self.pid {
+            Some(pid) => write!(frame, "{}", pid)?,
+            None => frame.push('-'),
+        }
+        write!(frame, " - - {}", message)
+    }
+}
+
+/// A header field: printable US-ASCII only, truncated to its maximum length, and NILVALUE
+/// when nothing is left
+struct HeaderField<'a>(&'a str, usize);
+
+impl<'a> Display for HeaderField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut printable = self
+            .0
+            .chars()
+            .filter(|c| ('!'..='~').contains(c))
+            .take(self.1);
+        match printable.next() {
+            Some(first) => {
+                f.write_char(first)?;
+                printable.try_for_each(|c| f.write_char(c))
+            }
+            None => f.write_char('-'),
+        }
+    }
+}
+
+#[test]
+fn test_formatters() {
+    let mut frame = String::new();
+    let mut formatter = Formatter3164::default();
+    formatter
+        .format(&mut frame, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert_eq!(frame, "<14>main: hello");
+
+    frame.clear();
+    formatter.facility = Facility::LOG_LOCAL0;
+    formatter.pid = Some(7);
+    formatter
+        .format(&mut frame, Severity::LOG_WARNING, 42)
+        .unwrap();
+    assert_eq!(frame, "<132>main[7]: 42");
+
+    frame.clear();
+    let mut formatter = Formatter5424::default();
+    formatter
+        .format(&mut frame, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert_eq!(frame, "<14>1 - - main - - - hello");
+
+    frame.clear();
+    formatter.hostname = Some("esp 32".into());
+    formatter.pid = Some(7);
+    formatter
+        .format(&mut frame, Severity::LOG_ERR, "hello")
+        .unwrap();
+    assert_eq!(frame, "<11>1 - esp32 main 7 - - hello");
+}
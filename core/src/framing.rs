@@ -0,0 +1,81 @@
+//! Framing of syslog messages over stream transports (RFC 6587)
+use alloc::format;
+use alloc::vec::Vec;
+use core::str;
+
+/// How frames are delimited on a stream, where nothing else tells where each one ends
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// every frame is prefixed by its length in decimal and a space, e.g. `42 <14>...`, which
+    /// carries any message unchanged; required by RFC 5425 over TLS
+    OctetCounted,
+    /// every frame is terminated by a line feed, as most servers also accept; a message
+    /// containing a line feed is received as several ones
+    NonTransparent,
+}
+
+impl Framing {
+    /// returns `frame`, delimited
+    pub fn encode(self, frame: &[u8]) -> Vec<u8> {
+        match self {
+            Framing::OctetCounted => {
+                let mut counted = format!("{} ", frame.len()).into_bytes();
+                counted.extend_from_slice(frame);
+                counted
+            }
+            Framing::NonTransparent => {
+                let mut terminated = Vec::with_capacity(frame.len() + 1);
+                terminated.extend_from_slice(frame);
+                terminated.push(b'\n');
+                terminated
+            }
+        }
+    }
+
+    /// splits `stream`, frames delimited with this framing, back into the frames; what can't
+    /// be split, e.g. a cut frame, is returned as the last one
+    pub fn split(self, mut stream: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        while !stream.is_empty() {
+            let end = match self {
+                Framing::OctetCounted => octet_count(stream),
+                Framing::NonTransparent => stream
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map(|len| (0, len, len + 1)),
+            };
+            let (start, len, next) = end.unwrap_or((0, stream.len(), stream.len()));
+            frames.push(stream[start..start + len].into());
+            stream = &stream[next..];
+        }
+        frames
+    }
+}
+
+/// returns where the first octet-counted frame of `stream` starts, its length, and where the
+/// next one starts
+fn octet_count(stream: &[u8]) -> Option<(usize, usize, usize)> {
+    let space = stream.iter().position(|&b| b == b' ')?;
+    let len: usize = str::from_utf8(&stream[..space]).ok()?.parse().ok()?;
+    let next = (space + 1)
+        .checked_add(len)
+        .filter(|&next| next <= stream.len())?;
+    Some((space + 1, len, next))
+}
+
+#[test]
+fn test_framing() {
+    assert_eq!(Framing::OctetCounted.encode(b"<14>hello"), b"9 <14>hello");
+    assert_eq!(Framing::NonTransparent.encode(b"<14>hello"), b"<14>hello\n");
+    assert_eq!(Framing::OctetCounted.encode(b""), b"0 ");
+
+    let stream = [Framing::OctetCounted.encode(b"<14>a b"), b"9 <14".into()].concat();
+    assert_eq!(
+        Framing::OctetCounted.split(&stream),
+        [&b"<14>a b"[..], b"9 <14"]
+    );
+    assert_eq!(
+        Framing::NonTransparent.split(b"<14>one\n<14>two\n<14>cut"),
+        [&b"<14>one"[..], b"<14>two", b"<14>cut"]
+    );
+}
@@ -0,0 +1,148 @@
+//! The `no_std` core of `esp_syslog`: RFC 3164 and RFC 5424 formatting, and RFC 6587 framing.
+//!
+//! Bare-metal esp-hal builds have no esp-idf, hence no `std`: `esp_syslog` itself does not
+//! build there. This crate only needs `core` and `alloc`, and with the `embedded-nal` feature
+//! sends the frames through any `embedded-nal` stack, such as `esp-wifi`'s smoltcp-based one.
+//!
+//! Without a clock the frames carry no TIMESTAMP, which collectors add on reception, as
+//! `esp_syslog` does without its `timestamps` feature:
+//!
+//! ```ignore
+//! let server = "192.168.1.10:514".parse()?;
+//! let mut logger = Logger::new(NalUdp::connect(stack, server)?, Formatter3164::default());
+//! logger.send(Severity::LOG_INFO, format_args!("connected in {} ms", elapsed))?;
+//! ```
+#![no_std]
+
+extern crate alloc;
+
+mod format;
+mod framing;
+#[cfg(feature = "embedded-nal")]
+mod nal;
+
+pub use format::{Format, Formatter3164, Formatter5424};
+pub use framing::Framing;
+#[cfg(feature = "embedded-nal")]
+pub use nal::{NalError, NalTcp, NalUdp};
+
+use alloc::string::String;
+use core::fmt::{self, Display};
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    LOG_EMERG,
+    LOG_ALERT,
+    LOG_CRIT,
+    LOG_ERR,
+    LOG_WARNING,
+    LOG_NOTICE,
+    LOG_INFO,
+    LOG_DEBUG,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Facility {
+    LOG_KERN = 0 << 3,
+    LOG_USER = 1 << 3,
+    LOG_MAIL = 2 << 3,
+    LOG_DAEMON = 3 << 3,
+    LOG_AUTH = 4 << 3,
+    LOG_SYSLOG = 5 << 3,
+    LOG_LPR = 6 << 3,
+    LOG_NEWS = 7 << 3,
+    LOG_UUCP = 8 << 3,
+    LOG_CRON = 9 << 3,
+    LOG_AUTHPRIV = 10 << 3,
+    LOG_FTP = 11 << 3,
+    LOG_LOCAL0 = 16 << 3,
+    LOG_LOCAL1 = 17 << 3,
+    LOG_LOCAL2 = 18 << 3,
+    LOG_LOCAL3 = 19 << 3,
+    LOG_LOCAL4 = 20 << 3,
+    LOG_LOCAL5 = 21 << 3,
+    LOG_LOCAL6 = 22 << 3,
+    LOG_LOCAL7 = 23 << 3,
+}
+
+/// Where the frames go: a datagram socket, or a stream delimiting them with a `Framing`
+pub trait Transport {
+    type Error;
+
+    /// sends `frame` as one syslog message
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Why a message wasn't sent
+#[derive(Debug)]
+pub enum SendError<E> {
+    /// the message's `Display` implementation failed
+    Format(fmt::Error),
+    Transport(E),
+}
+
+/// Formats messages with a formatter, and sends them through a transport
+pub struct Logger<T: Transport, F: Format> {
+    pub transport: T,
+    pub formatter: F,
+    /// reused from a message to the next, to allocate once it grew large enough
+    frame: String,
+}
+
+impl<T: Transport, F: Format> Logger<T, F> {
+    pub fn new(transport: T, formatter: F) -> Logger<T, F> {
+        Logger {
+            transport,
+            formatter,
+            frame: String::new(),
+        }
+    }
+
+    /// formats `message` at `severity`, and sends it
+    pub fn send<M: Display>(
+        &mut self,
+        severity: Severity,
+        message: M,
+    ) -> Result<(), SendError<T::Error>> {
+        self.frame.clear();
+        self.formatter
+            .format(&mut self.frame, severity, message)
+            .map_err(SendError::Format)?;
+        self.transport
+            .send(self.frame.as_bytes())
+            .map_err(SendError::Transport)
+    }
+}
+
+fn encode_priority(severity: Severity, facility: Facility) -> u8 {
+    facility as u8 | severity as u8
+}
+
+#[test]
+fn test_logger() {
+    use alloc::vec::Vec;
+
+    #[derive(Default)]
+    struct Sent(Vec<Vec<u8>>);
+
+    impl Transport for Sent {
+        type Error = ();
+
+        fn send(&mut self, frame: &[u8]) -> Result<(), ()> {
+            self.0.push(frame.into());
+            Ok(())
+        }
+    }
+
+    let mut logger = Logger::new(Sent::default(), Formatter3164::default());
+    logger
+        .send(Severity::LOG_ERR, format_args!("retry {}", 3))
+        .unwrap();
+    logger.send(Severity::LOG_DEBUG, "done").unwrap();
+    assert_eq!(
+        logger.transport.0,
+        [&b"<11>main: retry 3"[..], &b"<15>main: done"[..]]
+    );
+}
@@ -0,0 +1,184 @@
+//! Transports over `embedded-nal` network stacks, enabled by the `embedded-nal` feature
+use alloc::vec::Vec;
+use core::net::SocketAddr;
+
+use embedded_nal::{TcpClientStack, UdpClientStack};
+
+use crate::{Framing, Transport};
+
+/// Why a frame wasn't sent
+#[derive(Debug)]
+pub enum NalError<E> {
+    /// the network stack failed
+    Stack(E),
+    /// the TCP connection took none of the frame
+    WriteZero,
+}
+
+/// A UDP socket of an `embedded-nal` stack, connected to its server
+pub struct NalUdp<S: UdpClientStack> {
+    stack: S,
+    socket: S::UdpSocket,
+}
+
+impl<S: UdpClientStack> NalUdp<S> {
+    /// opens a socket of `stack`, connected to `server`
+    pub fn connect(mut stack: S, server: SocketAddr) -> Result<NalUdp<S>, NalError<S::Error>> {
+        let mut socket = stack.socket().map_err(NalError::Stack)?;
+        stack
+            .connect(&mut socket, server)
+            .map_err(NalError::Stack)?;
+        Ok(NalUdp { stack, socket })
+    }
+
+    /// closes the socket, and gives the stack back
+    pub fn close(mut self) -> Result<S, NalError<S::Error>> {
+        self.stack.close(self.socket).map_err(NalError::Stack)?;
+        Ok(self.stack)
+    }
+}
+
+impl<S: UdpClientStack> Transport for NalUdp<S> {
+    type Error = NalError<S::Error>;
+
+    /// Sends `frame` as one datagram, blocking while the stack is busy
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+        nb::block!(self.stack.send(&mut self.socket, frame)).map_err(NalError::Stack)
+    }
+}
+
+/// A TCP connection of an `embedded-nal` stack, every frame being delimited with a framing
+pub struct NalTcp<S: TcpClientStack> {
+    stack: S,
+    socket: S::TcpSocket,
+    framing: Framing,
+}
+
+impl<S: TcpClientStack> NalTcp<S> {
+    /// opens a socket of `stack`, and blocks until it is connected to `server`
+    pub fn connect(
+        mut stack: S,
+        server: SocketAddr,
+        framing: Framing,
+    ) -> Result<NalTcp<S>, NalError<S::Error>> {
+        let mut socket = stack.socket().map_err(NalError::Stack)?;
+        nb::block!(stack.connect(&mut socket, server)).map_err(NalError::Stack)?;
+        Ok(NalTcp {
+            stack,
+            socket,
+            framing,
+        })
+    }
+
+    /// closes the connection, and gives the stack back
+    pub fn close(mut self) -> Result<S, NalError<S::Error>> {
+        self.stack.close(self.socket).map_err(NalError::Stack)?;
+        Ok(self.stack)
+    }
+}
+
+impl<S: TcpClientStack> Transport for NalTcp<S> {
+    type Error = NalError<S::Error>;
+
+    /// Sends `frame`, delimited, blocking until the stack took all of it
+    fn send(&mut self, frame: &[u8]) -> Result<(), Self::Error> {
+        let framed: Vec<u8> = self.framing.encode(frame);
+        let mut sent = 0;
+        while sent < framed.len() {
+            match nb::block!(self.stack.send(&mut self.socket, &framed[sent..])) {
+                Ok(0) => return Err(NalError::WriteZero),
+                Ok(len) => sent += len,
+                Err(e) => return Err(NalError::Stack(e)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_nal_transports() {
+    use alloc::vec;
+    use embedded_nal::{TcpError, TcpErrorKind};
+
+    #[derive(Debug)]
+    struct Closed;
+
+    impl TcpError for Closed {
+        fn kind(&self) -> TcpErrorKind {
+            TcpErrorKind::PipeClosed
+        }
+    }
+
+    /// sends 4 bytes at a time, being busy every other call
+    #[derive(Default)]
+    struct MockStack {
+        sent: Vec<Vec<u8>>,
+        busy: bool,
+    }
+
+    impl UdpClientStack for MockStack {
+        type UdpSocket = ();
+        type Error = Closed;
+
+        fn socket(&mut self) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<(), Closed> {
+            self.sent.push(buffer.into());
+            Ok(())
+        }
+
+        fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<(usize, SocketAddr), Closed> {
+            Err(nb::Error::WouldBlock)
+        }
+
+        fn close(&mut self, _: ()) -> Result<(), Closed> {
+            Ok(())
+        }
+    }
+
+    impl TcpClientStack for MockStack {
+        type TcpSocket = ();
+        type Error = Closed;
+
+        fn socket(&mut self) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Closed> {
+            Ok(())
+        }
+
+        fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Closed> {
+            self.busy = !self.busy;
+            if self.busy {
+                return Err(nb::Error::WouldBlock);
+            }
+            let len = buffer.len().min(4);
+            self.sent.push(buffer[..len].into());
+            Ok(len)
+        }
+
+        fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<usize, Closed> {
+            Err(nb::Error::WouldBlock)
+        }
+
+        fn close(&mut self, _: ()) -> Result<(), Closed> {
+            Ok(())
+        }
+    }
+
+    let server = "192.168.1.10:514".parse().unwrap();
+    let mut udp = NalUdp::connect(MockStack::default(), server).unwrap();
+    udp.send(b"<14>datagram").unwrap();
+    assert_eq!(udp.close().unwrap().sent, vec![b"<14>datagram".to_vec()]);
+
+    let mut tcp = NalTcp::connect(MockStack::default(), server, Framing::OctetCounted).unwrap();
+    tcp.send(b"<14>stream").unwrap();
+    assert_eq!(tcp.close().unwrap().sent.concat(), b"10 <14>stream");
+}
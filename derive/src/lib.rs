@@ -0,0 +1,132 @@
+//! Derive macros for `esp_syslog`.
+//!
+//! `#[derive(StructuredData)]` implements `esp_syslog::SdElement` for a struct with named
+//! fields, writing each field as an SD-PARAM of a single RFC 5424 SD-ELEMENT. The code is
+//! generated at compile time, so no runtime reflection (or serde) is needed.
+//!
+//! ```rust,ignore
+//! use esp_syslog::StructuredData;
+//!
+//! #[derive(StructuredData)]
+//! #[sd(id = "sensor@32473")]
+//! struct Reading {
+//!     temp: f32,
+//!     #[sd(rename = "hum")]
+//!     humidity: u8,
+//!     #[sd(skip)]
+//!     raw: [u8; 16],
+//!     battery: Option<u16>,
+//! }
+//! ```
+//!
+//! Fields are written with their `Display` implementation. `Option` fields are only written
+//! when they hold a value.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr, Type};
+
+#[proc_macro_derive(StructuredData, attributes(sd))]
+pub fn derive_structured_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut sd_id = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("sd")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                sd_id = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `id = \"...\"`"))
+            }
+        })?;
+    }
+    let sd_id = sd_id.ok_or_else(|| {
+        Error::new_spanned(&input.ident, "missing `#[sd(id = \"...\")]` attribute")
+    })?;
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "`StructuredData` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`StructuredData` can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut writes = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut name = ident.to_string();
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("sd")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"` or `skip`"))
+                }
+            })?;
+        }
+        if skip {
+            continue;
+        }
+
+        writes.push(if is_option(&field.ty) {
+            quote! {
+                if let ::std::option::Option::Some(ref value) = self.#ident {
+                    ::esp_syslog::write_sd_param(w, #name, value)?;
+                }
+            }
+        } else {
+            quote! {
+                ::esp_syslog::write_sd_param(w, #name, &self.#ident)?;
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::esp_syslog::SdElement for #ident #ty_generics #where_clause {
+            const SD_ID: &'static str = #sd_id;
+
+            fn write_params<W: ::std::io::Write>(&self, w: &mut W) -> ::std::io::Result<()> {
+                #(#writes)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
@@ -0,0 +1,45 @@
+use esp_syslog::{Formatter5424, LogFormat, SdElement, Severity, StructuredData};
+
+#[derive(StructuredData)]
+#[sd(id = "sensor@32473")]
+struct Reading {
+    temp: f32,
+    #[sd(rename = "hum")]
+    humidity: u8,
+    #[sd(skip)]
+    #[allow(dead_code)]
+    raw: [u8; 4],
+    battery: Option<u16>,
+}
+
+/// returns the frame `reading` is sent in, from its SD-ELEMENT on
+fn structured_data(reading: &Reading) -> String {
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(&mut buf, Severity::LOG_INFO, (1, reading, "reading"))
+        .unwrap();
+    let frame = String::from_utf8(buf).unwrap();
+    frame[frame.find('[').unwrap()..].to_string()
+}
+
+#[test]
+fn test_derive_structured_data() {
+    assert_eq!(Reading::SD_ID, "sensor@32473");
+
+    let mut reading = Reading {
+        temp: 23.5,
+        humidity: 40,
+        raw: [0xde, 0xad, 0xbe, 0xef],
+        battery: Some(87),
+    };
+    assert_eq!(
+        structured_data(&reading),
+        "[sensor@32473 temp=\"23.5\" hum=\"40\" battery=\"87\"] reading"
+    );
+
+    reading.battery = None;
+    assert_eq!(
+        structured_data(&reading),
+        "[sensor@32473 temp=\"23.5\" hum=\"40\"] reading"
+    );
+}
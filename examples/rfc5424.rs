@@ -1,18 +1,17 @@
 extern crate esp_syslog;
 
 use std::collections::HashMap;
-use esp_syslog::{Facility, Formatter5424, TcpStream, LoggerBackend, BufWriter};
+use esp_syslog::{Facility, Formatter5424};
 
 fn main() {
     let formatter = Formatter5424 {
         facility: Facility::LOG_USER,
         process: "myprogram".into(),
-        pid: 0,
+        pid: None,
+        ..Default::default()
     };
 
-    let tcp_server = TcpStream::connect(("127.0.0.1", 601)).map(|s| LoggerBackend::Tcp(BufWriter::new(s)));
-
-    match esp_syslog::tcp(formatter, tcp_server) {
+    match esp_syslog::tcp(formatter, ("127.0.0.1", 601)) {
         Err(e) => println!("impossible to connect to syslog: {:?}", e),
         Ok(mut writer) => {
             writer
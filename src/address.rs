@@ -0,0 +1,112 @@
+//! Resolution of server names to all of their addresses, IPv6 and IPv4 ones
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use errors::*;
+
+/// Which addresses of a server name are tried first, see `AddressPreference::resolve`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AddressPreference {
+    /// the order of the resolver, which sorts them by RFC 6724 where it can
+    #[default]
+    Any,
+    /// IPv4 addresses first, e.g. on a network whose IPv6 routing is unreliable
+    PreferIpv4,
+    /// IPv6 addresses first
+    PreferIpv6,
+}
+
+impl AddressPreference {
+    /// returns every address `server` resolves to, in the preferred order
+    ///
+    /// The functions connecting to a server (`tcp`, `udp`...) try its addresses in turn until
+    /// one works, in the resolver's order; pass them the slice returned here to change it:
+    ///
+    /// ```no_run
+    /// # use esp_syslog::{AddressPreference, Formatter3164};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let formatter = Formatter3164::default();
+    /// let servers = AddressPreference::PreferIpv6.resolve("logs.lan:601")?;
+    /// let logger = esp_syslog::tcp(formatter, &servers[..])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve<T: ToSocketAddrs>(self, server: T) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs: Vec<SocketAddr> = server.to_socket_addrs()?.collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the server address resolves to nothing",
+            ));
+        }
+        self.sort(&mut addrs);
+        Ok(addrs)
+    }
+
+    /// puts the preferred addresses of `addrs` first, keeping their order otherwise
+    pub fn sort(self, addrs: &mut [SocketAddr]) {
+        match self {
+            AddressPreference::Any => {}
+            AddressPreference::PreferIpv4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+            AddressPreference::PreferIpv6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+        }
+    }
+}
+
+/// returns the first address `server` resolves to
+pub(crate) fn resolve_first<T: ToSocketAddrs>(server: T) -> Result<SocketAddr> {
+    AddressPreference::Any
+        .resolve(server)
+        .map(|addrs| addrs[0])
+        .map_err(Error::Resolution)
+}
+
+/// returns the result of `connect` for the first of `addrs` it succeeds with, or the last
+/// error
+pub(crate) fn first_ok<T, C>(addrs: &[SocketAddr], mut connect: C) -> io::Result<T>
+where
+    C: FnMut(SocketAddr) -> io::Result<T>,
+{
+    let mut last = io::Error::new(io::ErrorKind::InvalidInput, "no server address");
+    for &addr in addrs {
+        match connect(addr) {
+            Ok(connected) => return Ok(connected),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+#[test]
+fn test_address_preference() {
+    let v4: SocketAddr = "192.0.2.1:514".parse().unwrap();
+    let v6: SocketAddr = "[2001:db8::1]:514".parse().unwrap();
+    let v4_bis: SocketAddr = "192.0.2.2:514".parse().unwrap();
+    let servers = [v6, v4, v4_bis];
+
+    assert_eq!(
+        AddressPreference::Any.resolve(&servers[..]).unwrap(),
+        servers
+    );
+    assert_eq!(
+        AddressPreference::PreferIpv4.resolve(&servers[..]).unwrap(),
+        [v4, v4_bis, v6]
+    );
+    assert_eq!(
+        AddressPreference::PreferIpv6.resolve(&servers[..]).unwrap(),
+        servers
+    );
+    assert!(AddressPreference::Any.resolve(&[][..]).is_err());
+
+    let mut tried = Vec::new();
+    let connected = first_ok(&servers, |addr| {
+        tried.push(addr);
+        if addr.is_ipv4() {
+            Ok(addr)
+        } else {
+            Err(io::ErrorKind::AddrNotAvailable.into())
+        }
+    });
+    assert_eq!(connected.unwrap(), v4);
+    assert_eq!(tried, [v6, v4]);
+}
@@ -0,0 +1,113 @@
+//! Fields added to every message of a `BasicLogger` by an annotator
+use std::fmt::Display;
+use std::sync::Arc;
+
+use log::kv::{self, Key, Source, Value, VisitSource};
+use log::Level;
+
+use errors::*;
+use installed;
+
+/// A function adding fields to every message, see `BasicLogger::set_annotator`
+pub type Annotator = Arc<dyn Fn(&mut MessageFields) + Send + Sync>;
+
+/// The fields of a message being logged, sent as ` key=value` pairs by `Formatter3164`
+pub struct MessageFields<'a> {
+    level: Level,
+    target: &'a str,
+    fields: Vec<(String, String)>,
+}
+
+impl<'a> MessageFields<'a> {
+    pub(crate) fn new(level: Level, target: &'a str) -> Self {
+        MessageFields {
+            level,
+            target,
+            fields: Vec::new(),
+        }
+    }
+
+    /// the level of the message
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// the target of the message, e.g. the module path of the call site
+    pub fn target(&self) -> &str {
+        self.target
+    }
+
+    /// attaches `name=value` to the message
+    pub fn add<V: Display>(&mut self, name: &str, value: V) {
+        self.fields.push((name.to_string(), value.to_string()));
+    }
+
+    /// attaches the key/values of a `log` record, e.g. `info!(temp = 23.5; "reading")`
+    pub(crate) fn add_key_values(&mut self, source: &dyn Source) {
+        let _ = source.visit(self);
+    }
+
+    pub(crate) fn pairs(&self) -> Vec<(&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (&name[..], &value[..]))
+            .collect()
+    }
+}
+
+impl<'a, 'kvs> VisitSource<'kvs> for MessageFields<'a> {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> ::std::result::Result<(), kv::Error> {
+        self.add(key.as_str(), value);
+        Ok(())
+    }
+}
+
+/// sets the annotator of the logger installed by `init` or one of the `init_*` functions, see
+/// `BasicLogger::set_annotator`
+pub fn set_annotator<A: Fn(&mut MessageFields) + Send + Sync + 'static>(
+    annotator: A,
+) -> Result<()> {
+    installed()
+        .ok_or(Error::NotInstalled)?
+        .set_annotator(annotator);
+    Ok(())
+}
+
+#[test]
+fn test_annotator() {
+    use log::{LevelFilter, Log, Record};
+    use {
+        BasicLogger, Formatter3164, LimitedStorage, Logger, LoggerBackend, MemoryStorage,
+        SpoolStorage,
+    };
+
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let logger = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ));
+    logger.set_annotator(|fields| {
+        fields.add("slot", "ota_0");
+        if fields.target() == "power" {
+            fields.add("battery", 3.7);
+        }
+    });
+    log::set_max_level(LevelFilter::Trace);
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("power")
+            .args(format_args!("low"))
+            .build(),
+    );
+
+    let frame = match logger.logger.lock().backend {
+        LoggerBackend::Memory(ref mut frames) => frames.front().unwrap().unwrap(),
+        _ => unreachable!(),
+    };
+    assert!(frame.ends_with(b"main[0]: low slot=ota_0 battery=3.7"));
+}
@@ -0,0 +1,265 @@
+//! Tokio front-end, enabled by the `async` feature
+//!
+//! `AsyncLogger` formats messages like `Logger`, then writes them to a Tokio UDP socket or TCP
+//! stream without blocking the executor: every method returns a future resolving once the
+//! frame is handed over to the network stack.
+//!
+//! ```no_run,edition2018
+//! # use esp_syslog::Formatter3164;
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! # let server = "192.168.1.10:601".parse()?;
+//! let mut logger = esp_syslog::async_tcp(Formatter3164::default(), server).await?;
+//! logger.info("connected").await?;
+//! # Ok(())
+//! # }
+//! # fn main() {}
+//! ```
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use esp_syslog_core::Framing;
+use tokio::io::AsyncWrite;
+use tokio::net::{TcpStream, UdpSocket};
+
+use errors::*;
+use format::{LogFormat, Severity};
+
+/// Where an `AsyncLogger` writes its frames
+pub enum AsyncBackend {
+    /// A UDP socket connected to its server
+    Udp(UdpSocket),
+    /// A TCP connection, delimiting the frames with the framing if there is one
+    Tcp(TcpStream, Option<Framing>),
+}
+
+/// A logger sending messages through a Tokio socket, see the module documentation
+pub struct AsyncLogger<F> {
+    pub formatter: F,
+    pub backend: AsyncBackend,
+}
+
+impl<F> AsyncLogger<F> {
+    pub fn new(backend: AsyncBackend, formatter: F) -> Self {
+        AsyncLogger { formatter, backend }
+    }
+
+    /// formats `message` right away, and returns the future sending it
+    pub fn send<T>(&mut self, severity: Severity, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        let mut frame = Vec::new();
+        let error = self.formatter.format(&mut frame, severity, message).err();
+        if let AsyncBackend::Tcp(_, Some(framing)) = self.backend {
+            frame = framing.encode(&frame);
+        }
+        SendFrame {
+            backend: &mut self.backend,
+            frame,
+            written: 0,
+            error,
+        }
+    }
+
+    pub fn emerg<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_EMERG, message)
+    }
+
+    pub fn alert<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_ALERT, message)
+    }
+
+    pub fn crit<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_CRIT, message)
+    }
+
+    pub fn err<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_ERR, message)
+    }
+
+    pub fn warning<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_WARNING, message)
+    }
+
+    pub fn notice<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_NOTICE, message)
+    }
+
+    pub fn info<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_INFO, message)
+    }
+
+    pub fn debug<T>(&mut self, message: T) -> SendFrame<'_>
+    where
+        F: LogFormat<T>,
+    {
+        self.send(Severity::LOG_DEBUG, message)
+    }
+
+    /// returns the future flushing the TCP stream, UDP sockets having nothing to flush
+    pub fn flush(&mut self) -> Flush<'_> {
+        Flush {
+            backend: &mut self.backend,
+        }
+    }
+}
+
+/// The future returned by the sending methods of `AsyncLogger`
+pub struct SendFrame<'a> {
+    backend: &'a mut AsyncBackend,
+    frame: Vec<u8>,
+    written: usize,
+    error: Option<Error>,
+}
+
+impl<'a> Future for SendFrame<'a> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Err(error));
+        }
+        let sent = match *this.backend {
+            AsyncBackend::Udp(ref socket) => match socket.poll_send(cx, &this.frame) {
+                Poll::Ready(sent) => sent.map(|_| ()),
+                Poll::Pending => return Poll::Pending,
+            },
+            AsyncBackend::Tcp(ref mut stream, _) => loop {
+                if this.written == this.frame.len() {
+                    break Ok(());
+                }
+                match Pin::new(&mut *stream).poll_write(cx, &this.frame[this.written..]) {
+                    Poll::Ready(Ok(0)) => break Err(io::ErrorKind::WriteZero.into()),
+                    Poll::Ready(Ok(len)) => this.written += len,
+                    Poll::Ready(Err(e)) => break Err(e),
+                    Poll::Pending => return Poll::Pending,
+                }
+            },
+        };
+        Poll::Ready(sent.map_err(Error::from))
+    }
+}
+
+/// The future returned by `AsyncLogger::flush`
+pub struct Flush<'a> {
+    backend: &'a mut AsyncBackend,
+}
+
+impl<'a> Future for Flush<'a> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        match *self.get_mut().backend {
+            AsyncBackend::Udp(_) => Poll::Ready(Ok(())),
+            AsyncBackend::Tcp(ref mut stream, _) => Pin::new(stream)
+                .poll_flush(cx)
+                .map(|flushed| flushed.map_err(Error::from)),
+        }
+    }
+}
+
+/// returns an async UDP logger connecting `local` and `server`
+///
+/// The socket is set up right away, from within a Tokio runtime: outside of one, this panics.
+pub fn async_udp<T: ToSocketAddrs, F>(formatter: F, local: T, server: T) -> Result<AsyncLogger<F>> {
+    ::std::net::UdpSocket::bind(local)
+        .and_then(|socket| socket.connect(server).map(|()| socket))
+        .and_then(|socket| socket.set_nonblocking(true).map(|()| socket))
+        .and_then(UdpSocket::from_std)
+        .map_err(Error::from)
+        .map(|socket| AsyncLogger::new(AsyncBackend::Udp(socket), formatter))
+}
+
+/// returns the future connecting an async TCP logger to `server`, without framing
+pub fn async_tcp<F>(formatter: F, server: SocketAddr) -> Connect<F> {
+    Connect {
+        formatter: Some(formatter),
+        connecting: Box::pin(TcpStream::connect(server)),
+    }
+}
+
+/// The future returned by `async_tcp`
+pub struct Connect<F> {
+    formatter: Option<F>,
+    connecting: Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>,
+}
+
+// The formatter is never pinned
+impl<F> Unpin for Connect<F> {}
+
+impl<F> Future for Connect<F> {
+    type Output = Result<AsyncLogger<F>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<AsyncLogger<F>>> {
+        let this = self.get_mut();
+        match this.connecting.as_mut().poll(cx) {
+            Poll::Ready(Ok(stream)) => {
+                let formatter = this
+                    .formatter
+                    .take()
+                    .expect("Connect polled after completion");
+                Poll::Ready(Ok(AsyncLogger::new(
+                    AsyncBackend::Tcp(stream, None),
+                    formatter,
+                )))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(Error::from(e))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[test]
+fn test_async_logger() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use Formatter3164;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap();
+
+    let mut logger = runtime
+        .block_on(async_tcp(Formatter3164::default(), server))
+        .unwrap();
+    if let AsyncBackend::Tcp(_, ref mut framing) = logger.backend {
+        *framing = Some(Framing::OctetCounted);
+    }
+    runtime.block_on(logger.info("async")).unwrap();
+    runtime.block_on(logger.flush()).unwrap();
+    drop(logger);
+
+    let (mut accepted, _) = listener.accept().unwrap();
+    let mut received = String::new();
+    accepted.read_to_string(&mut received).unwrap();
+    assert!(received.starts_with(&format!("{} <14>", received.len() - 3)));
+    assert!(received.ends_with("main[0]: async"));
+}
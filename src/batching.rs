@@ -0,0 +1,160 @@
+//! Coalescing of several messages into one write, to wake the radio less often
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use esp_syslog_core::Framing;
+
+use LoggerBackend;
+
+/// The largest UDP payload fitting a 1500-byte Ethernet or Wi-Fi MTU over IPv4
+pub const UDP_BATCH_SIZE: usize = 1472;
+
+/// A backend delimiting the frames and sending them together, in as few writes as possible
+///
+/// Frames are line feed terminated (`Framing::NonTransparent`) by default, which most servers
+/// split back into messages, whether they arrive over TCP or in one UDP datagram. The batch is
+/// written as soon as the next frame wouldn't fit in `max_size` bytes, when a frame is written
+/// after the oldest one waited `max_delay`, and when the backend is flushed; a frame longer
+/// than `max_size` is sent alone. The backend has no timer, so flush it before sleeping.
+///
+/// A failed write drops the whole batch, as it would have dropped a single frame.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use esp_syslog::{Formatter3164, UDP_BATCH_SIZE};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (formatter, local, server) = (Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514");
+/// let backend = esp_syslog::udp(formatter, local, server)?
+///     .backend
+///     .with_batching(UDP_BATCH_SIZE, Duration::from_secs(2));
+/// # Ok(())
+/// # }
+/// ```
+pub struct BatchingBackend<W> {
+    pub inner: W,
+    framing: Framing,
+    max_size: usize,
+    max_delay: Duration,
+    batch: Vec<u8>,
+    /// when the oldest frame of the batch was written
+    oldest: Option<Instant>,
+}
+
+impl<W: Write> BatchingBackend<W> {
+    pub fn new(inner: W, max_size: usize, max_delay: Duration) -> Self {
+        BatchingBackend {
+            inner,
+            framing: Framing::NonTransparent,
+            max_size,
+            max_delay,
+            batch: Vec::with_capacity(max_size),
+            oldest: None,
+        }
+    }
+
+    /// delimits the frames with `framing`, e.g. `OctetCounted` for a server expecting it
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// returns the number of bytes waiting to be sent
+    pub fn pending(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// takes the frames of the batch, without their delimiters
+    pub(crate) fn take_batch(&mut self) -> Vec<Vec<u8>> {
+        self.oldest = None;
+        let frames = self.framing.split(&self.batch);
+        self.batch.clear();
+        frames
+    }
+
+    /// writes the batch, if any, with one write to the inner backend
+    pub(crate) fn send_batch(&mut self) -> io::Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        self.oldest = None;
+        let result = self.inner.write_all(&self.batch);
+        self.batch.clear();
+        result
+    }
+}
+
+impl<W: Write> Write for BatchingBackend<W> {
+    /// Adds `frame` to the batch, writing the batch first if the frame doesn't fit in it
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let frame_delimited = self.framing.encode(frame);
+        if self.batch.len() + frame_delimited.len() > self.max_size {
+            self.send_batch()?;
+        }
+        self.batch.extend_from_slice(&frame_delimited);
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+        if self.batch.len() >= self.max_size || oldest.elapsed() >= self.max_delay {
+            self.send_batch()?;
+        }
+        Ok(frame.len())
+    }
+
+    /// Writes the batch, then flushes the inner backend
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_batch()?;
+        self.inner.flush()
+    }
+}
+
+impl BatchingBackend<LoggerBackend> {
+    /// writes `frame` to the inner backend right away, ahead of the batch
+    pub(crate) fn write_now(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.inner.write_now(&self.framing.encode(frame))
+    }
+}
+
+impl LoggerBackend {
+    /// wraps the backend into a `BatchingBackend` sending up to `max_size` bytes of frames at
+    /// once, none of them waiting more than `max_delay` while frames keep coming
+    pub fn with_batching(self, max_size: usize, max_delay: Duration) -> LoggerBackend {
+        LoggerBackend::Batching(Box::new(BatchingBackend::new(self, max_size, max_delay)))
+    }
+}
+
+#[test]
+fn test_batching_backend() {
+    /// records every write separately
+    #[derive(Default)]
+    struct Writes(Vec<Vec<u8>>);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut backend = BatchingBackend::new(Writes::default(), 20, Duration::from_secs(60));
+    backend.write_all(b"<14>one").unwrap();
+    backend.write_all(b"<14>two").unwrap();
+    assert!(backend.inner.0.is_empty());
+    assert_eq!(backend.pending(), 16);
+    backend.write_all(b"<14>three").unwrap();
+    backend.write_all(b"<14>a long frame").unwrap();
+    backend.flush().unwrap();
+    assert_eq!(
+        backend.inner.0,
+        [
+            &b"<14>one\n<14>two\n"[..],
+            b"<14>three\n",
+            b"<14>a long frame\n"
+        ]
+    );
+
+    backend.set_framing(Framing::OctetCounted);
+    backend.max_delay = Duration::from_secs(0);
+    backend.write_all(b"<14>now").unwrap();
+    assert_eq!(backend.inner.0[3], b"7 <14>now");
+}
@@ -0,0 +1,190 @@
+//! syslog-send: sends test messages through esp_syslog, to check collector configuration
+//!
+//! Built with the host-only `cli` feature:
+//!
+//! ```text
+//! cargo run --features cli --bin syslog-send -- --proto tcp --format 5424 127.0.0.1:601 hello
+//! ```
+extern crate esp_syslog;
+
+use std::env;
+use std::io::Write;
+use std::process;
+
+use esp_syslog::{
+    Facility, Formatter3164, Formatter5424, LogFormat, Logger, LoggerBackend, Severity, WithFields,
+};
+
+const USAGE: &str = "usage: syslog-send [options] <server> <message>...
+
+options:
+    --proto <udp|tcp|unix>      transport, defaults to udp (unix ignores <server>)
+    --format <3164|5424>        message format, defaults to 3164
+    --severity <name>           emerg, alert, crit, err, warning, notice, info (default) or debug
+    --facility <name>           defaults to user
+    --process <name>            defaults to syslog-send
+    --pid <pid>                 defaults to the process id
+    --hostname <name>           defaults to the system hostname, if known";
+
+struct Options {
+    proto: String,
+    format: String,
+    severity: Severity,
+    facility: Facility,
+    process: String,
+    pid: u32,
+    hostname: Option<String>,
+    server: String,
+    message: String,
+}
+
+fn parse_severity(name: &str) -> Option<Severity> {
+    Some(match &name.to_lowercase()[..] {
+        "emerg" => Severity::LOG_EMERG,
+        "alert" => Severity::LOG_ALERT,
+        "crit" => Severity::LOG_CRIT,
+        "err" | "error" => Severity::LOG_ERR,
+        "warning" | "warn" => Severity::LOG_WARNING,
+        "notice" => Severity::LOG_NOTICE,
+        "info" => Severity::LOG_INFO,
+        "debug" => Severity::LOG_DEBUG,
+        _ => return None,
+    })
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        proto: "udp".to_string(),
+        format: "3164".to_string(),
+        severity: Severity::LOG_INFO,
+        facility: Facility::LOG_USER,
+        process: "syslog-send".to_string(),
+        pid: process::id(),
+        hostname: env::var("HOSTNAME").ok(),
+        server: String::new(),
+        message: String::new(),
+    };
+
+    let mut positional = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--help" {
+            return Err(String::new());
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", arg))?;
+        match &arg[..] {
+            "--proto" => options.proto = value,
+            "--format" => options.format = value,
+            "--severity" => {
+                options.severity =
+                    parse_severity(&value).ok_or_else(|| format!("unknown severity {}", value))?
+            }
+            "--facility" => {
+                options.facility = value
+                    .parse()
+                    .map_err(|()| format!("unknown facility {}", value))?
+            }
+            "--process" => options.process = value,
+            "--pid" => {
+                options.pid = value
+                    .parse()
+                    .map_err(|_| format!("invalid pid {}", value))?
+            }
+            "--hostname" => options.hostname = Some(value),
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    if options.proto != "unix" {
+        if positional.is_empty() {
+            return Err("missing server".to_string());
+        }
+        options.server = positional.remove(0);
+    }
+    if positional.is_empty() {
+        return Err("missing message".to_string());
+    }
+    options.message = positional.join(" ");
+    Ok(options)
+}
+
+fn connect<F>(options: &Options, formatter: F) -> esp_syslog::Result<Logger<LoggerBackend, F>> {
+    match &options.proto[..] {
+        "udp" => esp_syslog::udp(formatter, "0.0.0.0:0", &options.server[..]),
+        "tcp" => esp_syslog::tcp(formatter, &options.server[..]),
+        #[cfg(all(unix, not(target_os = "espidf")))]
+        "unix" => esp_syslog::unix(formatter),
+        _ => Err(esp_syslog::Error::UnsupportedPlatform),
+    }
+}
+
+fn send<F>(options: &Options, formatter: F) -> esp_syslog::Result<()>
+where
+    F: for<'a> LogFormat<WithFields<'a, &'a str>>,
+{
+    let mut logger = connect(options, formatter)?;
+    let message = WithFields {
+        message: &options.message[..],
+        fields: &[],
+    };
+    logger
+        .formatter
+        .format(&mut logger.backend, options.severity, message)?;
+    logger.backend.flush()?;
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("syslog-send: {}", e);
+            }
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Some(ref hostname) = options.hostname {
+        unsafe { esp_syslog::set_hostname(hostname.clone()) };
+    }
+
+    let result = match &options.format[..] {
+        "3164" => send(
+            &options,
+            Formatter3164 {
+                facility: options.facility,
+                process: options.process.clone(),
+                pid: Some(options.pid),
+                clock: Default::default(),
+                ..Default::default()
+            },
+        ),
+        "5424" => send(
+            &options,
+            Formatter5424 {
+                facility: options.facility,
+                process: options.process.clone(),
+                pid: Some(options.pid),
+                clock: Default::default(),
+                ..Default::default()
+            },
+        ),
+        format => {
+            eprintln!("syslog-send: unknown format {}", format);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("syslog-send: could not send message: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,81 @@
+//! Local side of the UDP sockets: source port, and interface on a device with several
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Where the socket of a UDP logger is bound, see `udp_bound`
+///
+/// By default the stack picks the source port, and the interface by its route to the server.
+/// On an ESP32 with both the Wi-Fi station and access point up, binding to the address of one
+/// of them has lwIP send the messages from that interface:
+///
+/// ```no_run
+/// # use std::net::IpAddr;
+/// # use esp_syslog::{Formatter3164, UdpBind, WifiInterface};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let formatter = Formatter3164::default();
+/// let bind = UdpBind {
+///     port: 5140,
+///     address: WifiInterface::Station.address().map(IpAddr::V4),
+/// };
+/// let logger = esp_syslog::udp_bound(formatter, bind, "192.168.1.10:514")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UdpBind {
+    /// the source port, 0 letting the stack pick one
+    pub port: u16,
+    /// the address of the interface to send from, any of the family of the server by default
+    pub address: Option<IpAddr>,
+}
+
+impl UdpBind {
+    /// returns the address to bind to for sending to `server`
+    pub fn local_addr(&self, server: &SocketAddr) -> SocketAddr {
+        let any = match *server {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        SocketAddr::new(self.address.unwrap_or(any), self.port)
+    }
+}
+
+#[test]
+#[cfg(not(feature = "host-sim"))]
+fn test_udp_bound() {
+    use std::net::UdpSocket;
+    use {Formatter3164, LoggerBackend};
+
+    let v6: SocketAddr = "[2001:db8::1]:514".parse().unwrap();
+    assert_eq!(
+        UdpBind::default().local_addr(&v6),
+        "[::]:0".parse::<SocketAddr>().unwrap()
+    );
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // A port that was free a moment ago
+    let port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let bind = UdpBind {
+        port,
+        address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    };
+    let mut logger =
+        ::udp_bound(Formatter3164::default(), bind, server.local_addr().unwrap()).unwrap();
+    match logger.backend {
+        LoggerBackend::UdpConnected(ref socket) => {
+            assert_eq!(
+                socket.local_addr().unwrap(),
+                SocketAddr::from(([127, 0, 0, 1], port))
+            )
+        }
+        _ => panic!("not a connected UDP logger"),
+    }
+    logger.info("bound").unwrap();
+    let mut buf = [0; 256];
+    let (len, from) = server.recv_from(&mut buf).unwrap();
+    assert_eq!(from.port(), port);
+    assert!(buf[..len].ends_with(b"main[0]: bound"));
+}
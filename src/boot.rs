@@ -0,0 +1,33 @@
+//! Identifier of the current boot, telling the messages of successive boots apart
+use std::sync::OnceLock;
+
+/// returns the ID of the current boot, 8 random hex digits generated on first use
+///
+/// Sent as the `boot` field of every message by `BasicLogger::with_boot_id`, so the collector
+/// can group the messages of a boot and spot reboots even when the clock isn't set yet.
+pub fn boot_id() -> &'static str {
+    static BOOT_ID: OnceLock<String> = OnceLock::new();
+    BOOT_ID.get_or_init(|| format!("{:08x}", random()))
+}
+
+#[cfg(target_os = "espidf")]
+pub(crate) fn random() -> u32 {
+    unsafe { esp_idf_svc::sys::esp_random() }
+}
+
+/// Seeded by the random keys of the standard library hash maps, and the time
+#[cfg(not(target_os = "espidf"))]
+pub(crate) fn random() -> u32 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::BuildHasher;
+    use std::time::SystemTime;
+
+    RandomState::new().hash_one(SystemTime::now()) as u32
+}
+
+#[test]
+fn test_boot_id() {
+    assert_eq!(boot_id().len(), 8);
+    assert!(boot_id().chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(boot_id(), boot_id());
+}
@@ -0,0 +1,179 @@
+//! Logging that never waits for the network, with a background flush thread
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use log::{Log, Metadata, Record};
+
+use format::{LogFormat, Severity};
+use sync::Lock;
+use {Formatter3164, LimitedStorage, Logger, LoggerBackend, MemoryStorage, Overflow, SpoolStorage};
+
+/// how long the flush thread waits, unless woken up, before retrying a failed write or
+/// checking whether the `BufferedLogger` was dropped
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A `log` implementation formatting messages into a bounded buffer, sent by its own thread
+///
+/// Logging formats the message and queues it, the flush thread being woken up to send it, so
+/// time-critical code only ever pays for the formatting. The buffer holds up to `capacity`
+/// bytes of frames; when it is full, `overflow` tells which messages are dropped. A frame
+/// that can't be sent stays at the front of the buffer, and is retried every second. The thread
+/// exits within a second of the `BufferedLogger` being dropped.
+pub struct BufferedLogger {
+    formatter: Formatter3164,
+    queue: Arc<Lock<LimitedStorage<MemoryStorage>>>,
+    flusher: Thread,
+    /// set by `shutdown`, and cleared by the flush thread once it tried to close the backend,
+    /// or by `shutdown` when it times out
+    close: Arc<AtomicBool>,
+}
+
+impl BufferedLogger {
+    /// takes over the backend of `logger`, sent to by a new thread, and its formatter
+    pub fn new(
+        logger: Logger<LoggerBackend, Formatter3164>,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> BufferedLogger {
+        let (backend, formatter) = logger.into_parts();
+        let storage = LimitedStorage::new(MemoryStorage::new(), capacity).with_overflow(overflow);
+        let queue = Arc::new(Lock::new(storage));
+        let weak = Arc::downgrade(&queue);
+        let close = Arc::new(AtomicBool::new(false));
+        let closed = close.clone();
+        let flusher = thread::Builder::new()
+            .name("syslog-flush".into())
+            .spawn(move || flush_loop(weak, backend, &closed))
+            .expect("failed to spawn the syslog flush thread")
+            .thread()
+            .clone();
+        BufferedLogger {
+            formatter,
+            queue,
+            flusher,
+            close,
+        }
+    }
+
+    /// returns the number of messages dropped because the buffer was full
+    pub fn dropped(&self) -> usize {
+        self.queue.lock().dropped()
+    }
+
+    /// waits up to `timeout` for the flush thread to send the queued messages and close the
+    /// connection, see `LoggerBackend::close`, and returns the number of messages dropped,
+    /// counting those still queued
+    ///
+    /// Meant to be called right before a deep sleep. Messages logged afterwards are sent as
+    /// before, a reconnecting TCP backend connecting again.
+    pub fn shutdown(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        self.close.store(true, Ordering::SeqCst);
+        self.flusher.unpark();
+        while self.close.load(Ordering::SeqCst) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Not left set after a timeout, or the flush thread would close the connection after
+        // the next message logged
+        self.close.store(false, Ordering::SeqCst);
+        let queue = self.queue.lock();
+        queue.dropped() + queue.len()
+    }
+}
+
+/// sends the queued frames until the `BufferedLogger` is dropped
+fn flush_loop(
+    queue: Weak<Lock<LimitedStorage<MemoryStorage>>>,
+    mut backend: LoggerBackend,
+    close: &AtomicBool,
+) {
+    while let Some(queue) = queue.upgrade() {
+        let drained = loop {
+            // The lock is only held to take the frame, not while it is sent
+            let frame = match queue.lock().front() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break true,
+                Err(_) => break false,
+            };
+            if backend.write_all(&frame).is_err() {
+                break false;
+            }
+            let _ = queue.lock().pop_front();
+        };
+        if drained && close.load(Ordering::SeqCst) {
+            // cleared even when closing failed, `shutdown` not waiting for a retry
+            let _ = backend.close();
+            close.store(false, Ordering::SeqCst);
+        } else {
+            let _ = backend.flush();
+        }
+        drop(queue);
+        thread::park_timeout(RETRY_INTERVAL);
+    }
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.level() <= log::STATIC_MAX_LEVEL
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut frame = Vec::new();
+        let severity = Severity::from(record.level());
+        if self
+            .formatter
+            .format(&mut frame, severity, record.args())
+            .is_ok()
+        {
+            let _ = self.queue.lock().push(&frame);
+            self.flusher.unpark();
+        }
+    }
+
+    fn flush(&self) {
+        self.flusher.unpark();
+    }
+}
+
+#[test]
+fn test_buffered_logger() {
+    use std::net::UdpSocket;
+
+    use log::{Level, LevelFilter};
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(server.local_addr().unwrap()).unwrap();
+    let logger = BufferedLogger::new(
+        Logger::new(
+            LoggerBackend::UdpConnected(socket),
+            Formatter3164::default(),
+        ),
+        1024,
+        Overflow::DropNewest,
+    );
+    log::set_max_level(LevelFilter::Trace);
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("in the background"))
+            .build(),
+    );
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: in the background"));
+    assert_eq!(logger.dropped(), 0);
+    assert_eq!(logger.shutdown(Duration::from_secs(1)), 0);
+    assert!(!logger.close.load(Ordering::SeqCst));
+
+    // timing out doesn't leave the connection to be closed later
+    assert_eq!(logger.shutdown(Duration::ZERO), 0);
+    assert!(!logger.close.load(Ordering::SeqCst));
+}
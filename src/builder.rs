@@ -0,0 +1,376 @@
+//! Builder-style construction of a logger
+//!
+//! `Syslog::builder()` gathers the options the free functions (`udp`, `tcp`, `tcp_with_framing`,
+//! ...) take, or that have to be set on the logger they return, in one place:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use esp_syslog::{Facility, Framing, Syslog, Timeouts};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let logger = Syslog::builder()
+//!     .facility(Facility::LOG_USER)
+//!     .hostname("esp32")
+//!     .transport_tcp("192.168.1.10:601")
+//!     .framing(Framing::OctetCounted)
+//!     .timeouts(Timeouts { connect: Some(Duration::from_secs(2)), ..Default::default() })
+//!     .format_5424()
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs};
+#[cfg(all(unix, not(target_os = "espidf")))]
+use std::path::PathBuf;
+
+use errors::*;
+use format::{Oversize, Severity};
+#[cfg(feature = "timestamps")]
+use Clock;
+use {
+    store_hostname, tcp_lazy_backend, AddressPreference, BasicLogger, BufferedLogger, Facility,
+    FlushPolicy, Formatter3164, Formatter5424, Framing, HostnameSource, LimitedStorage, Logger,
+    LoggerBackend, MemoryStorage, Overflow, SpoolBackend, SpoolStorage, Timeouts, Transport,
+    UdpBind,
+};
+
+/// The entry point of the builder API, see `SyslogBuilder`
+pub struct Syslog;
+
+impl Syslog {
+    /// returns a builder with the default options: `Formatter3164::default()`, and messages
+    /// kept in RAM until a transport is chosen
+    pub fn builder() -> SyslogBuilder<Formatter3164> {
+        SyslogBuilder {
+            formatter: Formatter3164::default(),
+            hostname: None,
+            transport: Destination::Memory(4096),
+            address_preference: AddressPreference::Any,
+            udp_bind: UdpBind::default(),
+            lazy_buffer: None,
+            framing: None,
+            timeouts: Timeouts::default(),
+            flush_policy: FlushPolicy::default(),
+            min_severity: Severity::LOG_DEBUG,
+            fire_and_forget: false,
+            spool: None,
+            filter: None,
+            format: PhantomData,
+        }
+    }
+}
+
+enum Destination {
+    Udp(io::Result<Vec<SocketAddr>>),
+    Tcp(io::Result<Vec<SocketAddr>>),
+    #[cfg(all(unix, not(target_os = "espidf")))]
+    Unix(PathBuf),
+    Memory(usize),
+    Custom(Box<dyn Transport + Send>),
+}
+
+/// Options of a logger formatting messages with `F`, see `Syslog::builder`
+///
+/// Addresses are resolved when given, but any error is only returned by `build`.
+pub struct SyslogBuilder<F> {
+    /// the formatter options, whatever the format
+    formatter: Formatter3164,
+    hostname: Option<String>,
+    transport: Destination,
+    address_preference: AddressPreference,
+    udp_bind: UdpBind,
+    /// the RAM kept for the messages of a TCP logger connecting on the first one
+    lazy_buffer: Option<usize>,
+    framing: Option<Framing>,
+    timeouts: Timeouts,
+    flush_policy: FlushPolicy,
+    min_severity: Severity,
+    fire_and_forget: bool,
+    spool: Option<Box<dyn SpoolStorage + Send>>,
+    filter: Option<String>,
+    format: PhantomData<F>,
+}
+
+impl<F> SyslogBuilder<F> {
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.formatter.facility = facility;
+        self
+    }
+
+    /// sets the HOSTNAME of every message, see `set_hostname`, when the logger is built
+    pub fn hostname<S: Into<String>>(mut self, hostname: S) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// reads the HOSTNAME of every message from `source`, e.g. the station interface, whose
+    /// name the DHCP server may only give once the logger was set up
+    pub fn hostname_source(mut self, source: HostnameSource) -> Self {
+        self.formatter.hostname = source;
+        self
+    }
+
+    /// sets the APP-NAME, `main` by default
+    pub fn process<S: Into<String>>(mut self, process: S) -> Self {
+        self.formatter.process = process.into();
+        self
+    }
+
+    /// sets the PROCID, `0` by default; `None` leaves it out of RFC 3164 messages, and sends
+    /// the NILVALUE (`-`) in RFC 5424 ones
+    pub fn pid(mut self, pid: Option<u32>) -> Self {
+        self.formatter.pid = pid;
+        self
+    }
+
+    /// cuts the TAG of RFC 3164 messages to the alphanumeric characters BSD-style receivers
+    /// expect, see `Formatter3164::strict_tag`
+    pub fn strict_tag(mut self, strict: bool) -> Self {
+        self.formatter.strict_tag = strict;
+        self
+    }
+
+    #[cfg(feature = "timestamps")]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.formatter.clock = clock;
+        self
+    }
+
+    /// caps the length of the frames, see `Oversize`
+    pub fn max_message_len(mut self, len: usize, oversize: Oversize) -> Self {
+        self.formatter.max_message_len = len;
+        self.formatter.oversize = oversize;
+        self
+    }
+
+    /// sends the messages over UDP to `server`, see `udp`
+    pub fn transport_udp<T: ToSocketAddrs>(mut self, server: T) -> Self {
+        self.transport = Destination::Udp(AddressPreference::Any.resolve(server));
+        self
+    }
+
+    /// sends the messages over TCP to `server`, see `tcp`
+    pub fn transport_tcp<T: ToSocketAddrs>(mut self, server: T) -> Self {
+        self.transport = Destination::Tcp(AddressPreference::Any.resolve(server));
+        self
+    }
+
+    /// sends the messages to the local syslog socket at `path`, see `unix_custom`
+    #[cfg(all(unix, not(target_os = "espidf")))]
+    pub fn transport_unix<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.transport = Destination::Unix(path.into());
+        self
+    }
+
+    /// keeps up to `bytes` of frames in RAM, for `attach_network` to send, see `init_early`
+    pub fn transport_memory(mut self, bytes: usize) -> Self {
+        self.transport = Destination::Memory(bytes);
+        self
+    }
+
+    /// sends the messages with `transport`, see `Transport`
+    pub fn transport<T: Transport + Send + 'static>(mut self, transport: T) -> Self {
+        self.transport = Destination::Custom(Box::new(transport));
+        self
+    }
+
+    /// sets which addresses of the server are tried first, see `AddressPreference`
+    pub fn address_preference(mut self, preference: AddressPreference) -> Self {
+        self.address_preference = preference;
+        self
+    }
+
+    /// binds the UDP socket to the source port and interface address of `bind`, see `udp_bound`
+    pub fn udp_bind(mut self, bind: UdpBind) -> Self {
+        self.udp_bind = bind;
+        self
+    }
+
+    /// connects over TCP on the first message rather than in `build`, keeping up to
+    /// `buffer_bytes` of the messages it can't send yet, see `tcp_lazy`
+    ///
+    /// If `spool` is set too, the messages are kept in its storage instead.
+    pub fn connect_lazily(mut self, buffer_bytes: usize) -> Self {
+        self.lazy_buffer = Some(buffer_bytes);
+        self
+    }
+
+    /// delimits the messages over TCP with `framing`, see `tcp_with_framing`
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = Some(framing);
+        self
+    }
+
+    /// bounds how long connecting to and writing to a TCP server may block, see `Timeouts`
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// see `Logger::set_min_severity`
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    /// see `Logger::set_fire_and_forget`
+    pub fn fire_and_forget(mut self, fire_and_forget: bool) -> Self {
+        self.fire_and_forget = fire_and_forget;
+        self
+    }
+
+    /// stores in `storage` the messages the transport fails to send, until it can, see
+    /// `SpoolBackend`
+    pub fn spool<S: SpoolStorage + Send + 'static>(mut self, storage: S) -> Self {
+        self.spool = Some(Box::new(storage));
+        self
+    }
+
+    /// formats messages as RFC 3164 ones, the default
+    pub fn format_3164(self) -> SyslogBuilder<Formatter3164> {
+        self.format()
+    }
+
+    /// formats messages as RFC 5424 ones
+    pub fn format_5424(self) -> SyslogBuilder<Formatter5424> {
+        self.format()
+    }
+
+    fn format<G>(self) -> SyslogBuilder<G> {
+        SyslogBuilder {
+            formatter: self.formatter,
+            hostname: self.hostname,
+            transport: self.transport,
+            address_preference: self.address_preference,
+            udp_bind: self.udp_bind,
+            lazy_buffer: self.lazy_buffer,
+            framing: self.framing,
+            timeouts: self.timeouts,
+            flush_policy: self.flush_policy,
+            min_severity: self.min_severity,
+            fire_and_forget: self.fire_and_forget,
+            spool: self.spool,
+            filter: self.filter,
+            format: PhantomData,
+        }
+    }
+
+    /// connects the transport, and returns the logger
+    pub fn build(self) -> Result<Logger<LoggerBackend, F>>
+    where
+        F: From<Formatter3164>,
+    {
+        if let Some(hostname) = self.hostname {
+            store_hostname(hostname);
+        }
+        let formatter = F::from(self.formatter);
+        let mut logger = match self.transport {
+            Destination::Udp(servers) => {
+                let mut servers = servers.map_err(Error::Resolution)?;
+                self.address_preference.sort(&mut servers);
+                ::udp_bound(formatter, self.udp_bind, &servers[..])?
+            }
+            Destination::Tcp(servers) => {
+                let mut servers = servers.map_err(Error::Resolution)?;
+                self.address_preference.sort(&mut servers);
+                let mut logger = match self.lazy_buffer {
+                    Some(_) => Logger::new(tcp_lazy_backend(&servers)?, formatter),
+                    None => ::tcp_with_timeouts(formatter, &servers[..], self.timeouts)?,
+                };
+                if let LoggerBackend::ReconnectingTcp(ref mut stream) = logger.backend {
+                    stream.set_framing(self.framing);
+                    stream
+                        .set_timeouts(self.timeouts)
+                        .map_err(Error::initialization)?;
+                }
+                match self.lazy_buffer {
+                    Some(bytes) if self.spool.is_none() => {
+                        let (network, formatter) = logger.into_parts();
+                        let frames = LimitedStorage::new(MemoryStorage::new(), bytes);
+                        Logger::new(network.with_spool(frames), formatter)
+                    }
+                    _ => logger,
+                }
+            }
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            Destination::Unix(path) => ::unix_custom(formatter, path)?,
+            Destination::Memory(bytes) => {
+                let frames = LimitedStorage::new(MemoryStorage::new(), bytes);
+                Logger::new(LoggerBackend::Memory(frames), formatter)
+            }
+            Destination::Custom(transport) => {
+                Logger::new(LoggerBackend::Custom(transport), formatter)
+            }
+        };
+        if let Some(storage) = self.spool {
+            let (network, formatter) = logger.into_parts();
+            let backend = LoggerBackend::Spool(Box::new(SpoolBackend::new(network, storage)));
+            logger = Logger::new(backend, formatter);
+        }
+        logger.flush_policy = self.flush_policy;
+        logger.min_severity = self.min_severity;
+        logger.fire_and_forget = self.fire_and_forget;
+        Ok(logger)
+    }
+}
+
+impl SyslogBuilder<Formatter3164> {
+    /// filters the messages of `build_basic` by target, see `BasicLogger::with_filter`
+    pub fn filter<S: Into<String>>(mut self, spec: S) -> Self {
+        self.filter = Some(spec.into());
+        self
+    }
+
+    /// connects the transport, and returns the logger to register with the `log` crate
+    pub fn build_basic(mut self) -> Result<BasicLogger> {
+        let filter = self.filter.take();
+        let logger = BasicLogger::new(self.build()?);
+        Ok(match filter {
+            Some(spec) => logger.with_filter(&spec),
+            None => logger,
+        })
+    }
+
+    /// connects the transport, and returns a logger sending from a background thread, see
+    /// `BufferedLogger`
+    pub fn build_buffered(self, capacity: usize, overflow: Overflow) -> Result<BufferedLogger> {
+        self.build()
+            .map(|logger| BufferedLogger::new(logger, capacity, overflow))
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_builder() {
+    use std::net::UdpSocket;
+    use Message5424;
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut logger = Syslog::builder()
+        .facility(Facility::LOG_LOCAL0)
+        .process("sensor")
+        .transport_udp(server.local_addr().unwrap())
+        .format_5424()
+        .min_severity(Severity::LOG_INFO)
+        .build()
+        .unwrap();
+    logger.debug(Message5424::new("dropped")).unwrap();
+    logger.info(Message5424::new("sent")).unwrap();
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    let message = String::from_utf8(buf[..len].to_vec()).unwrap();
+    assert!(message.starts_with("<134>1 "));
+    assert!(message.ends_with(" sensor 0 - - sent"));
+
+    assert!(Syslog::builder()
+        .transport_tcp("no such host:601")
+        .build()
+        .is_err());
+}
@@ -0,0 +1,188 @@
+//! HTTP bulk shipping backend, enabled by the `http` feature
+//!
+//! Frames are batched and POSTed newline-delimited, one frame per line, to an ingestion
+//! endpoint: an Elasticsearch or OpenSearch `_bulk` API, a Vector or Fluent Bit `http`
+//! source, or a custom one.
+use std::io::{self, Write};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tls")]
+use rustls::ClientConfig;
+
+use http::{BatchQueue, HttpEndpoint};
+
+/// number of frames sent in one request by default
+const DEFAULT_BATCH_SIZE: usize = 32;
+/// how long a frame waits for its batch to fill by default
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+/// how many batches are kept while the server is unreachable, the oldest frames being dropped
+/// beyond that
+const MAX_PENDING_BATCHES: usize = 4;
+
+/// A backend POSTing the frames in batches, one frame per line
+///
+/// A batch is sent once it holds `with_batch_size` frames, when a frame is written after the
+/// oldest queued one waited `with_flush_interval`, and when the backend is flushed. Frames
+/// the server doesn't take are kept for the next attempt.
+///
+/// With `FormatterJson` the body is NDJSON, which an Elasticsearch `_bulk` API takes once
+/// every document is preceded by its action line:
+///
+/// ```no_run
+/// # use esp_syslog::{Formatter3164, FormatterJson, HttpBackend, Logger, LoggerBackend};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let backend = HttpBackend::new("http://elastic:9200/syslog/_bulk")?
+///     .with_content_type("application/x-ndjson")
+///     .with_action_line("{\"create\":{}}");
+/// let logger = Logger::new(LoggerBackend::Http(backend), FormatterJson::<Formatter3164>::default());
+/// # Ok(())
+/// # }
+/// ```
+pub struct HttpBackend {
+    endpoint: HttpEndpoint,
+    content_type: String,
+    action_line: Option<String>,
+    flush_interval: Duration,
+    frames: BatchQueue<Vec<u8>>,
+    /// when the oldest queued frame was written
+    oldest: Option<Instant>,
+}
+
+impl HttpBackend {
+    /// POSTs to `url`, e.g. `http://collector:8080/logs`, the path defaulting to `/`
+    ///
+    /// `https://` URLs need the `tls` feature and `with_tls_config`.
+    pub fn new(url: &str) -> io::Result<HttpBackend> {
+        Ok(HttpBackend {
+            endpoint: HttpEndpoint::parse(url, "/")?,
+            content_type: "text/plain".to_string(),
+            action_line: None,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            frames: BatchQueue::new(DEFAULT_BATCH_SIZE, MAX_PENDING_BATCHES),
+            oldest: None,
+        })
+    }
+
+    /// adds a header sent with every request, e.g. an `Authorization` token
+    pub fn with_header(mut self, name: &str, value: &str) -> HttpBackend {
+        self.endpoint.add_header(name, value);
+        self
+    }
+
+    /// sets the TLS configuration used for `https://` URLs, with the root certificates
+    /// trusted for the server
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>) -> HttpBackend {
+        self.endpoint.set_tls_config(config);
+        self
+    }
+
+    /// sets the `Content-Type` of the requests (`text/plain` by default)
+    pub fn with_content_type(mut self, content_type: &str) -> HttpBackend {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    /// sends `line` before every frame, e.g. the action line of a `_bulk` request
+    pub fn with_action_line(mut self, line: &str) -> HttpBackend {
+        self.action_line = Some(line.to_string());
+        self
+    }
+
+    /// sets how many frames are batched in one request (32 by default)
+    pub fn with_batch_size(mut self, batch_size: usize) -> HttpBackend {
+        self.frames.set_batch_size(batch_size);
+        self
+    }
+
+    /// sets how long a frame may wait for its batch to fill (5 seconds by default)
+    ///
+    /// The backend has no timer: the batch is sent with the first frame written after the
+    /// interval, or by a flush.
+    pub fn with_flush_interval(mut self, interval: Duration) -> HttpBackend {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// POSTs `frame` alone right away, ahead of the queued frames
+    pub(crate) fn send_now(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.endpoint
+            .post(&self.content_type, &self.body_of(Some(frame)))
+    }
+
+    fn body(&self) -> Vec<u8> {
+        self.body_of(self.frames.entries.iter().map(|frame| &frame[..]))
+    }
+
+    fn body_of<'a, I: IntoIterator<Item = &'a [u8]>>(&self, frames: I) -> Vec<u8> {
+        let mut body = Vec::new();
+        for frame in frames {
+            if let Some(ref line) = self.action_line {
+                body.extend_from_slice(line.as_bytes());
+                body.push(b'\n');
+            }
+            body.extend_from_slice(frame);
+            body.push(b'\n');
+        }
+        body
+    }
+}
+
+impl Write for HttpBackend {
+    /// Queues `frame`, sending the batch once it is full or has waited the flush interval
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+        if self.frames.push(frame.to_vec()) || oldest.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(frame.len())
+    }
+
+    /// Sends the queued frames, which are kept for the next attempt if that fails
+    fn flush(&mut self) -> io::Result<()> {
+        if self.frames.entries.is_empty() {
+            return Ok(());
+        }
+        self.endpoint.post(&self.content_type, &self.body())?;
+        self.frames.entries.clear();
+        self.oldest = None;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_http_backend() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/syslog/_bulk", listener.local_addr().unwrap());
+    let mut backend = HttpBackend::new(&url)
+        .unwrap()
+        .with_content_type("application/x-ndjson")
+        .with_action_line("{\"create\":{}}")
+        .with_batch_size(2);
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let first = ::http::serve_one(stream, "200 OK");
+        let (stream, _) = listener.accept().unwrap();
+        (first, ::http::serve_one(stream, "200 OK"))
+    });
+
+    backend.write_all(b"{\"message\":\"one\"}").unwrap();
+    backend.write_all(b"{\"message\":\"two\"}").unwrap();
+    backend = backend.with_flush_interval(Duration::from_secs(0));
+    backend.write_all(b"{\"message\":\"three\"}").unwrap();
+
+    let ((headers, body), (_, last)) = server.join().unwrap();
+    assert!(headers.starts_with("POST /syslog/_bulk HTTP/1.1\r\n"));
+    assert!(headers.contains("\r\nContent-Type: application/x-ndjson\r\n"));
+    assert_eq!(
+        body,
+        &b"{\"create\":{}}\n{\"message\":\"one\"}\n{\"create\":{}}\n{\"message\":\"two\"}\n"[..]
+    );
+    assert_eq!(last, &b"{\"create\":{}}\n{\"message\":\"three\"}\n"[..]);
+    assert!(backend.frames.entries.is_empty());
+}
@@ -0,0 +1,162 @@
+//! `@cee:` JSON payloads (project Lumberjack), for rsyslog's mmjsonparse
+use std::fmt::{self, Arguments, Display};
+use std::io::Write;
+
+use errors::*;
+use format::{LogFormat, Severity, WithFields};
+use json::JsonStr;
+use payload::Bytes;
+use {Formatter3164, Formatter5424};
+
+/// The cookie rsyslog's mmjsonparse looks for at the start of the MSG
+pub const CEE_COOKIE: &str = "@cee:";
+
+/// Sends the MSG as an `@cee:{"msg":...}` JSON payload, in the frame produced by `formatter`
+///
+/// The fields of a `WithFields` message become members of the JSON object, rather than being
+/// appended to the message (RFC 3164) or sent as structured data (RFC 5424):
+/// `@cee:{"msg":"reading","temp":"23.5"}`. Messages can be `&str`, `String`,
+/// `fmt::Arguments` or `WithFields`.
+#[derive(Clone, Debug, Default)]
+pub struct FormatterCee<F> {
+    pub formatter: F,
+}
+
+/// Wire formatters able to frame a `@cee:` payload
+pub trait CeeFrame {
+    fn format_cee<W: Write>(&self, w: &mut W, severity: Severity, event: CeeEvent) -> Result<()>;
+}
+
+impl CeeFrame for Formatter3164 {
+    fn format_cee<W: Write>(&self, w: &mut W, severity: Severity, event: CeeEvent) -> Result<()> {
+        self.format(w, severity, event)
+    }
+}
+
+impl CeeFrame for Formatter5424 {
+    fn format_cee<W: Write>(&self, w: &mut W, severity: Severity, event: CeeEvent) -> Result<()> {
+        self.format(
+            w,
+            severity,
+            WithFields {
+                message: event,
+                fields: &[],
+            },
+        )
+    }
+}
+
+/// The `@cee:` payload of a message
+pub struct CeeEvent<'a> {
+    message: String,
+    fields: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> Display for CeeEvent<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{{\"msg\":{}", CEE_COOKIE, JsonStr(&self.message))?;
+        for &(name, value) in self.fields {
+            write!(f, ",{}:{}", JsonStr(name), JsonStr(value))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<'a, F: CeeFrame> LogFormat<&'a str> for FormatterCee<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a str) -> Result<()> {
+        let event = CeeEvent {
+            message: message.to_string(),
+            fields: &[],
+        };
+        self.formatter.format_cee(w, severity, event)
+    }
+}
+
+impl<F: CeeFrame> LogFormat<String> for FormatterCee<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: String) -> Result<()> {
+        let event = CeeEvent {
+            message,
+            fields: &[],
+        };
+        self.formatter.format_cee(w, severity, event)
+    }
+}
+
+impl<'a, F: CeeFrame> LogFormat<Arguments<'a>> for FormatterCee<F> {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: Arguments<'a>,
+    ) -> Result<()> {
+        let event = CeeEvent {
+            message: fmt::format(message),
+            fields: &[],
+        };
+        self.formatter.format_cee(w, severity, event)
+    }
+}
+
+/// Sends the bytes as UTF-8 text, see `BytesEncoding::Lossy`
+impl<'a, F: CeeFrame> LogFormat<&'a [u8]> for FormatterCee<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a [u8]) -> Result<()> {
+        let event = CeeEvent {
+            message: Bytes::lossy(message).to_string(),
+            fields: &[],
+        };
+        self.formatter.format_cee(w, severity, event)
+    }
+}
+
+impl<F: CeeFrame> LogFormat<Vec<u8>> for FormatterCee<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: Vec<u8>) -> Result<()> {
+        self.format(w, severity, &message[..])
+    }
+}
+
+impl<'a, F: CeeFrame, T: Display> LogFormat<WithFields<'a, T>> for FormatterCee<F> {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: WithFields<'a, T>,
+    ) -> Result<()> {
+        let event = CeeEvent {
+            message: message.message.to_string(),
+            fields: message.fields,
+        };
+        self.formatter.format_cee(w, severity, event)
+    }
+}
+
+#[test]
+fn test_cee_payload() {
+    let formatter = FormatterCee {
+        formatter: Formatter3164::default(),
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            WithFields {
+                message: "say \"hi\"",
+                fields: &[("temp", "23.5")],
+            },
+        )
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with("main[0]: @cee:{\"msg\":\"say \\\"hi\\\"\",\"temp\":\"23.5\"}"));
+
+    let formatter = FormatterCee {
+        formatter: Formatter5424::default(),
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(&mut buf, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with(" main 0 - - @cee:{\"msg\":\"hello\"}"));
+}
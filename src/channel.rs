@@ -0,0 +1,147 @@
+//! `embassy-sync` channel front-end, enabled by the `embassy` feature
+//!
+//! `ChannelLogger` is the `log` facade of async firmware: logging formats the message and
+//! queues it without ever blocking, dropping it if the channel is full, and `run` is the task
+//! sending the queued messages through a `Logger`. Memory use is bounded by the capacity of
+//! the channel, which lives in a `static`:
+//!
+//! ```ignore
+//! static CHANNEL: LogChannel<CriticalSectionRawMutex, 32> = LogChannel::new();
+//! static LOGGER: StaticCell<ChannelLogger<CriticalSectionRawMutex, 32>> = StaticCell::new();
+//!
+//! log::set_logger(LOGGER.init(ChannelLogger::new(CHANNEL.sender())))?;
+//! spawner.spawn(syslog_task(CHANNEL.receiver(), logger))?;
+//!
+//! #[embassy_executor::task]
+//! async fn syslog_task(receiver: Receiver<'static, CriticalSectionRawMutex, Entry, 32>, logger: Logger<Udp, Formatter3164>) {
+//!     esp_syslog::run_channel(receiver, logger).await
+//! }
+//! ```
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::{Channel, Receiver, Sender};
+use log::{Log, Metadata, Record};
+
+use format::{LogFormat, Severity};
+use Logger;
+
+/// A message queued by `ChannelLogger`
+pub type Entry = (Severity, String);
+
+/// The channel between `ChannelLogger` and `run`, holding up to `N` messages
+pub type LogChannel<M, const N: usize> = Channel<M, Entry, N>;
+
+/// A `log` implementation queuing every message in a `LogChannel`
+pub struct ChannelLogger<'a, M: RawMutex, const N: usize> {
+    sender: Sender<'a, M, Entry, N>,
+    dropped: AtomicUsize,
+}
+
+impl<'a, M: RawMutex, const N: usize> ChannelLogger<'a, M, N> {
+    pub fn new(sender: Sender<'a, M, Entry, N>) -> Self {
+        ChannelLogger {
+            sender,
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// returns how many messages were dropped because the channel was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<'a, M: RawMutex + Sync, const N: usize> Log for ChannelLogger<'a, M, N> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.level() <= log::STATIC_MAX_LEVEL
+    }
+
+    /// The `log` macros already checked the level
+    fn log(&self, record: &Record) {
+        let severity = Severity::from(record.level());
+        if self
+            .sender
+            .try_send((severity, format!("{}", record.args())))
+            .is_err()
+        {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// returns the task sending every message queued in the channel through `logger`, forever
+///
+/// Meant to be awaited by a task, since embassy tasks can't be generic. Messages failing to
+/// send are dropped.
+pub fn run<M: RawMutex, W: Write, F: LogFormat<String>, const N: usize>(
+    receiver: Receiver<M, Entry, N>,
+    logger: Logger<W, F>,
+) -> Run<M, W, F, N> {
+    Run { receiver, logger }
+}
+
+/// The future returned by `run`, which never completes
+pub struct Run<'a, M: RawMutex, W: Write, F, const N: usize> {
+    receiver: Receiver<'a, M, Entry, N>,
+    logger: Logger<W, F>,
+}
+
+// The fields are never pinned
+impl<'a, M: RawMutex, W: Write, F, const N: usize> Unpin for Run<'a, M, W, F, N> {}
+
+impl<'a, M: RawMutex, W: Write, F: LogFormat<String>, const N: usize> Future
+    for Run<'a, M, W, F, N>
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let run = self.get_mut();
+        while let Poll::Ready((severity, message)) = run.receiver.poll_receive(cx) {
+            let _ = run.logger.send(severity, message);
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_channel_logger() {
+    use std::task::Waker;
+
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use log::Level;
+    use Formatter3164;
+
+    let channel: LogChannel<CriticalSectionRawMutex, 1> = Channel::new();
+    let logger = ChannelLogger::new(channel.sender());
+    for message in ["first", "second"] {
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+    assert_eq!(logger.dropped(), 1);
+
+    let (reader, writer) = ::std::os::unix::net::UnixStream::pair().unwrap();
+    let mut task = run(
+        channel.receiver(),
+        Logger::new(writer, Formatter3164::default()),
+    );
+    let mut context = Context::from_waker(Waker::noop());
+    assert!(Pin::new(&mut task).poll(&mut context).is_pending());
+
+    let mut frame = vec![0; 128];
+    let len = ::std::io::Read::read(&mut &reader, &mut frame).unwrap();
+    let frame = String::from_utf8_lossy(&frame[..len]).into_owned();
+    assert!(frame.starts_with("<12>"));
+    assert!(frame.ends_with(": first"));
+}
@@ -0,0 +1,135 @@
+//! Where the formatters take the timestamps of the messages from
+use std::fmt;
+
+use time::{Duration, OffsetDateTime};
+
+/// The system time is taken as set once past 2024-01-01, the ESP32 booting in 1970 until SNTP
+/// sets it
+const SET_AFTER: i64 = 1_704_067_200;
+
+/// A timestamp source for the clocks `Clock` doesn't cover, e.g. a GPS receiver
+///
+/// ```no_run
+/// # extern crate esp_syslog;
+/// # extern crate time;
+/// # use esp_syslog::{Clock, Formatter5424, TimeSource};
+/// # use time::OffsetDateTime;
+/// # mod gps {
+/// #     pub struct Fix { pub time: time::OffsetDateTime }
+/// #     pub fn fix() -> Option<Fix> { None }
+/// # }
+/// struct Gps;
+///
+/// impl TimeSource for Gps {
+///     fn now(&self) -> Option<OffsetDateTime> {
+///         gps::fix().map(|fix| fix.time)
+///     }
+/// }
+///
+/// static GPS: Gps = Gps;
+/// let formatter = Formatter5424 { clock: Clock::Custom(&GPS), ..Default::default() };
+/// ```
+pub trait TimeSource: Sync {
+    /// returns the current time, or `None` while it is unknown
+    fn now(&self) -> Option<OffsetDateTime>;
+}
+
+/// The timestamp source of a formatter
+///
+/// When the time is unknown, RFC 5424 messages carry the NILVALUE (`-`) as their timestamp;
+/// RFC 3164 ones, which have no such thing, carry the system time.
+#[derive(Clone, Copy, Default)]
+pub enum Clock {
+    /// the system time, set by SNTP on the ESP32
+    #[default]
+    System,
+    /// an external clock such as a battery-backed RTC, read by the function, which is more
+    /// trustworthy than the system clock at boot; returning `None`, e.g. when the RTC lost
+    /// power, falls back to the system time
+    Rtc(fn() -> Option<OffsetDateTime>),
+    /// the time since boot, sent as a timestamp in January 1970 so it is recognizable as such
+    Uptime,
+    /// the system time once it was set, by SNTP or otherwise, unknown until then
+    Synced,
+    /// any other source, unknown when it returns `None`
+    Custom(&'static dyn TimeSource),
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Clock::System => f.write_str("System"),
+            Clock::Rtc(read) => f.debug_tuple("Rtc").field(&read).finish(),
+            Clock::Uptime => f.write_str("Uptime"),
+            Clock::Synced => f.write_str("Synced"),
+            Clock::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl Clock {
+    /// returns the current time, in UTC for the system time, and the system time when the
+    /// time is unknown
+    pub fn now(&self) -> OffsetDateTime {
+        self.try_now().unwrap_or_else(OffsetDateTime::now_utc)
+    }
+
+    /// returns the current time, in UTC for the system time, or `None` while it is unknown
+    pub fn try_now(&self) -> Option<OffsetDateTime> {
+        match *self {
+            Clock::System => Some(OffsetDateTime::now_utc()),
+            Clock::Rtc(read) => read().or_else(|| Some(OffsetDateTime::now_utc())),
+            Clock::Uptime => Some(OffsetDateTime::UNIX_EPOCH + uptime()),
+            Clock::Synced => Some(OffsetDateTime::now_utc()).filter(is_set),
+            Clock::Custom(source) => source.now(),
+        }
+    }
+}
+
+fn is_set(time: &OffsetDateTime) -> bool {
+    time.unix_timestamp() >= SET_AFTER
+}
+
+#[cfg(target_os = "espidf")]
+fn uptime() -> Duration {
+    Duration::microseconds(unsafe { esp_idf_svc::sys::esp_timer_get_time() })
+}
+
+/// Approximated by the time since the first message elsewhere
+#[cfg(not(target_os = "espidf"))]
+fn uptime() -> Duration {
+    use std::convert::TryFrom;
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    let elapsed = START.get_or_init(Instant::now).elapsed();
+    Duration::try_from(elapsed).unwrap_or(Duration::ZERO)
+}
+
+#[test]
+fn test_clock() {
+    fn rtc() -> Option<OffsetDateTime> {
+        Some(OffsetDateTime::UNIX_EPOCH + Duration::days(19844))
+    }
+    assert_eq!(Clock::Rtc(rtc).now().year(), 2024);
+    assert_eq!(
+        Clock::Rtc(|| None).now().year(),
+        OffsetDateTime::now_utc().year()
+    );
+    assert!(Clock::Uptime.now() < OffsetDateTime::UNIX_EPOCH + Duration::hours(1));
+
+    struct Unknown;
+
+    impl TimeSource for Unknown {
+        fn now(&self) -> Option<OffsetDateTime> {
+            None
+        }
+    }
+
+    static UNKNOWN: Unknown = Unknown;
+    assert!(Clock::Custom(&UNKNOWN).try_now().is_none());
+    assert!(Clock::Custom(&UNKNOWN).now() > OffsetDateTime::UNIX_EPOCH + Duration::days(19723));
+    assert!(Clock::Synced.try_now().is_some());
+    assert!(!is_set(&(OffsetDateTime::UNIX_EPOCH + uptime())));
+}
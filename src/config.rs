@@ -0,0 +1,517 @@
+//! One-call setup of the global logger from a `SyslogConfig`
+use std::io::{self, Write};
+use std::iter;
+use std::mem;
+use std::panic;
+use std::time::Duration;
+
+use log::LevelFilter;
+
+use errors::*;
+use filter::{TargetLevels, BUILD_FILTER};
+#[cfg(feature = "timestamps")]
+use Clock;
+use {
+    installed, register, set_network_available, store_hostname, Backoff, BasicLogger,
+    ConsoleFormatter, Facility, FlushPolicy, Formatter3164, Framing, LimitedStorage, Logger,
+    LoggerBackend, MemoryStorage, Overflow, Oversize, SpoolStorage, TimeZone,
+    DEFAULT_MAX_MESSAGE_LEN, INSTALLED,
+};
+
+/// The syslog server messages are sent to, as `host:port`
+#[derive(Clone, Debug)]
+pub enum Server {
+    Udp(String),
+    Tcp(String),
+}
+
+impl Server {
+    /// returns a backend sending to the server, connected if it is a TCP one
+    pub(crate) fn connect(&self) -> Result<LoggerBackend> {
+        match *self {
+            Server::Udp(ref server) => ::udp((), "0.0.0.0:0", &server[..]),
+            Server::Tcp(ref server) => ::tcp((), &server[..]),
+        }
+        .map(|logger| logger.backend)
+    }
+}
+
+/// Everything `init` sets up
+///
+/// ```no_run
+/// # extern crate log;
+/// # extern crate esp_syslog;
+/// # use esp_syslog::{Server, SyslogConfig};
+/// # use log::LevelFilter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// esp_syslog::init(SyslogConfig {
+///     server: Some(Server::Udp("logs.lan:514".into())),
+///     process: "thermostat".into(),
+///     target_levels: vec![("wifi".into(), LevelFilter::Warn)],
+///     ..Default::default()
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyslogConfig {
+    /// where messages are sent; without a server they are kept in RAM until
+    /// `attach_network`, as with `init_early`
+    pub server: Option<Server>,
+    pub facility: Facility,
+    pub process: String,
+    pub pid: Option<u32>,
+    #[cfg(feature = "timestamps")]
+    pub clock: Clock,
+    /// sent as the HOSTNAME, see `set_hostname`
+    pub hostname: Option<String>,
+    /// the time zone of the TIMESTAMP, see `Formatter3164::time_zone`
+    pub time_zone: TimeZone,
+    /// sends the process as a RFC 3164 TAG, see `Formatter3164::strict_tag`
+    pub strict_tag: bool,
+    /// the length of the longest frame sent, see `Formatter3164::max_message_len`
+    pub max_message_len: usize,
+    /// what is done with the longer ones, see `Oversize`
+    pub oversize: Oversize,
+    /// the level of every target without an override in `target_levels`
+    pub level: LevelFilter,
+    pub target_levels: Vec<(String, LevelFilter)>,
+    /// a `RUST_LOG`-style spec applied over `level` and `target_levels`, and over the
+    /// `ESP_SYSLOG_FILTER` spec of the build, e.g. read with `filter_from_nvs`, see
+    /// `BasicLogger::set_filter`
+    pub filter: Option<String>,
+    pub flush_policy: FlushPolicy,
+    /// how often a TCP connection is probed, and re-established if the server closed it
+    pub probe_interval: Duration,
+    /// the wait between attempts to re-establish a TCP connection
+    pub backoff: Backoff,
+    /// how messages are delimited over TCP, see `tcp_with_framing`; `None` sends them as they
+    /// are
+    pub framing: Option<Framing>,
+    /// how often the local address is checked, and the backend rebound if it changed, see
+    /// `BasicLogger::with_rebind_interval`
+    pub rebind_interval: Option<Duration>,
+    /// bytes of messages kept in RAM until a server is attached, without `server`
+    pub buffer_bytes: usize,
+    /// prints every message on the console, see `BasicLogger::with_console`
+    pub console: Option<ConsoleFormatter>,
+    /// sends the `boot` field with every message, see `BasicLogger::with_boot_id`
+    pub boot_id: bool,
+    /// sends the Wi-Fi signal with every message, see `BasicLogger::with_wifi_fields`
+    pub wifi_fields: bool,
+    /// logs panics at `LOG_CRIT` before the previous panic hook runs
+    pub log_panics: bool,
+    /// redirects stdout, and ESP-IDF's own log output with it, at `LOG_INFO` and stderr at
+    /// `LOG_ERR`, see `capture_stdio`
+    #[cfg(all(feature = "capture-stdio", unix))]
+    pub capture_stdio: bool,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        SyslogConfig {
+            server: None,
+            facility: Facility::LOG_USER,
+            process: "main".to_string(),
+            pid: None,
+            #[cfg(feature = "timestamps")]
+            clock: Clock::System,
+            hostname: None,
+            time_zone: TimeZone::Local,
+            strict_tag: false,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            oversize: Oversize::Truncate,
+            level: LevelFilter::Info,
+            target_levels: Vec::new(),
+            filter: None,
+            flush_policy: FlushPolicy::Batched,
+            probe_interval: Duration::from_secs(5),
+            backoff: Backoff::default(),
+            framing: None,
+            rebind_interval: Some(Duration::from_secs(30)),
+            buffer_bytes: 4096,
+            console: None,
+            boot_id: false,
+            wifi_fields: false,
+            log_panics: true,
+            #[cfg(all(feature = "capture-stdio", unix))]
+            capture_stdio: false,
+        }
+    }
+}
+
+impl SyslogConfig {
+    /// connects to the server and returns the logger of this configuration, without
+    /// installing it
+    pub fn build(&self) -> Result<BasicLogger> {
+        let mut basic = BasicLogger::new(self.logger()?);
+        if let Some(ref console) = self.console {
+            basic = basic.with_console(console.clone());
+        }
+        if self.boot_id {
+            basic = basic.with_boot_id();
+        }
+        if self.wifi_fields {
+            basic = basic.with_wifi_fields();
+        }
+        if let Some(interval) = self.rebind_interval {
+            basic = basic.with_rebind_interval(interval);
+        }
+        *basic.levels.lock() = self.levels();
+        #[cfg(target_os = "espidf")]
+        self.set_esp_levels(&basic)?;
+        Ok(basic)
+    }
+
+    fn logger(&self) -> Result<Logger<LoggerBackend, Formatter3164>> {
+        let formatter = Formatter3164 {
+            facility: self.facility,
+            process: self.process.clone(),
+            pid: self.pid,
+            #[cfg(feature = "timestamps")]
+            clock: self.clock,
+            time_zone: self.time_zone,
+            strict_tag: self.strict_tag,
+            max_message_len: self.max_message_len,
+            oversize: self.oversize,
+            ..Default::default()
+        };
+        let mut logger = match self.server {
+            Some(ref server) => Logger::new(server.connect()?, formatter),
+            None => {
+                let frames = LimitedStorage::new(MemoryStorage::new(), self.buffer_bytes);
+                Logger::new(LoggerBackend::Memory(frames), formatter)
+            }
+        };
+        logger.flush_policy = self.flush_policy;
+        if let LoggerBackend::ReconnectingTcp(ref mut stream) = logger.backend {
+            stream.set_probe_interval(self.probe_interval);
+            stream.set_backoff(self.backoff);
+            stream.set_framing(self.framing);
+        }
+        Ok(logger)
+    }
+
+    fn levels(&self) -> TargetLevels {
+        let mut levels = TargetLevels::default();
+        levels.set_default(self.level);
+        for &(ref target, level) in &self.target_levels {
+            levels.set(target, level);
+        }
+        for spec in BUILD_FILTER.iter().chain(self.filter.as_deref().iter()) {
+            levels.apply(spec);
+        }
+        levels
+    }
+
+    #[cfg(target_os = "espidf")]
+    fn set_esp_levels(&self, basic: &BasicLogger) -> Result<()> {
+        for &(ref target, level) in &self.target_levels {
+            basic
+                .esp_logger
+                .lock()
+                .set_target_level(target, level)
+                .map_err(Error::initialization)?;
+        }
+        Ok(())
+    }
+}
+
+impl BasicLogger {
+    /// applies `config` to this logger and every clone of it: server, format, levels and
+    /// flush policy
+    ///
+    /// The new server is connected to first, and nothing changes if that fails. Then every
+    /// message goes either entirely to the old backend or entirely to the new one: the messages
+    /// the old backend still holds, kept in RAM, spooled, batched or buffered for a
+    /// reconnection, are sent to the new server. Those it fails to take are spooled in RAM and
+    /// sent ahead of the next messages, the error being returned. Other backends are flushed,
+    /// their error being returned if that fails. The console output and the fields added to the messages by the
+    /// clones are left alone.
+    pub fn reconfigure(&self, config: &SyslogConfig) -> Result<()> {
+        let replacement = config.logger()?;
+        if let Some(ref hostname) = config.hostname {
+            store_hostname(hostname.clone());
+        }
+        let flushed = {
+            let mut logger = self.logger.lock();
+            let previous = mem::replace(&mut *logger, replacement);
+            hand_over(previous.backend, &mut logger.backend)
+        };
+        if config.server.is_some() {
+            set_network_available();
+        }
+
+        let mut levels = self.levels.lock();
+        *levels = config.levels();
+        log::set_max_level(levels.max());
+        #[cfg(target_os = "espidf")]
+        config.set_esp_levels(self)?;
+        flushed
+    }
+}
+
+/// sends the messages `previous` still holds to `next`, in order
+///
+/// If sending one fails, e.g. the new server not being reachable yet, it and the remaining ones
+/// are kept by `next`, see `LoggerBackend::keep`, and the error is returned.
+pub(crate) fn hand_over(mut previous: LoggerBackend, next: &mut LoggerBackend) -> Result<()> {
+    let mut queued = previous.take_queued().map_err(Error::from)?.into_iter();
+    while let Some(frame) = queued.next() {
+        if let Err(e) = next.write_all(&frame) {
+            next.keep(iter::once(frame).chain(queued))?;
+            return Err(Error::from(e));
+        }
+    }
+    next.flush().map_err(Error::from)
+}
+
+impl LoggerBackend {
+    /// takes the frames this backend still holds, oldest first, flushing the backends which
+    /// don't give them back
+    ///
+    /// Those of the network backend of a spool or of batching are older than its own ones.
+    fn take_queued(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        match *self {
+            LoggerBackend::Memory(ref mut frames) => take_stored(frames, Vec::new()),
+            LoggerBackend::Spool(ref mut backend) => {
+                let queued = backend.network.take_queued()?;
+                take_stored(&mut backend.storage, queued)
+            }
+            LoggerBackend::Batching(ref mut backend) => {
+                let mut queued = backend.inner.take_queued()?;
+                queued.extend(backend.take_batch());
+                Ok(queued)
+            }
+            LoggerBackend::ReconnectingTcp(ref mut stream) => Ok(stream.take_pending()),
+            ref mut backend => backend.flush().map(|()| Vec::new()),
+        }
+    }
+}
+
+impl LoggerBackend {
+    /// stores `frames`, oldest first, to be sent ahead of the next messages: in the storage
+    /// of a spool or of the RAM backend, else in a spool wrapped around the backend, just large
+    /// enough for them
+    fn keep<I: Iterator<Item = Vec<u8>>>(&mut self, frames: I) -> io::Result<()> {
+        match *self {
+            LoggerBackend::Memory(ref mut stored) => push_all(stored, frames),
+            LoggerBackend::Spool(ref mut backend) => push_all(&mut backend.storage, frames),
+            _ => {
+                let frames: Vec<_> = frames.collect();
+                let size = frames.iter().map(Vec::len).sum();
+                let storage = LimitedStorage::new(MemoryStorage::new(), size)
+                    .with_overflow(Overflow::DropNewest);
+                let empty = LoggerBackend::Memory(LimitedStorage::new(MemoryStorage::new(), 0));
+                *self = mem::replace(self, empty).with_spool(storage);
+                self.keep(frames.into_iter())
+            }
+        }
+    }
+}
+
+/// pushes `frames` to `storage`, in order
+fn push_all<S, I>(storage: &mut S, frames: I) -> io::Result<()>
+where
+    S: SpoolStorage + ?Sized,
+    I: Iterator<Item = Vec<u8>>,
+{
+    for frame in frames {
+        storage.push(&frame)?;
+    }
+    Ok(())
+}
+
+/// appends the frames of `storage` to `queued`, removing them
+fn take_stored<S: SpoolStorage + ?Sized>(
+    storage: &mut S,
+    mut queued: Vec<Vec<u8>>,
+) -> io::Result<Vec<Vec<u8>>> {
+    while let Some(frame) = storage.front()? {
+        queued.push(frame);
+        storage.pop_front()?;
+    }
+    Ok(queued)
+}
+
+/// sets up and installs the global logger as described by `config`
+///
+/// With a server, the network is marked as available right away.
+pub fn init(config: SyslogConfig) -> Result<()> {
+    if let Some(ref hostname) = config.hostname {
+        store_hostname(hostname.clone());
+    }
+    let logger = config.build()?;
+    register(logger)?;
+    if config.server.is_some() {
+        set_network_available();
+    }
+    if config.log_panics {
+        log_panics();
+    }
+    #[cfg(all(feature = "capture-stdio", unix))]
+    {
+        if config.capture_stdio {
+            ::capture_stdio(Some(::Severity::LOG_INFO), Some(::Severity::LOG_ERR))?;
+        }
+    }
+    Ok(())
+}
+
+/// applies `config` to the logger installed by `init` or one of the `init_*` functions, see
+/// `BasicLogger::reconfigure`
+pub fn reconfigure(config: &SyslogConfig) -> Result<()> {
+    installed().ok_or(Error::NotInstalled)?.reconfigure(config)
+}
+
+/// sends panic messages through the installed logger, then runs the previous panic hook
+fn log_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The logger may be what panicked
+        let installed = INSTALLED
+            .lock_unless_reentered()
+            .and_then(|installed| installed.clone());
+        if let Some(installed) = installed {
+            if let Some(mut logger) = installed.logger.lock_unless_reentered() {
+                let message = info.to_string().replace('\n', " ");
+                let _ = logger.crit(message);
+                let _ = logger.backend.flush();
+            }
+        }
+        previous(info);
+    }));
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_build() {
+    use std::net::UdpSocket;
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let config = SyslogConfig {
+        server: Some(Server::Udp(server.local_addr().unwrap().to_string())),
+        process: "thermostat".into(),
+        target_levels: vec![("wifi".into(), LevelFilter::Debug)],
+        ..Default::default()
+    };
+    let logger = config.build().unwrap();
+
+    assert_eq!(logger.levels.lock().level("app"), LevelFilter::Info);
+    assert_eq!(logger.levels.lock().level("wifi::scan"), LevelFilter::Debug);
+
+    logger.logger.lock().info("hello").unwrap();
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"thermostat: hello"));
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_reconfigure() {
+    use std::net::UdpSocket;
+
+    let config = SyslogConfig {
+        level: LevelFilter::Trace,
+        ..Default::default()
+    };
+    let logger = config.build().unwrap();
+    logger.logger.lock().info("queued").unwrap();
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let config = SyslogConfig {
+        server: Some(Server::Udp(server.local_addr().unwrap().to_string())),
+        process: "thermostat".into(),
+        level: LevelFilter::Trace,
+        target_levels: vec![("wifi".into(), LevelFilter::Warn)],
+        filter: Some("wifi::scan=error".into()),
+        ..Default::default()
+    };
+    logger.reconfigure(&config).unwrap();
+    logger.logger.lock().info("reconfigured").unwrap();
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main: queued"));
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"thermostat: reconfigured"));
+    assert_eq!(logger.levels.lock().level("wifi"), LevelFilter::Warn);
+    assert_eq!(logger.levels.lock().level("wifi::scan"), LevelFilter::Error);
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_reconfigure_hands_over_queues() {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use Transport;
+
+    struct Down;
+
+    impl Transport for Down {
+        fn send(&mut self, _: &[u8]) -> io::Result<()> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+    }
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let config = SyslogConfig {
+        server: Some(Server::Udp(server.local_addr().unwrap().to_string())),
+        process: "thermo-stat".into(),
+        strict_tag: true,
+        ..Default::default()
+    };
+    let mut buf = [0; 256];
+    let spooled = LoggerBackend::custom(Down).with_spool(MemoryStorage::new());
+    let batched = LoggerBackend::custom(Down).with_batching(1024, Duration::from_secs(60));
+    for backend in [spooled, batched] {
+        let logger = BasicLogger::new(Logger::new(backend, Formatter3164::default()));
+        logger.logger.lock().info("one").unwrap();
+        logger.logger.lock().info("two").unwrap();
+        logger.reconfigure(&config).unwrap();
+        logger.logger.lock().info("three").unwrap();
+
+        for message in [&b"main[0]: one"[..], b"main[0]: two", b"thermostat: three"] {
+            let len = server.recv(&mut buf).unwrap();
+            assert!(buf[..len].ends_with(message));
+        }
+    }
+}
+
+#[test]
+fn test_hand_over_to_a_server_down() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use Transport;
+
+    /// a new server not reachable yet
+    struct Later {
+        up: Arc<AtomicBool>,
+        sent: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl Transport for Later {
+        fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+            if !self.up.load(Ordering::SeqCst) {
+                return Err(io::ErrorKind::ConnectionRefused.into());
+            }
+            self.sent.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    let mut previous = LoggerBackend::Memory(LimitedStorage::new(MemoryStorage::new(), 1024));
+    previous.write_all(b"one").unwrap();
+    previous.write_all(b"two").unwrap();
+    let up = Arc::new(AtomicBool::new(false));
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut next = LoggerBackend::custom(Later {
+        up: up.clone(),
+        sent: sent.clone(),
+    });
+    assert!(hand_over(previous, &mut next).is_err());
+
+    up.store(true, Ordering::SeqCst);
+    next.write_all(b"three").unwrap();
+    assert_eq!(*sent.lock().unwrap(), [&b"one"[..], b"two", b"three"]);
+}
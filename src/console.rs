@@ -0,0 +1,164 @@
+//! Human-oriented console output for `BasicLogger`
+use std::io::{self, Write};
+#[cfg(feature = "esp-println")]
+use std::sync::Mutex;
+
+use log::{Level, Record};
+#[cfg(feature = "timestamps")]
+use time::OffsetDateTime;
+
+#[cfg(feature = "timestamps")]
+use format::now_local;
+
+/// Formats log records for people reading the serial console, e.g. during bring-up
+///
+/// Lines look like `10:42:07.512 WARN  wifi::scan       | no AP found`, with the level
+/// colored. This only affects the console: the frames sent over the network keep the format
+/// of the logger's wire formatter. See `BasicLogger::with_console`.
+#[derive(Clone, Debug)]
+pub struct ConsoleFormatter {
+    /// colors the level with ANSI escape codes
+    pub colors: bool,
+    /// targets are padded to this width so messages line up, longer ones are shortened from
+    /// the start
+    pub target_width: usize,
+}
+
+impl Default for ConsoleFormatter {
+    fn default() -> Self {
+        ConsoleFormatter {
+            colors: true,
+            target_width: 16,
+        }
+    }
+}
+
+impl ConsoleFormatter {
+    /// writes `record` as one line
+    pub fn format<W: Write>(&self, w: &mut W, record: &Record) -> io::Result<()> {
+        let time = time_of_day();
+
+        let target = record.target();
+        let target = match target
+            .char_indices()
+            .rev()
+            .nth(self.target_width.saturating_sub(1))
+        {
+            Some((start, _)) if target.len() > self.target_width => &target[start..],
+            _ => target,
+        };
+        let (color, level) = match record.level() {
+            Level::Error => ("31", "ERROR"),
+            Level::Warn => ("33", "WARN "),
+            Level::Info => ("32", "INFO "),
+            Level::Debug => ("36", "DEBUG"),
+            Level::Trace => ("90", "TRACE"),
+        };
+
+        // One write per line, so that concurrent lines don't interleave
+        let line = if self.colors {
+            format!(
+                "{} \x1b[{}m{}\x1b[0m {:width$} | {}\n",
+                time,
+                color,
+                level,
+                target,
+                record.args(),
+                width = self.target_width
+            )
+        } else {
+            format!(
+                "{} {} {:width$} | {}\n",
+                time,
+                level,
+                target,
+                record.args(),
+                width = self.target_width
+            )
+        };
+        w.write_all(line.as_bytes())
+    }
+}
+
+/// returns the local time, as `hh:mm:ss.mmm`
+#[cfg(feature = "timestamps")]
+fn time_of_day() -> String {
+    let now = now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.millisecond()
+    )
+}
+
+/// returns the UTC time, as `hh:mm:ss.mmm`, the local offset being unknown without the `time`
+/// crate
+#[cfg(not(feature = "timestamps"))]
+fn time_of_day() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = now.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+        now.subsec_millis()
+    )
+}
+
+/// held while a line is printed on the console
+#[cfg(feature = "esp-println")]
+static CONSOLE: Mutex<()> = Mutex::new(());
+
+/// runs `f`, which prints on the console, without any other line being printed meanwhile
+///
+/// The console lines of `BasicLogger` are printed this way, so wrapping the `esp_println`
+/// calls of the application (or using this crate's `println!`) keeps both outputs from
+/// interleaving on the UART. `f` must not log, which would deadlock.
+#[cfg(feature = "esp-println")]
+pub fn console_line<R, F: FnOnce() -> R>(f: F) -> R {
+    let _line = CONSOLE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f()
+}
+
+/// `esp_println::println!`, serialized with the console output of `BasicLogger`
+///
+/// The application must depend on `esp-println` itself.
+#[cfg(feature = "esp-println")]
+#[macro_export]
+macro_rules! println {
+    ($($arg:tt)*) => {
+        $crate::console_line(|| ::esp_println::println!($($arg)*))
+    };
+}
+
+#[test]
+fn test_console_line() {
+    let formatter = ConsoleFormatter {
+        colors: false,
+        target_width: 8,
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(
+            &mut buf,
+            &Record::builder()
+                .level(Level::Warn)
+                .target("app::wifi::scan")
+                .args(format_args!("no AP found"))
+                .build(),
+        )
+        .unwrap();
+    let line = String::from_utf8(buf).unwrap();
+    // hh:mm:ss.mmm
+    assert_eq!(line.find(' '), Some(12));
+    assert!(line.ends_with(" WARN  fi::scan | no AP found\n"));
+}
@@ -0,0 +1,119 @@
+//! Runtime control of the installed logger
+use std::mem;
+use std::time::Duration;
+
+use log::LevelFilter;
+
+use config::{hand_over, Server};
+use errors::*;
+use {set_network_available, store_hostname, BasicLogger, Stats};
+
+/// A handle to the logger installed by one of the `init_*` functions, returned by them
+///
+/// It changes the logger in place, for every thread, without registering it again with the
+/// `log` crate, e.g. after a remote configuration update:
+///
+/// ```no_run
+/// # use esp_syslog::{Facility, Server};
+/// # extern crate log;
+/// # use log::LevelFilter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (facility, level, process) = (Facility::LOG_USER, LevelFilter::Info, "main".to_string());
+/// let control = esp_syslog::init_udp("0.0.0.0:0", "logs.lan:514", facility, level, process, None)?;
+/// // later
+/// control.set_level(LevelFilter::Debug);
+/// control.set_server(&Server::Tcp("logs-2.lan:601".into()))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LoggerControl {
+    logger: BasicLogger,
+}
+
+impl LoggerControl {
+    pub(crate) fn new(logger: BasicLogger) -> LoggerControl {
+        LoggerControl { logger }
+    }
+
+    /// see `BasicLogger::set_level`
+    pub fn set_level(&self, level: LevelFilter) {
+        self.logger.set_level(level);
+    }
+
+    /// see `BasicLogger::set_filter`
+    pub fn set_filter(&self, spec: &str) {
+        self.logger.set_filter(spec);
+    }
+
+    /// sets the HOSTNAME of the messages sent from now on
+    pub fn set_hostname(&self, hostname: String) {
+        store_hostname(hostname);
+    }
+
+    /// connects to `server`, then sends the messages to it instead of the current server
+    ///
+    /// Nothing changes if connecting fails. Messages kept in RAM, e.g. by `init_early`, are sent
+    /// to the new server, those it fails to take being spooled as `BasicLogger::reconfigure`
+    /// does, and the previous backend is flushed, its error being returned if that fails.
+    pub fn set_server(&self, server: &Server) -> Result<()> {
+        let backend = server.connect()?;
+        let previous = {
+            let mut logger = self.logger.logger.lock();
+            let previous = mem::replace(&mut logger.backend, backend);
+            hand_over(previous, &mut logger.backend)
+        };
+        set_network_available();
+        previous
+    }
+
+    /// see `BasicLogger::stats`
+    pub fn stats(&self) -> Stats {
+        self.logger.stats()
+    }
+
+    /// see `BasicLogger::flush`
+    pub fn flush(&self) -> Result<()> {
+        self.logger.flush()
+    }
+
+    /// see `BasicLogger::network_available`
+    pub fn network_available(&self) -> Result<usize> {
+        self.logger.network_available()
+    }
+
+    /// see `BasicLogger::shutdown`
+    pub fn shutdown(&self, timeout: Duration) -> Result<Stats> {
+        self.logger.shutdown(timeout)
+    }
+
+    /// returns the installed logger, for the settings not covered here
+    pub fn logger(&self) -> &BasicLogger {
+        &self.logger
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_set_server() {
+    use std::net::UdpSocket;
+    use {Formatter3164, LimitedStorage, Logger, LoggerBackend, MemoryStorage};
+
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let control = LoggerControl::new(BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    )));
+    control.logger().logger.lock().info("kept").unwrap();
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap().to_string();
+    control.set_server(&Server::Udp(address)).unwrap();
+    control.logger().logger.lock().info("direct").unwrap();
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: kept"));
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: direct"));
+}
@@ -0,0 +1,219 @@
+//! `StaticLogger`, logging into a fixed buffer until a real logger exists
+use std::fmt::{self, Write as FmtWrite};
+
+use log::{Log, Metadata, Record};
+
+use format::Severity;
+use sync::Lock;
+use {set_network_available, BasicLogger, INSTALLED};
+
+/// longest message kept by `StaticLogger`, longer ones are truncated
+const MAX_MESSAGE: usize = 256;
+
+/// severities by value, as stored in the buffer
+const SEVERITIES: [Severity; 8] = [
+    Severity::LOG_EMERG,
+    Severity::LOG_ALERT,
+    Severity::LOG_CRIT,
+    Severity::LOG_ERR,
+    Severity::LOG_WARNING,
+    Severity::LOG_NOTICE,
+    Severity::LOG_INFO,
+    Severity::LOG_DEBUG,
+];
+
+/// A logger that can live in a `static`, keeping the latest messages in a buffer of `N` bytes
+///
+/// Logging doesn't allocate, so `log::set_logger(&LOGGER)` works before the allocator or the
+/// network are set up:
+///
+/// ```no_run
+/// # extern crate log;
+/// # extern crate esp_syslog;
+/// # use esp_syslog::{BasicLogger, Formatter3164, StaticLogger};
+/// # use log::LevelFilter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (formatter, local, server) = (Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514");
+/// static LOGGER: StaticLogger<2048> = StaticLogger::new();
+///
+/// log::set_logger(&LOGGER).unwrap();
+/// log::set_max_level(LevelFilter::Info);
+/// // ... once the network is up
+/// LOGGER.upgrade(BasicLogger::new(esp_syslog::udp(formatter, local, server)?))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The oldest messages are dropped when the buffer is full.
+pub struct StaticLogger<const N: usize> {
+    buffer: Lock<Ring<N>>,
+    logger: Lock<Option<BasicLogger>>,
+}
+
+impl<const N: usize> StaticLogger<N> {
+    pub const fn new() -> Self {
+        StaticLogger {
+            buffer: Lock::new(Ring {
+                bytes: [0; N],
+                start: 0,
+                len: 0,
+            }),
+            logger: Lock::new(None),
+        }
+    }
+
+    /// sends every buffered message through `logger`, in order, then hands the following
+    /// messages over to it and marks the network as available
+    ///
+    /// `logger` also becomes the logger `set_target_level` applies to, unless one of the
+    /// `init_*` functions installed one. If sending a buffered message fails, the remaining
+    /// ones are dropped and the error is returned; the following messages still go to
+    /// `logger`.
+    pub fn upgrade(&self, logger: BasicLogger) -> ::Result<()> {
+        let mut buffer = self.buffer.lock();
+        *self.logger.lock() = Some(logger.clone());
+        INSTALLED.lock().get_or_insert_with(|| logger.clone());
+        set_network_available();
+
+        let mut message = [0; MAX_MESSAGE];
+        let mut inner = logger.logger.lock();
+        while let Some((severity, len)) = buffer.pop(&mut message) {
+            let sent = inner.send(severity, String::from_utf8_lossy(&message[..len]));
+            if sent.is_err() {
+                buffer.len = 0;
+                return sent;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for StaticLogger<N> {
+    fn default() -> Self {
+        StaticLogger::new()
+    }
+}
+
+impl<const N: usize> Log for StaticLogger<N> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match *self.logger.lock() {
+            Some(ref logger) => logger.enabled(metadata),
+            None => metadata.level() <= log::max_level(),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(ref logger) = *self.logger.lock() {
+            return logger.log(record);
+        }
+        let severity = Severity::from(record.level());
+        let mut message = Truncated {
+            bytes: [0; MAX_MESSAGE],
+            len: 0,
+        };
+        let _ = write!(message, "{}", record.args());
+        self.buffer
+            .lock()
+            .push(severity, &message.bytes[..message.len]);
+    }
+
+    fn flush(&self) {
+        if let Some(ref logger) = *self.logger.lock() {
+            let _ = logger.flush();
+        }
+    }
+}
+
+/// Messages stored back to back, each as its severity, its length on two bytes, and its text
+struct Ring<const N: usize> {
+    bytes: [u8; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Ring<N> {
+    fn push(&mut self, severity: Severity, message: &[u8]) {
+        let size = 3 + message.len();
+        if size > N {
+            return;
+        }
+        let mut scratch = [0; MAX_MESSAGE];
+        while N - self.len < size {
+            self.pop(&mut scratch);
+        }
+        let len = message.len() as u16;
+        let header = [severity as u8, (len >> 8) as u8, len as u8];
+        for &byte in header.iter().chain(message) {
+            self.bytes[(self.start + self.len) % N] = byte;
+            self.len += 1;
+        }
+    }
+
+    /// removes the oldest message, copying its text to `message`
+    fn pop(&mut self, message: &mut [u8; MAX_MESSAGE]) -> Option<(Severity, usize)> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = |i: usize| self.bytes[(self.start + i) % N];
+        let severity = SEVERITIES[byte(0) as usize];
+        let len = ((byte(1) as usize) << 8) | byte(2) as usize;
+        for (i, slot) in message[..len].iter_mut().enumerate() {
+            *slot = byte(3 + i);
+        }
+        self.start = (self.start + 3 + len) % N;
+        self.len -= 3 + len;
+        Some((severity, len))
+    }
+}
+
+/// Formats up to `MAX_MESSAGE` bytes, dropping the rest at a character boundary
+struct Truncated {
+    bytes: [u8; MAX_MESSAGE],
+    len: usize,
+}
+
+impl FmtWrite for Truncated {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut end = s.len().min(MAX_MESSAGE - self.len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_static_logger() {
+    use std::net::UdpSocket;
+
+    use log::Level;
+    use {Formatter3164, Logger, LoggerBackend};
+
+    static LOGGER: StaticLogger<64> = StaticLogger::new();
+    for message in ["dropped, the buffer is full", "first", "second"] {
+        LOGGER.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .args(format_args!("{} message", message))
+                .build(),
+        );
+    }
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let backend = LoggerBackend::Udp(socket, server.local_addr().unwrap());
+    LOGGER
+        .upgrade(BasicLogger::new(Logger::new(
+            backend,
+            Formatter3164::default(),
+        )))
+        .unwrap();
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: first message"));
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: second message"));
+}
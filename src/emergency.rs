@@ -0,0 +1,139 @@
+//! Direct sending of the last messages before a brownout or a reset
+use std::io::{self, Write};
+
+use errors::*;
+use format::{LogFormat, Severity};
+use {installed, Logger, LoggerBackend};
+
+/// longest frame sent by `Logger::send_now`, longer ones are truncated
+const MAX_FRAME: usize = 512;
+
+impl<F> Logger<LoggerBackend, F> {
+    /// sends `message` at `severity` right away, ahead of the messages waiting in the backend
+    ///
+    /// The frame is formatted into a fixed buffer, without allocating, and written straight to
+    /// the socket, skipping the buffer of the TCP backends, the batches of the batching and
+    /// HTTP ones and the frames a spool holds; the backend is flushed afterwards. Meant for the
+    /// last message before a brownout or a reset, which would otherwise be lost with the queued
+    /// ones.
+    pub fn send_now<T>(&mut self, severity: Severity, message: T) -> Result<()>
+    where
+        F: LogFormat<T>,
+    {
+        let mut frame = FixedFrame {
+            bytes: [0; MAX_FRAME],
+            len: 0,
+        };
+        self.formatter.format(&mut frame, severity, message)?;
+        let frame = &frame.bytes[..frame.len];
+
+        self.backend
+            .write_now(frame)
+            .and_then(|()| self.backend.flush())
+            .map_err(Error::from)
+    }
+
+    /// sends `message` at `LOG_EMERG` right away, see `send_now`
+    pub fn emerg_now<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<T>,
+    {
+        self.send_now(Severity::LOG_EMERG, message)
+    }
+
+    /// sends `message` at `LOG_ALERT` right away, see `send_now`
+    pub fn alert_now<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<T>,
+    {
+        self.send_now(Severity::LOG_ALERT, message)
+    }
+}
+
+impl LoggerBackend {
+    /// writes `frame` to the network ahead of the frames waiting in the backend
+    pub(crate) fn write_now(&mut self, frame: &[u8]) -> io::Result<()> {
+        match *self {
+            // The buffer only holds whole frames, so the frame can go first
+            LoggerBackend::Tcp(ref mut socket) => socket.get_mut().write_all(frame),
+            LoggerBackend::ReconnectingTcp(ref mut stream) => stream.write_now(frame),
+            LoggerBackend::Batching(ref mut batching) => batching.write_now(frame),
+            // Spooled only if the network fails it
+            LoggerBackend::Spool(ref mut spool) => match spool.network.write_now(frame) {
+                Ok(()) => Ok(()),
+                Err(_) => spool.write_all(frame),
+            },
+            #[cfg(feature = "http")]
+            LoggerBackend::Http(ref mut http) => http.send_now(frame),
+            ref mut backend => backend.write_all(frame),
+        }
+    }
+}
+
+/// sends `message` at `LOG_EMERG` right away through the logger installed by `init` or one
+/// of the `init_*` functions, see `Logger::send_now`
+///
+/// Fails rather than deadlocking when called while the same thread is sending a message, e.g.
+/// from a panic hook or a backend.
+pub fn emergency(message: &str) -> Result<()> {
+    let installed = installed().ok_or(Error::NotInstalled)?;
+    let mut logger = installed
+        .logger
+        .lock_unless_reentered()
+        .ok_or_else(|| Error::Io(io::Error::other("the logger is already sending")))?;
+    logger.emerg_now(message)
+}
+
+/// A frame of up to `MAX_FRAME` bytes, dropping the rest
+struct FixedFrame {
+    bytes: [u8; MAX_FRAME],
+    len: usize,
+}
+
+impl Write for FixedFrame {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_FRAME - self.len);
+        self.bytes[self.len..self.len + len].copy_from_slice(&buf[..len]);
+        self.len += len;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_send_now() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use Formatter3164;
+
+    /// returns what the server received from `logger`, once dropped
+    fn received(listener: &TcpListener, logger: Logger<LoggerBackend, Formatter3164>) -> String {
+        let (mut server, _) = listener.accept().unwrap();
+        drop(logger);
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).unwrap();
+        String::from_utf8(received).unwrap()
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut logger = ::tcp(Formatter3164::default(), listener.local_addr().unwrap()).unwrap();
+    logger.info("queued").unwrap();
+    logger.emerg_now("brownout").unwrap();
+    let sent = received(&listener, logger);
+    let brownout = sent.find("main[0]: brownout").unwrap();
+    assert!(sent[brownout..].ends_with("main[0]: queued"));
+    assert!(sent.starts_with("<8>"));
+
+    let mut logger = ::tcp(Formatter3164::default(), listener.local_addr().unwrap()).unwrap();
+    logger.backend = logger.backend.with_batching(4096, Duration::from_secs(60));
+    logger.info("batched").unwrap();
+    logger.alert_now("brownout").unwrap();
+    let sent = received(&listener, logger);
+    assert!(sent.starts_with("<9>"));
+    assert!(sent.ends_with("main[0]: batched\n"));
+}
@@ -1,7 +1,134 @@
-error_chain! {
-    errors { Initialization UnsupportedPlatform Format Write }
+//! The error type of the crate
+//!
+//! The variants tell a caller what to do about a failure: `Disconnected` and `QueueFull` ones
+//! may go away by retrying later, `Resolution`, `Tls` and `Initialization` ones rather need the
+//! configuration fixed, and a message failing with `Format` or `Validation` is best dropped.
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
 
-    foreign_links {
-        Io(::std::io::Error) #[doc = "Link to a `std::error::Error` type."];
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// setting the logger up failed: a socket couldn't be bound, an option set on it, or the
+    /// logger installed
+    Initialization(Box<dyn error::Error + Send + Sync>),
+    /// no logger is installed, for the free functions acting on the installed one
+    NotInstalled,
+    /// a server name didn't resolve, or to no usable address
+    Resolution(io::Error),
+    /// the server couldn't be reached, or the connection to it was lost: the next attempt may
+    /// work, once the network is back
+    Disconnected(io::Error),
+    /// the TLS handshake with the server failed, or its certificate was rejected
+    Tls(io::Error),
+    /// sending failed otherwise
+    Io(io::Error),
+    /// a message couldn't be formatted, e.g. a `Display` implementation failing
+    Format(io::Error),
+    /// a field of a message is invalid, e.g. its MSGID
+    Validation(&'static str),
+    /// the message couldn't be queued or spooled, the storage being full
+    QueueFull,
+    /// the operation isn't supported on this platform
+    UnsupportedPlatform,
+}
+
+impl Error {
+    pub(crate) fn initialization<E>(error: E) -> Error
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        Error::Initialization(error.into())
+    }
+
+    /// sorts a failure to connect over TLS: rustls reports handshake and certificate errors
+    /// as `InvalidData` ones
+    #[cfg(all(feature = "tls", not(feature = "host-sim")))]
+    pub(crate) fn tls(error: io::Error) -> Error {
+        match error.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput => Error::Tls(error),
+            _ => Error::from(error),
+        }
+    }
+
+    /// returns whether the operation may succeed if attempted again later, without changing
+    /// the configuration
+    pub fn is_transient(&self) -> bool {
+        matches!(*self, Error::Disconnected(_) | Error::QueueFull)
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Sorts a failure to send by its `io::ErrorKind`
+    fn from(error: io::Error) -> Error {
+        match error.kind() {
+            io::ErrorKind::NotConnected
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::AddrNotAvailable => Error::Disconnected(error),
+            io::ErrorKind::StorageFull | io::ErrorKind::OutOfMemory => Error::QueueFull,
+            io::ErrorKind::Unsupported => Error::UnsupportedPlatform,
+            _ => Error::Io(error),
+        }
     }
 }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Initialization(ref e) => write!(f, "failed to initialize the logger: {e}"),
+            Error::NotInstalled => f.write_str("no syslog logger is installed"),
+            Error::Resolution(ref e) => write!(f, "failed to resolve the server address: {e}"),
+            Error::Disconnected(ref e) => write!(f, "disconnected from the server: {e}"),
+            Error::Tls(ref e) => write!(f, "TLS failure: {e}"),
+            Error::Io(ref e) => write!(f, "failed to send the message: {e}"),
+            Error::Format(ref e) => write!(f, "failed to format the message: {e}"),
+            Error::Validation(reason) => write!(f, "invalid syslog message: {reason}"),
+            Error::QueueFull => f.write_str("the message queue is full"),
+            Error::UnsupportedPlatform => f.write_str("unsupported on this platform"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Initialization(ref e) => Some(&**e),
+            Error::Resolution(ref e)
+            | Error::Disconnected(ref e)
+            | Error::Tls(ref e)
+            | Error::Io(ref e)
+            | Error::Format(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_error_kinds() {
+    let refused = Error::from(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert!(matches!(refused, Error::Disconnected(_)));
+    assert!(refused.is_transient());
+    assert!(matches!(
+        Error::from(io::Error::from(io::ErrorKind::StorageFull)),
+        Error::QueueFull
+    ));
+    let other = Error::from(io::Error::from(io::ErrorKind::InvalidData));
+    assert!(!other.is_transient());
+    assert!(error::Error::source(&other).is_some());
+    assert_eq!(
+        Error::Validation("a MSGID is 1 to 32 characters long").to_string(),
+        "invalid syslog message: a MSGID is 1 to 32 characters long"
+    );
+}
@@ -55,6 +55,34 @@ impl FromStr for Facility {
     }
 }
 
+impl Facility {
+    /// returns the lowercase name of the facility, e.g. `local0`, as `FromStr` takes it
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Facility::LOG_KERN => "kern",
+            Facility::LOG_USER => "user",
+            Facility::LOG_MAIL => "mail",
+            Facility::LOG_DAEMON => "daemon",
+            Facility::LOG_AUTH => "auth",
+            Facility::LOG_SYSLOG => "syslog",
+            Facility::LOG_LPR => "lpr",
+            Facility::LOG_NEWS => "news",
+            Facility::LOG_UUCP => "uucp",
+            Facility::LOG_CRON => "cron",
+            Facility::LOG_AUTHPRIV => "authpriv",
+            Facility::LOG_FTP => "ftp",
+            Facility::LOG_LOCAL0 => "local0",
+            Facility::LOG_LOCAL1 => "local1",
+            Facility::LOG_LOCAL2 => "local2",
+            Facility::LOG_LOCAL3 => "local3",
+            Facility::LOG_LOCAL4 => "local4",
+            Facility::LOG_LOCAL5 => "local5",
+            Facility::LOG_LOCAL6 => "local6",
+            Facility::LOG_LOCAL7 => "local7",
+        }
+    }
+}
+
 impl Default for Facility {
     /// Returns the default `Facility`, which is `LOG_USER` (as [specified by POSIX]).
     ///
@@ -0,0 +1,160 @@
+//! Failover between redundant syslog servers
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use tcp::ReconnectingTcpStream;
+use LoggerBackend;
+
+/// how often the primary is retried by default, while another server is in use
+const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Backends tried in order, moving on to the next one when a write fails
+///
+/// Every write is one whole frame, sent to the active backend; when that fails the frame is
+/// sent to the following backends, in order and wrapping around, and the first one taking it
+/// becomes the active one. While a backend other than the first one (the primary) is active,
+/// the primary is retried once per probe interval, and becomes active again as soon as it
+/// takes a frame. The backend left is flushed.
+///
+/// ```no_run
+/// # use esp_syslog::{FailoverBackend, Formatter3164, Logger, LoggerBackend};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let backend = FailoverBackend::tcp(&["10.0.0.10:601".parse()?, "10.0.0.11:601".parse()?])?;
+/// let logger = Logger::new(LoggerBackend::Failover(backend), Formatter3164::default());
+/// # Ok(())
+/// # }
+/// ```
+pub struct FailoverBackend {
+    backends: Vec<LoggerBackend>,
+    active: usize,
+    probe_interval: Duration,
+    last_probe: Instant,
+}
+
+impl FailoverBackend {
+    /// fails with `io::ErrorKind::InvalidInput` without any backend
+    pub fn new(backends: Vec<LoggerBackend>) -> io::Result<FailoverBackend> {
+        if backends.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a failover backend needs at least one server",
+            ));
+        }
+        Ok(FailoverBackend {
+            backends,
+            active: 0,
+            probe_interval: DEFAULT_PROBE_INTERVAL,
+            last_probe: Instant::now(),
+        })
+    }
+
+    /// returns a backend over TCP connections to `servers`, the first one being the primary,
+    /// each one connected on its first write, see `ReconnectingTcpStream::lazy`
+    pub fn tcp(servers: &[SocketAddr]) -> io::Result<FailoverBackend> {
+        let backends = servers
+            .iter()
+            .map(|&server| LoggerBackend::ReconnectingTcp(ReconnectingTcpStream::lazy(server)))
+            .collect();
+        FailoverBackend::new(backends)
+    }
+
+    /// sets how often the primary is retried while another backend is active, 30 seconds by
+    /// default
+    pub fn set_probe_interval(&mut self, interval: Duration) {
+        self.probe_interval = interval;
+    }
+
+    /// returns the index of the backend in use, 0 being the primary
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// returns the backend in use, e.g. to adjust its options
+    pub fn active_mut(&mut self) -> &mut LoggerBackend {
+        &mut self.backends[self.active]
+    }
+
+    pub fn backends(&self) -> &[LoggerBackend] {
+        &self.backends
+    }
+
+    pub fn backends_mut(&mut self) -> &mut [LoggerBackend] {
+        &mut self.backends
+    }
+
+    fn switch(&mut self, index: usize) {
+        if index != self.active {
+            let _ = self.backends[self.active].flush();
+            self.active = index;
+        }
+    }
+}
+
+impl Write for FailoverBackend {
+    /// Sends `frame` as one syslog message, to the first backend taking it
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let start = self.active;
+        if start != 0 && self.last_probe.elapsed() >= self.probe_interval {
+            self.last_probe = Instant::now();
+            if self.backends[0].write_all(frame).is_ok() {
+                self.switch(0);
+                return Ok(frame.len());
+            }
+        }
+        let mut error = None;
+        for offset in 0..self.backends.len() {
+            let index = (start + offset) % self.backends.len();
+            match self.backends[index].write_all(frame) {
+                Ok(()) => {
+                    self.switch(index);
+                    return Ok(frame.len());
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(error.unwrap())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.backends[self.active].flush()
+    }
+}
+
+#[test]
+fn test_failover() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+    use tcp::Backoff;
+
+    let primary = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let secondary = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut backend = FailoverBackend::tcp(&[primary, secondary.local_addr().unwrap()]).unwrap();
+    if let LoggerBackend::ReconnectingTcp(ref mut stream) = backend.backends_mut()[0] {
+        stream.set_backoff(Backoff {
+            initial: Duration::from_millis(50),
+            max: Duration::from_millis(50),
+        });
+    }
+    backend.set_probe_interval(Duration::ZERO);
+
+    // The primary is down
+    backend.write_all(b"one").unwrap();
+    assert_eq!(backend.active(), 1);
+
+    let primary = TcpListener::bind(primary).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    backend.write_all(b"two").unwrap();
+    assert_eq!(backend.active(), 0);
+    backend.flush().unwrap();
+
+    let mut buf = [0; 3];
+    secondary.accept().unwrap().0.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"one");
+    primary.accept().unwrap().0.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"two");
+}
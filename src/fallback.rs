@@ -0,0 +1,94 @@
+//! Local fallback for the messages the network backend failed to send
+use std::io::{self, Write};
+
+use LoggerBackend;
+
+/// A backend writing to `secondary` the frames `primary` fails to send, instead of losing them
+///
+/// The secondary is typically the console (the UART on an ESP32, stdout on a host), so each
+/// frame is followed there by a line feed. A write only fails if both fail.
+///
+/// ```no_run
+/// # use std::io;
+/// # use esp_syslog::Formatter3164;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (formatter, local, server) = (Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514");
+/// let backend = esp_syslog::udp(formatter, local, server)?.backend.with_fallback(io::stdout());
+/// # Ok(())
+/// # }
+/// ```
+pub struct FallbackBackend<W, S> {
+    pub primary: W,
+    pub secondary: S,
+    fallbacks: usize,
+}
+
+impl<W: Write, S: Write> FallbackBackend<W, S> {
+    pub fn new(primary: W, secondary: S) -> Self {
+        FallbackBackend {
+            primary,
+            secondary,
+            fallbacks: 0,
+        }
+    }
+
+    /// returns the number of frames written to the secondary
+    pub fn fallbacks(&self) -> usize {
+        self.fallbacks
+    }
+}
+
+impl<W: Write, S: Write> Write for FallbackBackend<W, S> {
+    /// Sends `frame` as one syslog message, to the secondary if the primary fails
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if let Err(e) = self.primary.write_all(frame) {
+            let mut line = Vec::with_capacity(frame.len() + 1);
+            line.extend_from_slice(frame);
+            line.push(b'\n');
+            self.secondary.write_all(&line).map_err(|_| e)?;
+            self.fallbacks += 1;
+        }
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = self.secondary.flush();
+        self.primary.flush()
+    }
+}
+
+impl LoggerBackend {
+    /// wraps the backend into a `FallbackBackend` writing to `secondary` what it fails to send
+    pub fn with_fallback<S: Write + Send + 'static>(self, secondary: S) -> LoggerBackend {
+        LoggerBackend::Fallback(Box::new(FallbackBackend::new(self, Box::new(secondary))))
+    }
+}
+
+#[test]
+fn test_fallback() {
+    /// a network backend that is down
+    struct Down;
+
+    impl Write for Down {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut backend = FallbackBackend::new(Down, Vec::new());
+    backend.write_all(b"<14>offline").unwrap();
+    assert_eq!(backend.fallbacks(), 1);
+    assert_eq!(backend.secondary, b"<14>offline\n");
+
+    let mut sent = FallbackBackend::new(Vec::new(), Vec::new());
+    sent.write_all(b"<14>online").unwrap();
+    assert_eq!(sent.fallbacks(), 0);
+    assert!(sent.secondary.is_empty());
+
+    let mut lost = FallbackBackend::new(Down, Down);
+    assert!(lost.write_all(b"<14>lost").is_err());
+}
@@ -0,0 +1,152 @@
+//! Per-target level filtering for `BasicLogger`
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+use format::Severity;
+
+#[cfg(target_os = "espidf")]
+use errors::*;
+
+/// the filter spec of the `ESP_SYSLOG_FILTER` environment variable at build time, applied by
+/// `init` and the `init_*` functions
+pub(crate) const BUILD_FILTER: Option<&str> = option_env!("ESP_SYSLOG_FILTER");
+
+/// Level overrides keyed by `log` target, e.g. `wifi` or `app::sensors`
+///
+/// An override applies to its target and every target nested below it (`wifi` covers
+/// `wifi::scan`); the most specific one wins. Targets without an override use the default
+/// level, if one was set, and are not filtered otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct TargetLevels {
+    default: Option<LevelFilter>,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl TargetLevels {
+    pub fn has_default(&self) -> bool {
+        self.default.is_some()
+    }
+
+    pub fn set_default(&mut self, level: LevelFilter) {
+        self.default = Some(level);
+    }
+
+    pub fn set(&mut self, target: &str, level: LevelFilter) {
+        match self.targets.iter_mut().find(|(name, _)| name == target) {
+            Some(entry) => entry.1 = level,
+            None => self.targets.push((target.to_string(), level)),
+        }
+    }
+
+    /// applies the `RUST_LOG`-style filter `spec`, as understood by `env_logger`
+    ///
+    /// `spec` is a comma-separated list of `target=level` overrides and of a bare level for
+    /// the default, e.g. `warn,wifi=debug,app::sensors=off`; a bare target gets `trace`.
+    /// Levels are case-insensitive, and directives that don't parse are skipped.
+    pub fn apply(&mut self, spec: &str) {
+        for directive in spec.split(',').map(str::trim) {
+            let mut parts = directive.splitn(2, '=');
+            let target = parts.next().unwrap_or("").trim();
+            match parts.next().map(str::trim) {
+                Some(level) if !target.is_empty() => {
+                    if let Ok(level) = LevelFilter::from_str(level) {
+                        self.set(target, level);
+                    }
+                }
+                Some(_) => {}
+                None if target.is_empty() => {}
+                None => match LevelFilter::from_str(target) {
+                    Ok(level) => self.set_default(level),
+                    Err(_) => self.set(target, LevelFilter::Trace),
+                },
+            }
+        }
+    }
+
+    /// returns the level that applies to `target`
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(name, _)| covers(name, target))
+            .max_by_key(|(name, _)| name.len())
+            .map_or(self.fallback(), |&(_, level)| level)
+    }
+
+    /// returns the most verbose level of any target, to be used as `log::max_level`
+    pub fn max(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.fallback(), ::std::cmp::max)
+    }
+
+    fn fallback(&self) -> LevelFilter {
+        self.default.unwrap_or(LevelFilter::Trace)
+    }
+}
+
+/// reads a filter spec stored as a string in NVS, see `BasicLogger::set_filter`, e.g. to
+/// raise the verbosity of a single device in the field
+#[cfg(target_os = "espidf")]
+pub fn filter_from_nvs<T: esp_idf_svc::nvs::NvsPartitionId>(
+    nvs: &esp_idf_svc::nvs::EspNvs<T>,
+    name: &str,
+) -> Result<Option<String>> {
+    let mut buf = [0; 256];
+    nvs.get_str(name, &mut buf)
+        .map_err(Error::initialization)
+        .map(|spec| spec.map(str::to_string))
+}
+
+/// returns the most verbose level whose messages `BasicLogger` sends at `severity` or above
+pub(crate) fn severity_filter(severity: Severity) -> LevelFilter {
+    match severity {
+        Severity::LOG_EMERG | Severity::LOG_ALERT | Severity::LOG_CRIT => LevelFilter::Off,
+        Severity::LOG_ERR => LevelFilter::Error,
+        Severity::LOG_WARNING => LevelFilter::Warn,
+        Severity::LOG_NOTICE | Severity::LOG_INFO => LevelFilter::Info,
+        Severity::LOG_DEBUG => LevelFilter::Trace,
+    }
+}
+
+fn covers(name: &str, target: &str) -> bool {
+    target
+        .strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+#[test]
+fn test_target_levels() {
+    let mut levels = TargetLevels::default();
+    assert_eq!(levels.level("main"), LevelFilter::Trace);
+
+    levels.set_default(LevelFilter::Info);
+    levels.set("wifi", LevelFilter::Debug);
+    levels.set("wifi::scan", LevelFilter::Off);
+
+    assert_eq!(levels.level("main"), LevelFilter::Info);
+    assert_eq!(levels.level("wifi"), LevelFilter::Debug);
+    assert_eq!(levels.level("wifi::connect"), LevelFilter::Debug);
+    assert_eq!(levels.level("wifi::scan::passive"), LevelFilter::Off);
+    assert_eq!(levels.level("wifimgr"), LevelFilter::Info);
+    assert_eq!(levels.max(), LevelFilter::Debug);
+
+    levels.set("wifi", LevelFilter::Warn);
+    assert_eq!(levels.level("wifi"), LevelFilter::Warn);
+    assert_eq!(levels.max(), LevelFilter::Info);
+}
+
+#[test]
+fn test_filter_spec() {
+    let mut levels = TargetLevels::default();
+    levels.set("app", LevelFilter::Error);
+    levels.apply("warn, wifi=DEBUG,app::sensors=off,mqtt,http=loud,=info,");
+
+    assert_eq!(levels.level("main"), LevelFilter::Warn);
+    assert_eq!(levels.level("wifi::scan"), LevelFilter::Debug);
+    assert_eq!(levels.level("app"), LevelFilter::Error);
+    assert_eq!(levels.level("app::sensors"), LevelFilter::Off);
+    assert_eq!(levels.level("mqtt"), LevelFilter::Trace);
+    assert_eq!(levels.level("http"), LevelFilter::Warn);
+}
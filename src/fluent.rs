@@ -0,0 +1,302 @@
+//! Fluentd/Fluent Bit `forward` protocol backend, enabled by the `fluentd` feature
+//!
+//! Every frame is parsed back and sent as one event in Message Mode, `[tag, time, record]`
+//! encoded with msgpack over TCP. The record holds the fields Fluentd's own syslog input
+//! produces (`host`, `ident`, `pid`, `message`...), the numeric `severity` and `facility`, and
+//! the SD-PARAMs as `exampleSDID@32473.iut` entries; frames `validate` rejects, e.g. those of
+//! a custom formatter, are sent as a bare `message`.
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use tcp::ReconnectingTcpStream;
+use validate::validate;
+use Confirm;
+
+/// A backend sending every frame as an event to a Fluentd or Fluent Bit `forward` input
+pub struct FluentForward {
+    stream: ReconnectingTcpStream,
+    tag: String,
+    ack_timeout: Option<Duration>,
+    chunks: u64,
+}
+
+impl FluentForward {
+    /// connects to the `forward` input at `server`, tagging events with `tag`
+    pub fn connect(server: SocketAddr, tag: &str) -> io::Result<FluentForward> {
+        Ok(FluentForward {
+            stream: ReconnectingTcpStream::connect(server)?,
+            tag: tag.to_string(),
+            ack_timeout: None,
+            chunks: 0,
+        })
+    }
+
+    /// makes every event wait for the server's acknowledgment, for up to `timeout`
+    ///
+    /// Writing fails if the acknowledgment doesn't arrive in time.
+    pub fn with_ack(mut self, timeout: Duration) -> FluentForward {
+        self.ack_timeout = Some(timeout);
+        self
+    }
+
+    fn chunk_id(&mut self) -> String {
+        self.chunks += 1;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        format!("{:x}-{:x}", now, self.chunks)
+    }
+
+    fn event(&self, frame: &[u8], chunk: Option<&str>) -> Vec<u8> {
+        let mut event = Vec::new();
+        let message = validate(frame).ok();
+        let time = message
+            .as_ref()
+            .and_then(|message| message.timestamp.as_ref())
+            .and_then(|timestamp| OffsetDateTime::parse(timestamp, &Rfc3339).ok())
+            .unwrap_or_else(OffsetDateTime::now_utc);
+
+        array_len(&mut event, if chunk.is_some() { 4 } else { 3 });
+        string(&mut event, &self.tag);
+        event_time(&mut event, time);
+        match message {
+            Some(message) => {
+                let severity = message.severity().to_string();
+                let facility = message.facility().to_string();
+                let mut record = vec![
+                    ("message", &message.message[..]),
+                    ("severity", &severity[..]),
+                    ("facility", &facility[..]),
+                ];
+                let fields = [
+                    ("host", &message.hostname),
+                    ("ident", &message.app_name),
+                    ("pid", &message.proc_id),
+                    ("msgid", &message.msg_id),
+                ];
+                for &(key, value) in &fields {
+                    if let Some(ref value) = *value {
+                        record.push((key, value));
+                    }
+                }
+                let params: Vec<(String, &str)> = message
+                    .structured_data
+                    .iter()
+                    .flat_map(|(id, params)| {
+                        params
+                            .iter()
+                            .map(move |(name, value)| (format!("{}.{}", id, name), &value[..]))
+                    })
+                    .collect();
+
+                map_len(&mut event, record.len() + params.len());
+                for (key, value) in record {
+                    string(&mut event, key);
+                    string(&mut event, value);
+                }
+                for (key, value) in params {
+                    string(&mut event, &key);
+                    string(&mut event, value);
+                }
+            }
+            None => {
+                map_len(&mut event, 1);
+                string(&mut event, "message");
+                string(&mut event, &String::from_utf8_lossy(frame));
+            }
+        }
+        if let Some(chunk) = chunk {
+            map_len(&mut event, 1);
+            string(&mut event, "chunk");
+            string(&mut event, chunk);
+        }
+        event
+    }
+
+    fn wait_ack(&mut self, chunk: &str, timeout: Duration) -> io::Result<()> {
+        self.stream.flush()?;
+        let stream = self.stream.get_ref().ok_or(io::ErrorKind::NotConnected)?;
+        let previous = stream.read_timeout()?;
+        stream.set_read_timeout(Some(timeout))?;
+        let ack = read_ack(&mut &*stream);
+        stream.set_read_timeout(previous)?;
+        match ack? {
+            ref ack if ack == chunk => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected forward acknowledgment",
+            )),
+        }
+    }
+}
+
+impl Write for FluentForward {
+    /// Sends `frame` as one event, waiting for its acknowledgment if enabled
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        match self.ack_timeout {
+            Some(timeout) => {
+                let chunk = self.chunk_id();
+                let event = self.event(frame, Some(&chunk));
+                self.stream.write_all(&event)?;
+                self.wait_ack(&chunk, timeout)?;
+            }
+            None => {
+                let event = self.event(frame, None);
+                self.stream.write_all(&event)?;
+            }
+        }
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Confirm for FluentForward {
+    /// Events are confirmed as they are written with `with_ack`; without it, only sending
+    /// everything written so far is waited for
+    fn confirm(&mut self, timeout: Duration) -> io::Result<()> {
+        let stream = match self.stream.get_ref() {
+            Some(stream) => stream,
+            None => return self.stream.flush(),
+        };
+        let previous = stream.write_timeout()?;
+        stream.set_write_timeout(Some(timeout))?;
+        let flushed = self.stream.flush();
+        if let Some(stream) = self.stream.get_ref() {
+            stream.set_write_timeout(previous)?;
+        }
+        flushed
+    }
+}
+
+fn array_len(out: &mut Vec<u8>, len: usize) {
+    if len < 16 {
+        out.push(0x90 | len as u8);
+    } else {
+        out.push(0xdc);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+}
+
+fn map_len(out: &mut Vec<u8>, len: usize) {
+    if len < 16 {
+        out.push(0x80 | len as u8);
+    } else {
+        out.push(0xde);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+}
+
+fn string(out: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        out.push(0xa0 | len as u8);
+    } else if len < 0x100 {
+        out.extend_from_slice(&[0xd9, len as u8]);
+    } else if len < 0x10000 {
+        out.push(0xda);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(0xdb);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// writes the `EventTime` extension, seconds and nanoseconds
+fn event_time(out: &mut Vec<u8>, time: OffsetDateTime) {
+    out.extend_from_slice(&[0xd7, 0x00]);
+    out.extend_from_slice(&(time.unix_timestamp() as u32).to_be_bytes());
+    out.extend_from_slice(&time.nanosecond().to_be_bytes());
+}
+
+fn read_string<R: Read>(input: &mut R) -> io::Result<String> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    let len = match byte[0] {
+        b @ 0xa0..=0xbf => (b & 0x1f) as usize,
+        0xd9 => {
+            input.read_exact(&mut byte)?;
+            byte[0] as usize
+        }
+        0xda => {
+            let mut len = [0; 2];
+            input.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as usize
+        }
+        _ => return Err(io::ErrorKind::InvalidData.into()),
+    };
+    let mut s = vec![0; len];
+    input.read_exact(&mut s)?;
+    String::from_utf8(s).map_err(|_| io::ErrorKind::InvalidData.into())
+}
+
+/// reads a `{"ack": chunk}` response, returning the chunk
+fn read_ack<R: Read>(input: &mut R) -> io::Result<String> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    if byte[0] != 0x81 || read_string(input)? != "ack" {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    read_string(input)
+}
+
+#[test]
+fn test_forward_with_ack() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut forward = FluentForward::connect(listener.local_addr().unwrap(), "device.logs")
+        .unwrap()
+        .with_ack(Duration::from_secs(5));
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut event = vec![0; 256];
+        let len = stream.read(&mut event).unwrap();
+        event.truncate(len);
+        // the chunk id is the last string of the event
+        let chunk = event.rsplit(|&b| b & 0xe0 == 0xa0).next().unwrap().to_vec();
+        let mut ack = vec![0x81];
+        string(&mut ack, "ack");
+        string(&mut ack, ::std::str::from_utf8(&chunk).unwrap());
+        stream.write_all(&ack).unwrap();
+        event
+    });
+
+    let frame = b"<11>1 2024-05-01T10:00:00Z esp32 app 12 boot [reading@32473 temp=\"23.5\"] hello";
+    forward.write_all(frame).unwrap();
+
+    let event = server.join().unwrap();
+    let mut expected = vec![0x94];
+    string(&mut expected, "device.logs");
+    expected.extend_from_slice(&[0xd7, 0x00, 0x66, 0x32, 0x12, 0xa0, 0, 0, 0, 0]);
+    expected.push(0x88);
+    for s in [
+        "message",
+        "hello",
+        "severity",
+        "3",
+        "facility",
+        "1",
+        "host",
+        "esp32",
+        "ident",
+        "app",
+        "pid",
+        "12",
+        "msgid",
+        "boot",
+        "reading@32473.temp",
+        "23.5",
+    ] {
+        string(&mut expected, s);
+    }
+    assert!(event.starts_with(&expected));
+}
@@ -1,14 +1,25 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fmt::Display;
-use std::io::Write;
-use ::{get_hostname, time};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+#[cfg(feature = "timestamps")]
+use time;
 
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, Record};
+
+use annotate::MessageFields;
+#[cfg(feature = "timestamps")]
+use clock::Clock;
 use errors::*;
 use facility::Facility;
+use hostname::HostnameSource;
+use sanitize::{HeaderField, Msg, SdName, SdValue, Tag, APP_NAME_MAX, HOSTNAME_MAX, MSGID_MAX};
 use Priority;
 
 #[allow(non_camel_case_types)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Severity {
     LOG_EMERG,
     LOG_ALERT,
@@ -20,6 +31,65 @@ pub enum Severity {
     LOG_DEBUG,
 }
 
+impl Severity {
+    /// returns the lowercase keyword of the severity, e.g. `warning`, as syslog(3) names it
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::LOG_EMERG => "emerg",
+            Severity::LOG_ALERT => "alert",
+            Severity::LOG_CRIT => "crit",
+            Severity::LOG_ERR => "err",
+            Severity::LOG_WARNING => "warning",
+            Severity::LOG_NOTICE => "notice",
+            Severity::LOG_INFO => "info",
+            Severity::LOG_DEBUG => "debug",
+        }
+    }
+
+    /// returns the severity of a PRI value
+    #[cfg(feature = "mqtt")]
+    pub(crate) fn of(priority: Priority) -> Severity {
+        [
+            Severity::LOG_EMERG,
+            Severity::LOG_ALERT,
+            Severity::LOG_CRIT,
+            Severity::LOG_ERR,
+            Severity::LOG_WARNING,
+            Severity::LOG_NOTICE,
+            Severity::LOG_INFO,
+            Severity::LOG_DEBUG,
+        ][usize::from(priority & 7)]
+    }
+}
+
+/// The severity `BasicLogger` sends the messages of a level at, `Trace` going to `LOG_DEBUG`
+impl From<Level> for Severity {
+    fn from(level: Level) -> Severity {
+        match level {
+            Level::Error => Severity::LOG_ERR,
+            Level::Warn => Severity::LOG_WARNING,
+            Level::Info => Severity::LOG_INFO,
+            Level::Debug | Level::Trace => Severity::LOG_DEBUG,
+        }
+    }
+}
+
+/// The level of a severity, for the severities `From<Level>` returns; the others (e.g. `LOG_CRIT`)
+/// have no `log` counterpart and are returned as the error, for the caller to pick one
+impl TryFrom<Severity> for Level {
+    type Error = Severity;
+
+    fn try_from(severity: Severity) -> ::std::result::Result<Level, Severity> {
+        match severity {
+            Severity::LOG_ERR => Ok(Level::Error),
+            Severity::LOG_WARNING => Ok(Level::Warn),
+            Severity::LOG_INFO => Ok(Level::Info),
+            Severity::LOG_DEBUG => Ok(Level::Debug),
+            other => Err(other),
+        }
+    }
+}
+
 pub trait LogFormat<T> {
     fn format<W: Write>(&self, w: &mut W, severity: Severity, message: T) -> Result<()>;
 
@@ -56,32 +126,205 @@ pub trait LogFormat<T> {
     }
 }
 
+/// the `max_message_len` of the formatters by default
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 1024;
+
+/// What a formatter does with a frame longer than its `max_message_len`
+///
+/// Datagrams over the MTU are fragmented, if not dropped by the network stack, and servers
+/// drop or cut the messages over their own limit, so a long stack trace is better shortened,
+/// or split, before it is sent.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Oversize {
+    /// cuts the frame to `max_message_len`, ending it with `...`
+    #[default]
+    Truncate,
+    /// sends the rest of the message in as many frames as needed, each one with the header of
+    /// the first; truncates if the header alone is too long
+    Split,
+}
+
+impl Oversize {
+    /// writes `frame`, whose first `header_len` bytes are the header, in frames of at most
+    /// `max_len` bytes, every one with a single write
+    ///
+    /// The frame is cut, or split, in place: every part is written from `frame` itself, the
+    /// header being copied in front of the rest of the message, over bytes already sent.
+    fn write_frame<W: Write>(
+        self,
+        w: &mut W,
+        frame: &mut Vec<u8>,
+        header_len: usize,
+        max_len: usize,
+    ) -> io::Result<()> {
+        if frame.len() <= max_len {
+            return w.write_all(frame);
+        }
+        if self == Oversize::Split && header_len < max_len {
+            let mut header = 0;
+            let mut rest = header_len;
+            while rest < frame.len() {
+                let len = match char_floor(&frame[rest..], max_len - header_len) {
+                    // a character longer than the room left, sent whole
+                    0 => char_floor(&frame[rest..], 4).max(1),
+                    len => len,
+                };
+                w.write_all(&frame[header..rest + len])?;
+                rest += len;
+                frame.copy_within(header..header + header_len, rest - header_len);
+                header = rest - header_len;
+            }
+            return Ok(());
+        }
+        let cut = char_floor(frame, max_len.saturating_sub(3));
+        frame.truncate(cut);
+        frame.extend_from_slice(b"...");
+        w.write_all(frame)
+    }
+}
+
+/// the capacity of the frame buffer a thread keeps between messages, see `with_frame`
+const KEPT_FRAME_CAPACITY: usize = 4096;
+
+/// runs `build` with an empty buffer for a frame, the one the thread reuses for its messages
+///
+/// Once the buffer grew to the length of the frames sent, formatting a message allocates
+/// nothing, sparing the heap of a long-running device the fragmentation of a buffer per
+/// message. A frame built while the buffer is in use, by a `Display` implementation logging
+/// itself, gets a new one.
+pub(crate) fn with_frame<R, F: FnOnce(&mut Vec<u8>) -> R>(build: F) -> R {
+    thread_local!(static FRAME: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) });
+    FRAME.with(|frame| match frame.try_borrow_mut() {
+        Ok(mut frame) => {
+            frame.clear();
+            let built = build(&mut frame);
+            frame.clear();
+            frame.shrink_to(KEPT_FRAME_CAPACITY);
+            built
+        }
+        Err(_) => build(&mut Vec::new()),
+    })
+}
+
+/// returns the longest length of at most `len` bytes of the UTF-8 `text` ending on a character
+fn char_floor(text: &[u8], len: usize) -> usize {
+    if len >= text.len() {
+        return text.len();
+    }
+    (0..=len)
+        .rev()
+        .find(|&i| text[i] & 0xC0 != 0x80)
+        .unwrap_or(0)
+}
+
+/// Formats RFC 3164 (BSD syslog) messages
+///
+/// The MSG is sent on a single line, every run of ASCII control characters, e.g. a line
+/// break, being replaced by a space, so the non-transparent framing doesn't split it.
 #[derive(Clone, Debug)]
 pub struct Formatter3164 {
     pub facility: Facility,
+    /// see `HostnameSource`, the hostname set by `set_hostname` by default; left out along
+    /// with the TIMESTAMP without the `timestamps` feature
+    pub hostname: HostnameSource,
     pub process: String,
-    pub pid: u32,
+    /// sent as `process[pid]:`, or just `process:` when `None`, e.g. on targets without
+    /// meaningful process ids
+    pub pid: Option<u32>,
+    #[cfg(feature = "timestamps")]
+    pub clock: Clock,
+    /// the length of the longest frame sent, in bytes, see `Oversize`; 1024 by default, as RFC
+    /// 3164 requires
+    pub max_message_len: usize,
+    pub oversize: Oversize,
+    /// the time zone of the TIMESTAMP, which has no offset the collector could convert it
+    /// with; the device's local time by default, as BSD syslog sends
+    pub time_zone: TimeZone,
+    /// sends the process as the TAG RFC 3164 4.1.3 allows, its first 32 alphanumeric
+    /// characters or `main` without any, and leaves a `pid` of 0 out; `false` by default,
+    /// sending both as they are
+    pub strict_tag: bool,
 }
 
 impl<T: Display> LogFormat<T> for Formatter3164 {
     fn format<W: Write>(&self, w: &mut W, severity: Severity, message: T) -> Result<()> {
-        let format =
-            time::format_description::parse("[month repr:short] [day] [hour]:[minute]:[second]")
-                .unwrap();
-        let hostname = get_hostname();
+        self.format_as(w, severity, &self.process, message)
+    }
+}
+
+impl<'a, T: Display> LogFormat<WithProcess<'a, T>> for Formatter3164 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: WithProcess<'a, T>,
+    ) -> Result<()> {
+        self.format_as(w, severity, log_message.process, log_message.message)
+    }
+}
+
+impl Formatter3164 {
+    /// formats `message` as sent by `process`
+    fn format_as<W: Write, T: Display>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        process: &str,
+        message: T,
+    ) -> Result<()> {
+        #[cfg(feature = "timestamps")]
+        let timestamp = self.timestamp();
+        with_frame(|frame| {
+            write!(frame, "<{}>", encode_priority(severity, self.facility))
+                .map_err(Error::Format)?;
+            // Without a TIMESTAMP, collectors would take the HOSTNAME for the TAG: they add
+            // both themselves, as RFC 3164 4.3.3 has relays do
+            #[cfg(feature = "timestamps")]
+            self.hostname
+                .with(|hostname| write!(frame, "{timestamp} {hostname} "))
+                .map_err(Error::Format)?;
+            if self.strict_tag {
+                let pid = self.pid.filter(|&pid| pid != 0);
+                write!(frame, "{}{}: ", Tag(process), Pid(pid))
+            } else {
+                write!(frame, "{}{}: ", process, Pid(self.pid))
+            }
+            .map_err(Error::Format)?;
+            let header_len = frame.len();
+            write!(frame, "{}", Msg(message)).map_err(Error::Format)?;
+            self.oversize
+                .write_frame(w, frame, header_len, self.max_message_len)
+                .map_err(Error::from)
+        })
+    }
+
+    #[cfg(feature = "timestamps")]
+    fn timestamp(&self) -> Timestamp3164 {
+        Timestamp3164(self.time_zone.convert(self.clock.now()))
+    }
+}
+
+/// The TIMESTAMP of a RFC 3164 message, `Mmm dd hh:mm:ss`, the day padded with a space as RFC
+/// 3164 4.1.2 requires: `Mar  5 07:08:09`
+#[cfg(feature = "timestamps")]
+struct Timestamp3164(time::OffsetDateTime);
+
+#[cfg(feature = "timestamps")]
+impl Display for Timestamp3164 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let time = self.0;
         write!(
-            w,
-            "<{}>{} {} {}[{}]: {}",
-            encode_priority(severity, self.facility),
-            now_local()
-                .map(|timestamp| timestamp.format(&format).unwrap())
-                .unwrap(),
-            hostname,
-            self.process,
-            self.pid,
-            message
+            f,
+            "{} {:>2} {:02}:{:02}:{:02}",
+            MONTHS[time.month() as usize - 1],
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
         )
-        .chain_err(|| ErrorKind::Format)
     }
 }
 
@@ -95,42 +338,341 @@ impl Default for Formatter3164 {
     /// [specified by POSIX]: https://pubs.opengroup.org/onlinepubs/9699919799/functions/closelog.html
     fn default() -> Self {
         let process = "main".to_string();
-        let pid: u32 = 0;
+        let pid = Some(0);
 
         Self {
             facility: Default::default(),
+            hostname: HostnameSource::Global,
             process,
             pid,
+            #[cfg(feature = "timestamps")]
+            clock: Clock::System,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            oversize: Oversize::Truncate,
+            time_zone: TimeZone::Local,
+            strict_tag: false,
+        }
+    }
+}
+
+/// The time zone of the timestamps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeZone {
+    /// with a `Z` offset
+    #[default]
+    Utc,
+    /// with the offset of the local time zone, e.g. `+02:00`; UTC on hosts where the `time`
+    /// crate can't tell it
+    Local,
+}
+
+impl TimeZone {
+    /// returns `time` in this time zone
+    #[cfg(feature = "timestamps")]
+    fn convert(self, time: time::OffsetDateTime) -> time::OffsetDateTime {
+        match self {
+            TimeZone::Utc => time.to_offset(time::UtcOffset::UTC),
+            TimeZone::Local => match now_local() {
+                Ok(local) => time.to_offset(local.offset()),
+                Err(_) => time.to_offset(time::UtcOffset::UTC),
+            },
+        }
+    }
+}
+
+/// The most digits in a TIME-SECFRAC, a microsecond
+pub const MAX_SECOND_DIGITS: u8 = 6;
+
+/// A RFC 5424 TIMESTAMP (a RFC 3339 one with at most 6 fractional digits and no leap second),
+/// NILVALUE when the time is unknown
+#[cfg(feature = "timestamps")]
+struct Timestamp {
+    time: Option<time::OffsetDateTime>,
+    digits: u8,
+}
+
+#[cfg(feature = "timestamps")]
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = match self.time {
+            Some(time) => time,
+            None => return f.write_str("-"),
+        };
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            time.year(),
+            time.month() as u8,
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        )?;
+        let digits = self.digits.min(MAX_SECOND_DIGITS);
+        if digits > 0 {
+            let fraction = time.nanosecond() / 10u32.pow(9 - digits as u32);
+            write!(f, ".{:0width$}", fraction, width = digits as usize)?;
+        }
+        let offset = time.offset();
+        if offset.is_utc() {
+            f.write_str("Z")
+        } else {
+            let (hours, minutes, _) = offset.as_hms();
+            let sign = if offset.is_negative() { '-' } else { '+' };
+            write!(f, "{}{:02}:{:02}", sign, hours.abs(), minutes.abs())
         }
     }
 }
 
+/// The `[pid]` part of a RFC 3164 TAG, empty without a pid
+struct Pid(Option<u32>);
+
+impl Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(pid) => write!(f, "[{pid}]"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The PROCID of a RFC 5424 header, the NILVALUE without a pid
+struct ProcId(Option<u32>);
+
+impl Display for ProcId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(pid) => write!(f, "{pid}"),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// A message with ad-hoc `key=value` fields attached, as sent by `Logger::info_with` and friends.
+///
+/// `Formatter3164` appends the fields to the message as ` key=value` pairs, while
+/// `Formatter5424` sends them as the SD-PARAMs of a `FIELDS_SD_ID` element.
+#[derive(Clone, Copy, Debug)]
+pub struct WithFields<'a, T> {
+    pub message: T,
+    pub fields: &'a [(&'a str, &'a str)],
+}
+
+impl<'a, T: Display> Display for WithFields<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for &(name, value) in self.fields {
+            write!(f, " {name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A message sent as from another process than the formatter's, e.g. a subsystem of the
+/// firmware (`wifi`, `ota`...), as sent by `Logger::with_process`
+///
+/// `process` is the TAG of `Formatter3164` messages, and the APP-NAME of `Formatter5424` ones.
+#[derive(Clone, Copy, Debug)]
+pub struct WithProcess<'a, T> {
+    pub process: &'a str,
+    pub message: T,
+}
+
+/// A `log` record sent with its key-values, e.g. `info!(temp = 23.5; "reading")`
+///
+/// `Formatter3164` appends them to the arguments as ` key=value` pairs, as it does the fields of
+/// a `WithFields` message, while `Formatter5424` sends them as the SD-PARAMs of a
+/// `FIELDS_SD_ID` element, as it does a `&Record`.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordMessage<'a, 'b>(pub &'a Record<'b>);
+
+impl<'a, 'b> Display for RecordMessage<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.args())?;
+        self.0
+            .key_values()
+            .visit(&mut KeyValues(f))
+            .map_err(|_| fmt::Error)
+    }
+}
+
+/// writes every key-value it visits as ` key=value`
+struct KeyValues<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b, 'kvs> VisitSource<'kvs> for KeyValues<'a, 'b> {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> ::std::result::Result<(), kv::Error> {
+        write!(self.0, " {key}={value}").map_err(kv::Error::from)
+    }
+}
+
+/// SD-ID of the element `Formatter5424` uses for the fields of a `WithFields` message
+pub const FIELDS_SD_ID: &str = "fields@32473";
+
 /// RFC 5424 structured data
 pub type StructuredData = HashMap<String, HashMap<String, String>>;
 
+/// A single RFC 5424 SD-ELEMENT that writes its own SD-PARAMs, without going through a
+/// `StructuredData` map.
+///
+/// With the `derive` feature this can be implemented with `#[derive(StructuredData)]`.
+pub trait SdElement {
+    /// The SD-ID of the element, e.g. `exampleSDID@32473`
+    const SD_ID: &'static str;
+
+    /// Writes every SD-PARAM of the element, each one preceded by a space
+    fn write_params<W: Write>(&self, w: &mut W) -> io::Result<()>;
+}
+
+/// Writes a single ` name="value"` SD-PARAM
+#[doc(hidden)]
+pub fn write_sd_param<W: Write, V: Display + ?Sized>(
+    w: &mut W,
+    name: &str,
+    value: &V,
+) -> io::Result<()> {
+    write!(w, " {}=\"{}\"", SdName(name), SdValue(value))
+}
+
+/// Formats RFC 5424 messages
+///
+/// Fields are sanitized so strict collectors accept the messages: the header fields keep
+/// printable US-ASCII only, truncated to their maximum lengths, with NILVALUE (`-`) when
+/// nothing is left; SD-IDs and SD-NAMEs lose the characters they can't hold, `"`, `\` and `]`
+/// are escaped in SD-PARAM values, and every run of ASCII control characters of the MSG, e.g. a
+/// line break, is replaced by a space.
 #[derive(Clone, Debug)]
 pub struct Formatter5424 {
     pub facility: Facility,
+    /// see `HostnameSource`, the hostname set by `set_hostname` by default
+    pub hostname: HostnameSource,
     pub process: String,
-    pub pid: u32,
+    /// the PROCID, the NILVALUE (`-`) when `None`
+    pub pid: Option<u32>,
+    #[cfg(feature = "timestamps")]
+    pub clock: Clock,
+    /// the length of the longest frame sent, in bytes, see `Oversize`; 1024 by default
+    pub max_message_len: usize,
+    pub oversize: Oversize,
+    /// the fractional second digits of the timestamps, up to `MAX_SECOND_DIGITS`; 6 by
+    /// default
+    pub second_digits: u8,
+    pub time_zone: TimeZone,
 }
 
 impl Formatter5424 {
+    #[cfg(feature = "timestamps")]
+    fn timestamp(&self) -> Timestamp {
+        let time = self
+            .clock
+            .try_now()
+            .map(|time| self.time_zone.convert(time));
+        Timestamp {
+            time,
+            digits: self.second_digits,
+        }
+    }
+
+    /// NILVALUE, the collector timestamping the messages on reception
+    #[cfg(not(feature = "timestamps"))]
+    fn timestamp(&self) -> &'static str {
+        "-"
+    }
+
+    /// appends `message` to the header in `frame`, and writes the frame
+    fn write_message<W: Write, T: Display>(
+        &self,
+        w: &mut W,
+        frame: &mut Vec<u8>,
+        message: T,
+    ) -> Result<()> {
+        let header_len = frame.len();
+        write!(frame, "{}", Msg(message)).map_err(Error::Format)?;
+        self.oversize
+            .write_frame(w, frame, header_len, self.max_message_len)
+            .map_err(Error::from)
+    }
+
     pub fn format_5424_structured_data(&self, data: StructuredData) -> String {
-        if data.is_empty() {
-            "-".to_string()
-        } else {
-            let mut res = String::new();
-            for (id, params) in &data {
-                res = res + "[" + id;
-                for (name, value) in params {
-                    res = res + " " + name + "=\"" + value + "\"";
-                }
-                res += "]";
+        SdElements(&data).to_string()
+    }
+}
+
+/// The STRUCTURED-DATA of a `StructuredData` map, or NILVALUE when empty
+struct SdElements<'a>(&'a StructuredData);
+
+impl<'a> Display for SdElements<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("-");
+        }
+        for (id, params) in self.0 {
+            write!(f, "[{}", SdName(id))?;
+            for (name, value) in params {
+                write!(f, " {}=\"{}\"", SdName(name), SdValue(value))?;
             }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// A RFC 5424 MSGID, 1 to 32 printable US-ASCII characters
+///
+/// Numeric ids, the only ones the tuple messages took at first, convert to it:
+///
+/// ```no_run
+/// # use esp_syslog::{Formatter5424, LogFormat, MsgId, Severity};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (formatter, mut w) = (Formatter5424::default(), Vec::new());
+/// # let data = std::collections::HashMap::new();
+/// formatter.format(&mut w, Severity::LOG_INFO, (MsgId::new("DHCPACK")?, data.clone(), "lease renewed"))?;
+/// formatter.format(&mut w, Severity::LOG_INFO, (1, data, "lease renewed"))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MsgId(String);
 
-            res
+impl MsgId {
+    /// fails if `msg_id` is empty, longer than 32 characters, or holds anything but printable
+    /// US-ASCII
+    pub fn new<S: Into<String>>(msg_id: S) -> Result<MsgId> {
+        let msg_id = msg_id.into();
+        if msg_id.is_empty() || msg_id.len() > MSGID_MAX {
+            return Err(Error::Validation("a MSGID is 1 to 32 characters long"));
         }
+        if !msg_id.bytes().all(|b| (b'!'..=b'~').contains(&b)) {
+            return Err(Error::Validation(
+                "a MSGID holds printable US-ASCII characters only",
+            ));
+        }
+        Ok(MsgId(msg_id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<u32> for MsgId {
+    fn from(msg_id: u32) -> MsgId {
+        MsgId(msg_id.to_string())
+    }
+}
+
+impl From<MsgId> for String {
+    fn from(msg_id: MsgId) -> String {
+        msg_id.0
+    }
+}
+
+impl Display for MsgId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
@@ -142,22 +684,306 @@ impl<T: Display> LogFormat<(u32, StructuredData, T)> for Formatter5424 {
         log_message: (u32, StructuredData, T),
     ) -> Result<()> {
         let (message_id, data, message) = log_message;
+        self.format(w, severity, (MsgId::from(message_id), data, message))
+    }
+}
 
-        write!(
-            w,
-            "<{}>1 {} {} {} {} {} {} {}", // v1
-            encode_priority(severity, self.facility),
-            time::OffsetDateTime::now_utc()
-                .format(&time::format_description::well_known::Rfc3339)
-                .unwrap(),
-            get_hostname(),
-            self.process,
-            self.pid,
-            message_id,
-            self.format_5424_structured_data(data),
-            message
-        )
-        .chain_err(|| ErrorKind::Format)
+impl<T: Display> LogFormat<(MsgId, StructuredData, T)> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: (MsgId, StructuredData, T),
+    ) -> Result<()> {
+        let (message_id, data, message) = log_message;
+
+        with_frame(|frame| {
+            self.hostname
+                .with(|hostname| {
+                    write!(
+                        frame,
+                        "<{}>1 {} {} {} {} {} {} ", // v1
+                        encode_priority(severity, self.facility),
+                        self.timestamp(),
+                        HeaderField(hostname, HOSTNAME_MAX),
+                        HeaderField(&self.process, APP_NAME_MAX),
+                        ProcId(self.pid),
+                        message_id,
+                        SdElements(&data)
+                    )
+                })
+                .map_err(Error::Format)?;
+            self.write_message(w, frame, message)
+        })
+    }
+}
+
+impl<'a, S: SdElement, T: Display> LogFormat<(u32, &'a S, T)> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: (u32, &'a S, T),
+    ) -> Result<()> {
+        let (message_id, element, message) = log_message;
+        self.format(w, severity, (MsgId::from(message_id), element, message))
+    }
+}
+
+impl<'a, S: SdElement, T: Display> LogFormat<(MsgId, &'a S, T)> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: (MsgId, &'a S, T),
+    ) -> Result<()> {
+        let (message_id, element, message) = log_message;
+
+        with_frame(|frame| {
+            self.hostname
+                .with(|hostname| {
+                    write!(
+                        frame,
+                        "<{}>1 {} {} {} {} {} [{}",
+                        encode_priority(severity, self.facility),
+                        self.timestamp(),
+                        HeaderField(hostname, HOSTNAME_MAX),
+                        HeaderField(&self.process, APP_NAME_MAX),
+                        ProcId(self.pid),
+                        message_id,
+                        SdName(S::SD_ID)
+                    )
+                    .and_then(|()| element.write_params(frame))
+                    .and_then(|()| write!(frame, "] "))
+                })
+                .map_err(Error::Format)?;
+            self.write_message(w, frame, message)
+        })
+    }
+}
+
+impl<'a, T: Display> LogFormat<WithFields<'a, T>> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: WithFields<'a, T>,
+    ) -> Result<()> {
+        self.format_fields(w, severity, &self.process, log_message)
+    }
+}
+
+impl<'a, T: Display> LogFormat<WithProcess<'a, T>> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: WithProcess<'a, T>,
+    ) -> Result<()> {
+        let message = WithFields {
+            message: log_message.message,
+            fields: &[],
+        };
+        self.format_fields(w, severity, log_message.process, message)
+    }
+}
+
+impl Formatter5424 {
+    /// formats `log_message` as sent by `process`
+    fn format_fields<W: Write, T: Display>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        process: &str,
+        log_message: WithFields<T>,
+    ) -> Result<()> {
+        with_frame(|frame| {
+            self.hostname
+                .with(|hostname| {
+                    write!(
+                        frame,
+                        "<{}>1 {} {} {} {} - {} ",
+                        encode_priority(severity, self.facility),
+                        self.timestamp(),
+                        HeaderField(hostname, HOSTNAME_MAX),
+                        HeaderField(process, APP_NAME_MAX),
+                        ProcId(self.pid),
+                        FieldsElement(log_message.fields)
+                    )
+                })
+                .map_err(Error::Format)?;
+            self.write_message(w, frame, log_message.message)
+        })
+    }
+}
+
+/// Formats the arguments of `record` straight into the frame, without a `String` in between,
+/// its key-values being sent as the SD-PARAMs of a `FIELDS_SD_ID` element
+///
+/// `Formatter3164` takes the record as a `RecordMessage`.
+impl<'a, 'b> LogFormat<&'a Record<'b>> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        record: &'a Record<'b>,
+    ) -> Result<()> {
+        let mut fields = MessageFields::new(record.level(), record.target());
+        fields.add_key_values(record.key_values());
+        let message = WithFields {
+            message: *record.args(),
+            fields: &fields.pairs(),
+        };
+        self.format(w, severity, message)
+    }
+}
+
+impl<'a, 'b> LogFormat<RecordMessage<'a, 'b>> for Formatter5424 {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        log_message: RecordMessage<'a, 'b>,
+    ) -> Result<()> {
+        self.format(w, severity, log_message.0)
+    }
+}
+
+/// The `FIELDS_SD_ID` element of a `WithFields` message, or NILVALUE without fields
+struct FieldsElement<'a>(&'a [(&'a str, &'a str)]);
+
+impl<'a> Display for FieldsElement<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "-");
+        }
+        write!(f, "[{FIELDS_SD_ID}")?;
+        for &(name, value) in self.0 {
+            write!(f, " {}=\"{}\"", SdName(name), SdValue(value))?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// RFC 5424 structured data built element by element, keeping their order
+///
+/// ```no_run
+/// # use esp_syslog::StructuredDataBuilder;
+/// let data = StructuredDataBuilder::new()
+///     .element("exampleSDID@32473")
+///     .param("iut", "3")
+///     .param("eventSource", "Application");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructuredDataBuilder {
+    elements: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl StructuredDataBuilder {
+    pub fn new() -> Self {
+        StructuredDataBuilder::default()
+    }
+
+    /// starts a new element, receiving the following `param`s
+    pub fn element<S: Into<String>>(mut self, id: S) -> Self {
+        self.elements.push((id.into(), Vec::new()));
+        self
+    }
+
+    /// adds a SD-PARAM to the last element
+    ///
+    /// Panics if no element was started.
+    pub fn param<N: Into<String>, V: Display>(mut self, name: N, value: V) -> Self {
+        self.elements
+            .last_mut()
+            .expect("StructuredDataBuilder::param called before element")
+            .1
+            .push((name.into(), value.to_string()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+/// Writes the elements, or NILVALUE without any
+impl Display for StructuredDataBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.elements.is_empty() {
+            return write!(f, "-");
+        }
+        for (id, params) in &self.elements {
+            write!(f, "[{}", SdName(id))?;
+            for (name, value) in params {
+                write!(f, " {}=\"{}\"", SdName(name), SdValue(value))?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+/// An RFC 5424 message, with an optional MSGID and structured data, for `Formatter5424`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Message5424 {
+    /// sent as NILVALUE when `None`
+    pub msg_id: Option<String>,
+    /// the APP-NAME, the formatter's `process` when `None`
+    pub process: Option<String>,
+    pub structured_data: StructuredDataBuilder,
+    pub message: String,
+}
+
+impl Message5424 {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Message5424 {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    /// sets the MSGID, sanitized when sent, or taken as is from a `MsgId`
+    pub fn msg_id<S: Into<String>>(mut self, msg_id: S) -> Self {
+        self.msg_id = Some(msg_id.into());
+        self
+    }
+
+    /// sends the message as from `process` rather than the formatter's, see `WithProcess`
+    pub fn process<S: Into<String>>(mut self, process: S) -> Self {
+        self.process = Some(process.into());
+        self
+    }
+
+    pub fn structured_data(mut self, structured_data: StructuredDataBuilder) -> Self {
+        self.structured_data = structured_data;
+        self
+    }
+}
+
+impl LogFormat<Message5424> for Formatter5424 {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: Message5424) -> Result<()> {
+        with_frame(|frame| {
+            self.hostname
+                .with(|hostname| {
+                    write!(
+                        frame,
+                        "<{}>1 {} {} {} {} {} {} ",
+                        encode_priority(severity, self.facility),
+                        self.timestamp(),
+                        HeaderField(hostname, HOSTNAME_MAX),
+                        HeaderField(
+                            message.process.as_deref().unwrap_or(&self.process),
+                            APP_NAME_MAX
+                        ),
+                        ProcId(self.pid),
+                        HeaderField(message.msg_id.as_deref().unwrap_or(""), MSGID_MAX),
+                        message.structured_data
+                    )
+                })
+                .map_err(Error::Format)?;
+            self.write_message(w, frame, message.message)
+        })
     }
 }
 
@@ -167,25 +993,46 @@ impl Default for Formatter5424 {
     /// The default settings are as follows:
     ///
     /// * `facility`: `LOG_USER`, as [specified by POSIX].
-    /// * `hostname`: Automatically detected using [the `hostname` crate], if possible.
+    /// * `hostname`: The one set by `set_hostname`, see `HostnameSource`.
     /// * `process`: Automatically detected using [`std::env::current_exe`], or if that fails, an empty string.
     /// * `pid`: Automatically detected using [`libc::getpid`].
     ///
     /// [`libc::getpid`]: https://docs.rs/libc/0.2/libc/fn.getpid.html
     /// [specified by POSIX]: https://pubs.opengroup.org/onlinepubs/9699919799/functions/closelog.html
     /// [`std::env::current_exe`]: https://doc.rust-lang.org/std/env/fn.current_exe.html
-    /// [the `hostname` crate]: https://crates.io/crates/hostname
     fn default() -> Self {
         // Get the defaults from `Formatter3164` and move them over.
+        Formatter3164::default().into()
+    }
+}
+
+impl From<Formatter3164> for Formatter5424 {
+    /// Takes the settings of a `Formatter3164`, a missing `pid` being sent as the NILVALUE.
+    fn from(formatter: Formatter3164) -> Self {
         let Formatter3164 {
             facility,
+            hostname,
             process,
             pid,
-        } = Default::default();
+            #[cfg(feature = "timestamps")]
+            clock,
+            max_message_len,
+            oversize,
+            // the offset RFC 5424 timestamps carry makes UTC ones as good
+            time_zone: _,
+            strict_tag: _,
+        } = formatter;
         Self {
             facility,
+            hostname,
             process,
             pid,
+            #[cfg(feature = "timestamps")]
+            clock,
+            max_message_len,
+            oversize,
+            second_digits: MAX_SECOND_DIGITS,
+            time_zone: TimeZone::Utc,
         }
     }
 }
@@ -194,15 +1041,15 @@ fn encode_priority(severity: Severity, facility: Facility) -> Priority {
     facility as u8 | severity as u8
 }
 
-#[cfg(unix)]
+#[cfg(all(feature = "timestamps", unix))]
 // On unix platforms, time::OffsetDateTime::now_local always returns an error so use UTC instead
 // https://github.com/time-rs/time/issues/380
-fn now_local() -> std::result::Result<time::OffsetDateTime, time::error::IndeterminateOffset> {
+pub(crate) fn now_local() -> std::result::Result<time::OffsetDateTime, time::error::IndeterminateOffset> {
     Ok(time::OffsetDateTime::now_utc())
 }
 
-#[cfg(not(unix))]
-fn now_local() -> std::result::Result<time::OffsetDateTime, time::error::IndeterminateOffset> {
+#[cfg(all(feature = "timestamps", not(unix)))]
+pub(crate) fn now_local() -> std::result::Result<time::OffsetDateTime, time::error::IndeterminateOffset> {
     time::OffsetDateTime::now_local()
 }
 
@@ -221,6 +1068,359 @@ fn test_formatter3164_defaults() {
     // Can't really make any assertions about the pid.
 }
 
+#[test]
+fn test_formatter3164_without_pid() {
+    let formatter = Formatter3164 {
+        pid: None,
+        ..Default::default()
+    };
+
+    let mut buf = Vec::new();
+    formatter
+        .format(&mut buf, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().ends_with("main: hello"));
+}
+
+#[test]
+fn test_strict_tag() {
+    let strict = |process: &str, pid| {
+        let formatter = Formatter3164 {
+            process: process.to_string(),
+            pid,
+            strict_tag: true,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        formatter
+            .format(&mut buf, Severity::LOG_INFO, "hello")
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    assert!(strict("ota-update", Some(0)).ends_with("otaupdate: hello"));
+    assert!(strict("ota-update", Some(42)).ends_with("otaupdate[42]: hello"));
+    assert!(strict("-", None).ends_with("main: hello"));
+}
+
+#[test]
+#[cfg(feature = "timestamps")]
+fn test_timestamp3164() {
+    // 2024-03-01 07:08:09 UTC
+    let first = time::OffsetDateTime::from_unix_timestamp(1_709_276_889).unwrap();
+    for day in 1..=31 {
+        let time = first + time::Duration::days(day - 1);
+        let expected = format!("Mar {:>2} 07:08:09", day);
+        assert_eq!(Timestamp3164(time).to_string(), expected);
+    }
+    assert_eq!(Timestamp3164(first).to_string(), "Mar  1 07:08:09");
+    assert_eq!(
+        Timestamp3164(first + time::Duration::days(9)).to_string(),
+        "Mar 10 07:08:09"
+    );
+
+    // in the offset of the time zone, which RFC 3164 doesn't carry
+    let offset = time::UtcOffset::from_hms(-8, 0, 0).unwrap();
+    assert_eq!(
+        Timestamp3164(first.to_offset(offset)).to_string(),
+        "Feb 29 23:08:09"
+    );
+
+    let formatter = Formatter3164 {
+        clock: Clock::Uptime,
+        time_zone: TimeZone::Utc,
+        ..Default::default()
+    };
+    assert_eq!(formatter.timestamp().0.offset(), time::UtcOffset::UTC);
+    let formatter = Formatter3164 {
+        clock: Clock::Uptime,
+        time_zone: TimeZone::Local,
+        ..Default::default()
+    };
+    let local = now_local().map_or(time::UtcOffset::UTC, |time| time.offset());
+    assert_eq!(formatter.timestamp().0.offset(), local);
+}
+
+#[test]
+fn test_frame_buffer() {
+    let capacity = with_frame(|frame| {
+        frame.extend_from_slice(&[b'x'; 100]);
+        // a frame built meanwhile gets a buffer of its own
+        with_frame(|nested| assert!(nested.is_empty()));
+        frame.capacity()
+    });
+    with_frame(|frame| {
+        assert!(frame.is_empty());
+        assert_eq!(frame.capacity(), capacity);
+    });
+    with_frame(|frame| frame.resize(2 * KEPT_FRAME_CAPACITY, 0));
+    with_frame(|frame| assert!(frame.capacity() <= KEPT_FRAME_CAPACITY));
+}
+
+#[test]
+fn test_oversize() {
+    /// every write as a frame
+    struct Frames(Vec<String>);
+
+    impl Write for Frames {
+        fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+            self.0.push(String::from_utf8(frame.to_vec()).unwrap());
+            Ok(frame.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let formatter = Formatter3164 {
+        max_message_len: 64,
+        ..Default::default()
+    };
+    let mut frames = Frames(Vec::new());
+    formatter
+        .format(&mut frames, Severity::LOG_INFO, "é".repeat(64))
+        .unwrap();
+    assert_eq!(frames.0.len(), 1);
+    assert!(frames.0[0].len() <= 64);
+    assert!(frames.0[0].ends_with("éé..."));
+
+    let formatter = Formatter5424 {
+        max_message_len: 80,
+        oversize: Oversize::Split,
+        ..Default::default()
+    };
+    let mut frames = Frames(Vec::new());
+    let trace = "0123456789".repeat(10);
+    formatter
+        .format(&mut frames, Severity::LOG_ERR, Message5424::new(&trace[..]))
+        .unwrap();
+    assert!(frames.0.len() > 1);
+    let header = &frames.0[0][..frames.0[0].find(" - - ").unwrap() + 5];
+    let mut rest = String::new();
+    for frame in &frames.0 {
+        assert!(frame.len() <= 80);
+        rest.push_str(frame.strip_prefix(header).unwrap());
+    }
+    assert_eq!(rest, trace);
+}
+
+#[test]
+fn test_formatter5424_sd_element() {
+    struct Reading {
+        temp: f32,
+    }
+
+    impl SdElement for Reading {
+        const SD_ID: &'static str = "sensor@32473";
+
+        fn write_params<W: Write>(&self, w: &mut W) -> io::Result<()> {
+            write_sd_param(w, "temp", &self.temp)
+        }
+    }
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            (1, &Reading { temp: 23.5 }, "hello"),
+        )
+        .unwrap();
+
+    let message = String::from_utf8(buf).unwrap();
+    assert!(message.starts_with("<14>1 "));
+    assert!(message.ends_with(" 1 [sensor@32473 temp=\"23.5\"] hello"));
+}
+
+#[test]
+fn test_with_fields() {
+    let fields = [("temp", "23.5"), ("sensor", "bme280")];
+    let message = WithFields {
+        message: "reading",
+        fields: &fields,
+    };
+
+    let mut buf = Vec::new();
+    Formatter3164::default()
+        .format(&mut buf, Severity::LOG_INFO, message)
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with("main[0]: reading temp=23.5 sensor=bme280"));
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(&mut buf, Severity::LOG_INFO, message)
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with(" main 0 - [fields@32473 temp=\"23.5\" sensor=\"bme280\"] reading"));
+}
+
+#[test]
+#[cfg(feature = "timestamps")]
+fn test_unknown_time() {
+    use clock::TimeSource;
+
+    struct Unknown;
+
+    impl TimeSource for Unknown {
+        fn now(&self) -> Option<time::OffsetDateTime> {
+            None
+        }
+    }
+
+    static UNKNOWN: Unknown = Unknown;
+    let formatter = Formatter5424 {
+        clock: Clock::Custom(&UNKNOWN),
+        ..Default::default()
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(&mut buf, Severity::LOG_INFO, Message5424::new("booting"))
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().starts_with("<14>1 - "));
+}
+
+#[test]
+#[cfg(feature = "timestamps")]
+fn test_timestamp() {
+    use time::{Duration, OffsetDateTime, UtcOffset};
+
+    let time =
+        OffsetDateTime::UNIX_EPOCH + Duration::days(19844) + Duration::nanoseconds(123_456_789);
+    let timestamp = |time, digits| Timestamp { time, digits }.to_string();
+    assert_eq!(timestamp(Some(time), 6), "2024-05-01T00:00:00.123456Z");
+    assert_eq!(timestamp(Some(time), 3), "2024-05-01T00:00:00.123Z");
+    assert_eq!(timestamp(Some(time), 0), "2024-05-01T00:00:00Z");
+    assert_eq!(timestamp(Some(time), 9), "2024-05-01T00:00:00.123456Z");
+    let offset = UtcOffset::from_hms(-5, -30, 0).unwrap();
+    assert_eq!(
+        timestamp(Some(time.to_offset(offset)), 1),
+        "2024-04-30T18:30:00.1-05:30"
+    );
+    assert_eq!(timestamp(None, 6), "-");
+}
+
+#[test]
+#[cfg(not(feature = "timestamps"))]
+fn test_without_timestamps() {
+    let mut buf = Vec::new();
+    Formatter3164::default()
+        .format(&mut buf, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert_eq!(buf, b"<14>main[0]: hello");
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(&mut buf, Severity::LOG_INFO, Message5424::new("hello"))
+        .unwrap();
+    assert!(buf.starts_with(b"<14>1 - "));
+}
+
+#[test]
+fn test_msg_id() {
+    assert_eq!(MsgId::new("DHCPACK").unwrap().as_str(), "DHCPACK");
+    assert!(MsgId::new("").is_err());
+    assert!(MsgId::new("two words").is_err());
+    assert!(MsgId::new("x".repeat(33)).is_err());
+    assert_eq!(MsgId::from(42).to_string(), "42");
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            (
+                MsgId::new("DHCPACK").unwrap(),
+                StructuredData::new(),
+                "lease",
+            ),
+        )
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with(" DHCPACK - lease"));
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            (7, StructuredData::new(), "lease"),
+        )
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().ends_with(" 7 - lease"));
+}
+
+#[test]
+fn test_message5424() {
+    let data = StructuredDataBuilder::new()
+        .element("exampleSDID@32473")
+        .param("iut", 3)
+        .param("eventSource", "Application")
+        .element("examplePriority@32473")
+        .param("note", "a \"quoted\" ]");
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(
+            &mut buf,
+            Severity::LOG_NOTICE,
+            Message5424::new("started")
+                .msg_id("ID47")
+                .structured_data(data),
+        )
+        .unwrap();
+
+    let message = String::from_utf8(buf).unwrap();
+    assert!(message.starts_with("<13>1 "));
+    assert!(message.ends_with(concat!(
+        " ID47 [exampleSDID@32473 iut=\"3\" eventSource=\"Application\"]",
+        "[examplePriority@32473 note=\"a \\\"quoted\\\" \\]\"] started"
+    )));
+
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(&mut buf, Severity::LOG_INFO, Message5424::new("plain"))
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().ends_with(" - - plain"));
+
+    let mut buf = Vec::new();
+    let readings = 3;
+    Formatter5424::default()
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            &Record::builder()
+                .args(format_args!("{} readings", readings))
+                .build(),
+        )
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().ends_with(" - - 3 readings"));
+
+    let key_values = [("temp", 23.5)];
+    let record = Record::builder()
+        .args(format_args!("reading"))
+        .key_values(&key_values)
+        .build();
+    let mut buf = Vec::new();
+    Formatter5424::default()
+        .format(&mut buf, Severity::LOG_INFO, &record)
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with(" - [fields@32473 temp=\"23.5\"] reading"));
+
+    let mut buf = Vec::new();
+    Formatter3164::default()
+        .format(&mut buf, Severity::LOG_INFO, RecordMessage(&record))
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with("main[0]: reading temp=23.5"));
+}
+
 #[test]
 fn test_formatter5424_defaults() {
     let d = Formatter5424::default();
@@ -235,3 +1435,33 @@ fn test_formatter5424_defaults() {
 
     // Can't really make any assertions about the pid.
 }
+
+#[test]
+fn test_formatter5424_without_pid() {
+    let formatter: Formatter5424 = Formatter3164 {
+        process: "ota".to_string(),
+        pid: None,
+        ..Default::default()
+    }
+    .into();
+    assert_eq!(formatter.pid, None);
+
+    let mut buf = Vec::new();
+    formatter
+        .format(&mut buf, Severity::LOG_INFO, Message5424::new("hello"))
+        .unwrap();
+    assert!(String::from_utf8(buf).unwrap().contains(" ota - - - hello"));
+}
+
+#[test]
+fn test_level_conversions() {
+    assert!(matches!(Severity::from(Level::Warn), Severity::LOG_WARNING));
+    assert!(matches!(Severity::from(Level::Trace), Severity::LOG_DEBUG));
+    assert_eq!(Level::try_from(Severity::LOG_ERR).ok(), Some(Level::Error));
+    let info = Severity::from(Level::Info);
+    assert_eq!(Level::try_from(info).ok(), Some(Level::Info));
+    assert!(matches!(
+        Level::try_from(Severity::LOG_CRIT),
+        Err(Severity::LOG_CRIT)
+    ));
+}
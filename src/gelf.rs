@@ -0,0 +1,303 @@
+//! Graylog Extended Log Format (GELF) messages, and the GELF UDP transport
+//!
+//! GELF messages are JSON objects rather than syslog frames, so a Graylog input takes them
+//! without a relay. Over UDP, messages longer than a datagram are split into chunks, which
+//! Graylog reassembles.
+use std::fmt::{self, Arguments, Display};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use boot;
+#[cfg(feature = "timestamps")]
+use clock::Clock;
+use errors::*;
+use facility::Facility;
+use format::{LogFormat, Severity, WithFields};
+use hostname::HostnameSource;
+use json::JsonStr;
+use payload::Bytes;
+
+/// The largest datagram sent by `GelfUdp` by default, which fits the MTU of most links
+pub const GELF_CHUNK_SIZE: usize = 1420;
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LEN: usize = 12;
+/// Graylog drops messages split into more chunks
+const MAX_CHUNKS: usize = 128;
+
+/// Formats GELF 1.1 messages
+///
+/// The first line of the message is the `short_message`, and the whole message the
+/// `full_message` when it spans several lines. The `level` is the syslog severity, and the
+/// facility, the process and the fields of a `WithFields` message are sent as additional
+/// fields (`_facility`, `_process`, `_temp`...), their names stripped of the characters GELF
+/// doesn't allow. The timestamp is left out when the clock doesn't know the time, so Graylog
+/// uses the reception time. Messages can be `&str`, `String`, `fmt::Arguments` or
+/// `WithFields`.
+#[derive(Clone, Debug)]
+pub struct FormatterGelf {
+    pub facility: Facility,
+    /// the `host`, see `HostnameSource`
+    pub hostname: HostnameSource,
+    pub process: String,
+    #[cfg(feature = "timestamps")]
+    pub clock: Clock,
+}
+
+impl Default for FormatterGelf {
+    /// `LOG_USER`, `main` and the system clock, as `Formatter3164::default`
+    fn default() -> Self {
+        FormatterGelf {
+            facility: Facility::default(),
+            hostname: HostnameSource::Global,
+            process: "main".to_string(),
+            #[cfg(feature = "timestamps")]
+            clock: Clock::System,
+        }
+    }
+}
+
+impl FormatterGelf {
+    fn write_gelf<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<()> {
+        let mut frame = Vec::new();
+        let short_message = message.lines().next().unwrap_or("");
+        self.hostname
+            .with(|hostname| {
+                write!(
+                    frame,
+                    "{{\"version\":\"1.1\",\"host\":{},\"short_message\":{}",
+                    JsonStr(hostname),
+                    JsonStr(short_message)
+                )
+            })
+            .map_err(Error::Format)?;
+        if short_message.len() < message.len() {
+            write!(frame, ",\"full_message\":{}", JsonStr(message)).map_err(Error::Format)?;
+        }
+        #[cfg(feature = "timestamps")]
+        if let Some(time) = self.clock.try_now() {
+            write!(
+                frame,
+                ",\"timestamp\":{}.{:03}",
+                time.unix_timestamp(),
+                time.millisecond()
+            )
+            .map_err(Error::Format)?;
+        }
+        write!(
+            frame,
+            ",\"level\":{},\"_facility\":\"{}\",\"_process\":{}",
+            severity as u8,
+            self.facility.name(),
+            JsonStr(&self.process)
+        )
+        .map_err(Error::Format)?;
+        for &(name, value) in fields {
+            let name = FieldName(name).to_string();
+            // `_id` is reserved
+            if !name.is_empty() && name != "id" {
+                write!(frame, ",\"_{}\":{}", name, JsonStr(value)).map_err(Error::Format)?;
+            }
+        }
+        frame.push(b'}');
+        w.write_all(&frame).map_err(Error::from)
+    }
+}
+
+/// An additional field name, keeping the characters GELF allows: letters, digits, `_`, `.`
+/// and `-`
+struct FieldName<'a>(&'a str);
+
+impl<'a> Display for FieldName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0
+            .chars()
+            .filter(|&c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            .try_for_each(|c| fmt::Write::write_char(f, c))
+    }
+}
+
+impl<'a> LogFormat<&'a str> for FormatterGelf {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a str) -> Result<()> {
+        self.write_gelf(w, severity, message, &[])
+    }
+}
+
+impl LogFormat<String> for FormatterGelf {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: String) -> Result<()> {
+        self.write_gelf(w, severity, &message, &[])
+    }
+}
+
+impl<'a> LogFormat<Arguments<'a>> for FormatterGelf {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: Arguments<'a>,
+    ) -> Result<()> {
+        self.write_gelf(w, severity, &fmt::format(message), &[])
+    }
+}
+
+/// Sends the bytes as UTF-8 text, see `BytesEncoding::Lossy`
+impl<'a> LogFormat<&'a [u8]> for FormatterGelf {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a [u8]) -> Result<()> {
+        self.write_gelf(w, severity, &Bytes::lossy(message).to_string(), &[])
+    }
+}
+
+impl LogFormat<Vec<u8>> for FormatterGelf {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: Vec<u8>) -> Result<()> {
+        self.format(w, severity, &message[..])
+    }
+}
+
+impl<'a, T: Display> LogFormat<WithFields<'a, T>> for FormatterGelf {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: WithFields<'a, T>,
+    ) -> Result<()> {
+        self.write_gelf(w, severity, &message.message.to_string(), message.fields)
+    }
+}
+
+/// A UDP socket sending every write as one GELF message, split into chunks when it is longer
+/// than the chunk size
+///
+/// Messages needing more than 128 chunks fail to send, Graylog dropping them otherwise.
+pub struct GelfUdp {
+    socket: UdpSocket,
+    chunk_size: usize,
+    /// the first half of the message ids, random per socket
+    id_prefix: u32,
+    messages: u32,
+}
+
+impl GelfUdp {
+    /// returns a socket connected to `server`
+    pub fn connect(server: SocketAddr) -> io::Result<GelfUdp> {
+        let local = match server {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        Ok(GelfUdp {
+            socket,
+            chunk_size: GELF_CHUNK_SIZE,
+            id_prefix: boot::random(),
+            messages: 0,
+        })
+    }
+
+    /// sets the size of the largest datagram, `GELF_CHUNK_SIZE` by default; Graylog suggests
+    /// 8192 on a LAN
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(CHUNK_HEADER_LEN + 1);
+    }
+
+    fn send_chunked(&mut self, message: &[u8]) -> io::Result<()> {
+        let chunks: Vec<&[u8]> = message.chunks(self.chunk_size - CHUNK_HEADER_LEN).collect();
+        if chunks.len() > MAX_CHUNKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the message needs more than 128 GELF chunks",
+            ));
+        }
+        self.messages = self.messages.wrapping_add(1);
+        let mut id = [0; 8];
+        id[..4].copy_from_slice(&self.id_prefix.to_be_bytes());
+        id[4..].copy_from_slice(&self.messages.to_be_bytes());
+        let mut datagram = Vec::with_capacity(self.chunk_size);
+        for (sequence, chunk) in chunks.iter().enumerate() {
+            datagram.clear();
+            datagram.extend_from_slice(&CHUNK_MAGIC);
+            datagram.extend_from_slice(&id);
+            datagram.push(sequence as u8);
+            datagram.push(chunks.len() as u8);
+            datagram.extend_from_slice(chunk);
+            self.socket.send(&datagram)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for GelfUdp {
+    fn write(&mut self, message: &[u8]) -> io::Result<usize> {
+        if message.len() <= self.chunk_size {
+            self.socket.send(message)?;
+        } else {
+            self.send_chunked(message)?;
+        }
+        Ok(message.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_gelf() {
+    let formatter = FormatterGelf {
+        facility: Facility::LOG_LOCAL0,
+        hostname: HostnameSource::Static("esp32"),
+        process: "sensor".to_string(),
+        #[cfg(feature = "timestamps")]
+        clock: Clock::Uptime,
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(
+            &mut buf,
+            Severity::LOG_WARNING,
+            WithFields {
+                message: "low battery\nat 3.1 V",
+                fields: &[("volts", "3.1"), ("id", "7"), ("sensor name", "bme280")],
+            },
+        )
+        .unwrap();
+    let message = String::from_utf8(buf).unwrap();
+    assert!(message.starts_with("{\"version\":\"1.1\",\"host\":\"esp32\","));
+    #[cfg(feature = "timestamps")]
+    assert!(message.contains(
+        ",\"short_message\":\"low battery\",\"full_message\":\"low battery\\nat 3.1 V\",\
+         \"timestamp\":0."
+    ));
+    #[cfg(not(feature = "timestamps"))]
+    assert!(message.contains(
+        ",\"short_message\":\"low battery\",\"full_message\":\"low battery\\nat 3.1 V\",\
+         \"level\":4,"
+    ));
+    assert!(message.ends_with(
+        ",\"level\":4,\"_facility\":\"local0\",\"_process\":\"sensor\",\"_volts\":\"3.1\",\
+         \"_sensorname\":\"bme280\"}"
+    ));
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut gelf = GelfUdp::connect(server.local_addr().unwrap()).unwrap();
+    gelf.set_chunk_size(20);
+    gelf.write_all(b"short").unwrap();
+    gelf.write_all(b"a message of 26 characters").unwrap();
+
+    let mut datagram = [0; 64];
+    let len = server.recv(&mut datagram).unwrap();
+    assert_eq!(&datagram[..len], b"short");
+    let mut message = Vec::new();
+    for sequence in 0..4 {
+        let len = server.recv(&mut datagram).unwrap();
+        assert_eq!(datagram[..2], CHUNK_MAGIC);
+        assert_eq!(datagram[10..12], [sequence, 4]);
+        message.extend_from_slice(&datagram[CHUNK_HEADER_LEN..len]);
+    }
+    assert_eq!(message, b"a message of 26 characters");
+}
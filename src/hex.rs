@@ -0,0 +1,66 @@
+//! Hex dumps, for `Logger::debug_hex`
+use std::fmt::{self, Display};
+
+/// bytes per line of a dump
+pub const HEX_LINE_BYTES: usize = 16;
+/// bytes dumped at most, the remaining ones are only counted
+pub const HEX_MAX_BYTES: usize = 1024;
+
+/// One line of a dump: `label 0010: 48 65 6c 6c 6f 00 ...  |Hello.|`
+pub struct HexLine<'a> {
+    pub label: &'a str,
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Display for HexLine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:04x}:", self.label, self.offset)?;
+        for i in 0..HEX_LINE_BYTES {
+            match self.bytes.get(i) {
+                Some(byte) => write!(f, " {byte:02x}")?,
+                None => write!(f, "   ")?,
+            }
+        }
+        write!(f, "  |")?;
+        for &byte in self.bytes {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            write!(f, "{c}")?;
+        }
+        write!(f, "|")
+    }
+}
+
+/// returns the lines dumping `bytes`, up to `HEX_MAX_BYTES`
+pub fn hex_lines<'a>(label: &'a str, bytes: &'a [u8]) -> impl Iterator<Item = HexLine<'a>> {
+    bytes[..bytes.len().min(HEX_MAX_BYTES)]
+        .chunks(HEX_LINE_BYTES)
+        .enumerate()
+        .map(move |(i, chunk)| HexLine {
+            label,
+            offset: i * HEX_LINE_BYTES,
+            bytes: chunk,
+        })
+}
+
+#[test]
+fn test_hex_lines() {
+    let lines: Vec<String> = hex_lines("rx", b"Hello, world!\0\x01\x02\xffend")
+        .map(|line| line.to_string())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "rx 0000: 48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 00 01 02  |Hello, world!...|",
+            "rx 0010: ff 65 6e 64                                      |.end|",
+        ]
+    );
+    assert_eq!(
+        hex_lines("big", &[0; 4096]).count(),
+        HEX_MAX_BYTES / HEX_LINE_BYTES
+    );
+}
@@ -0,0 +1,166 @@
+//! Where the formatters take the HOSTNAME of the messages from
+use std::fmt;
+use std::str;
+
+use with_hostname;
+
+/// A hostname source for the cases `HostnameSource` doesn't cover, e.g. a name kept in NVS
+pub trait HostnameProvider: Sync {
+    /// returns the hostname, or `None` for the one set by `set_hostname`
+    fn hostname(&self) -> Option<String>;
+}
+
+/// The HOSTNAME source of a formatter
+///
+/// The hostname is read as every message is formatted, so a device getting its name after the
+/// logger was set up, e.g. from its DHCP server, sends it from then on. A source without a
+/// hostname (yet) falls back to the one set by `set_hostname`.
+///
+/// ```no_run
+/// # use esp_syslog::{Formatter5424, HostnameSource};
+/// let formatter = Formatter5424 {
+///     hostname: HostnameSource::StationHostname,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Default)]
+pub enum HostnameSource {
+    /// the hostname set by `set_hostname` or `SyslogBuilder::hostname`, `esp32-unknown` until
+    /// then
+    #[default]
+    Global,
+    /// this hostname
+    Static(&'static str),
+    /// the hostname returned by the function
+    Function(fn() -> Option<String>),
+    /// `esp32-` and the last three bytes of the MAC address of the Wi-Fi station, e.g.
+    /// `esp32-a1b2c3`, as the ESP-IDF names devices by default
+    StationMac,
+    /// the hostname of the Wi-Fi station interface, the one it gives its DHCP server
+    StationHostname,
+    /// any other source
+    Custom(&'static dyn HostnameProvider),
+}
+
+impl fmt::Debug for HostnameSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HostnameSource::Global => f.write_str("Global"),
+            HostnameSource::Static(hostname) => f.debug_tuple("Static").field(&hostname).finish(),
+            HostnameSource::Function(read) => f.debug_tuple("Function").field(&read).finish(),
+            HostnameSource::StationMac => f.write_str("StationMac"),
+            HostnameSource::StationHostname => f.write_str("StationHostname"),
+            HostnameSource::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl HostnameSource {
+    /// returns the hostname of the messages formatted now
+    pub fn hostname(&self) -> String {
+        self.with(str::to_string)
+    }
+
+    /// runs `f` with the hostname, without copying it where it can
+    pub(crate) fn with<R, F: FnOnce(&str) -> R>(&self, f: F) -> R {
+        match *self {
+            HostnameSource::Global => with_hostname(f),
+            HostnameSource::Static(hostname) => f(hostname),
+            HostnameSource::Function(read) => match read() {
+                Some(hostname) => f(&hostname),
+                None => with_hostname(f),
+            },
+            HostnameSource::StationMac => match station_mac() {
+                Some(mac) => f(str::from_utf8(&mac_hostname(mac)).unwrap()),
+                None => with_hostname(f),
+            },
+            HostnameSource::StationHostname => with_station_hostname(f),
+            HostnameSource::Custom(provider) => match provider.hostname() {
+                Some(hostname) => f(&hostname),
+                None => with_hostname(f),
+            },
+        }
+    }
+}
+
+/// returns `esp32-` and the last three bytes of `mac` in hex
+fn mac_hostname(mac: [u8; 6]) -> [u8; 12] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hostname = *b"esp32-000000";
+    for (i, &byte) in mac[3..].iter().enumerate() {
+        hostname[6 + 2 * i] = DIGITS[usize::from(byte >> 4)];
+        hostname[7 + 2 * i] = DIGITS[usize::from(byte & 0xf)];
+    }
+    hostname
+}
+
+/// returns the MAC address of the Wi-Fi station, burnt in the eFuses
+#[cfg(target_os = "espidf")]
+fn station_mac() -> Option<[u8; 6]> {
+    use esp_idf_svc::sys;
+
+    let mut mac = [0; 6];
+    match unsafe { sys::esp_read_mac(mac.as_mut_ptr(), sys::esp_mac_type_t_ESP_MAC_WIFI_STA) } {
+        sys::ESP_OK => Some(mac),
+        _ => None,
+    }
+}
+
+/// returns the MAC address of the Wi-Fi station, `None` off an ESP32
+#[cfg(not(target_os = "espidf"))]
+fn station_mac() -> Option<[u8; 6]> {
+    None
+}
+
+/// runs `f` with the hostname of the Wi-Fi station interface, the global one while it has none
+#[cfg(target_os = "espidf")]
+fn with_station_hostname<R, F: FnOnce(&str) -> R>(f: F) -> R {
+    use esp_idf_svc::sys;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    let netif =
+        unsafe { sys::esp_netif_get_handle_from_ifkey(b"WIFI_STA_DEF\0".as_ptr() as *const _) };
+    if !netif.is_null() {
+        let mut hostname = ptr::null();
+        let got = unsafe { sys::esp_netif_get_hostname(netif, &mut hostname) };
+        if got == sys::ESP_OK && !hostname.is_null() {
+            // the name is kept by the interface
+            if let Ok(hostname) = unsafe { CStr::from_ptr(hostname) }.to_str() {
+                if !hostname.is_empty() {
+                    return f(hostname);
+                }
+            }
+        }
+    }
+    with_hostname(f)
+}
+
+/// runs `f` with the global hostname, there being no Wi-Fi station off an ESP32
+#[cfg(not(target_os = "espidf"))]
+fn with_station_hostname<R, F: FnOnce(&str) -> R>(f: F) -> R {
+    with_hostname(f)
+}
+
+#[test]
+fn test_hostname_source() {
+    assert_eq!(
+        &mac_hostname([0x24, 0x0a, 0xc4, 0xa1, 0xb2, 0x0c]),
+        b"esp32-a1b20c"
+    );
+    assert_eq!(HostnameSource::Static("sensor-7").hostname(), "sensor-7");
+
+    struct Dhcp;
+
+    impl HostnameProvider for Dhcp {
+        fn hostname(&self) -> Option<String> {
+            Some("from-dhcp".to_string())
+        }
+    }
+
+    static DHCP: Dhcp = Dhcp;
+    assert_eq!(HostnameSource::Custom(&DHCP).hostname(), "from-dhcp");
+    // other tests may set the global hostname meanwhile
+    let fallback = HostnameSource::Function(|| None).hostname();
+    assert!(!fallback.is_empty());
+}
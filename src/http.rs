@@ -0,0 +1,237 @@
+//! Minimal HTTP/1.1 client for the HTTP-based backends
+//!
+//! Every request opens its own connection (`Connection: close`), which suits the infrequent
+//! batched POSTs of the backends and keeps no state between them. `https://` URLs need the
+//! `tls` feature, and a TLS configuration.
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use rustls::pki_types::ServerName;
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+
+/// how long connecting to, writing to and reading from the server may take
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An `http://` or `https://` URL requests are sent to, with the extra headers to send along
+#[derive(Clone, Debug)]
+pub struct HttpEndpoint {
+    https: bool,
+    host: String,
+    port: u16,
+    path: String,
+    headers: Vec<(String, String)>,
+    #[cfg(feature = "tls")]
+    tls_config: Option<Arc<ClientConfig>>,
+}
+
+fn invalid_url(url: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid HTTP URL {}", url),
+    )
+}
+
+impl HttpEndpoint {
+    /// parses `http[s]://host[:port][/path]`, defaulting to `default_path` without a path
+    pub fn parse(url: &str, default_path: &str) -> io::Result<HttpEndpoint> {
+        let (https, rest) = match url.strip_prefix("https://") {
+            Some(rest) => (true, rest),
+            None => (
+                false,
+                url.strip_prefix("http://")
+                    .ok_or_else(|| invalid_url(url))?,
+            ),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, default_path),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, port.parse().map_err(|_| invalid_url(url))?)
+            }
+            _ if https => (authority, 443),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid_url(url));
+        }
+        Ok(HttpEndpoint {
+            https,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            headers: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        })
+    }
+
+    /// adds a header sent with every request, e.g. `Authorization`
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// sets the TLS configuration used for `https://` URLs
+    #[cfg(feature = "tls")]
+    pub fn set_tls_config(&mut self, config: Arc<ClientConfig>) {
+        self.tls_config = Some(config);
+    }
+
+    /// POSTs `body`, failing unless the server answers with a 2xx status
+    pub fn post(&self, content_type: &str, body: &[u8]) -> io::Result<()> {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        let addr = (host, self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for host"))?;
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+
+        if !self.https {
+            return self.exchange(stream, content_type, body);
+        }
+        #[cfg(feature = "tls")]
+        {
+            if let Some(ref config) = self.tls_config {
+                let name = ServerName::try_from(host.to_string())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let connection =
+                    ClientConnection::new(config.clone(), name).map_err(io::Error::other)?;
+                return self.exchange(StreamOwned::new(connection, stream), content_type, body);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "https:// URLs need the tls feature and a TLS configuration",
+        ))
+    }
+
+    fn exchange<S: Read + Write>(
+        &self,
+        mut stream: S,
+        content_type: &str,
+        body: &[u8],
+    ) -> io::Result<()> {
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path,
+            self.host,
+            self.port,
+            content_type,
+            body.len()
+        );
+        for (name, value) in &self.headers {
+            request = request + name + ": " + value + "\r\n";
+        }
+        request += "\r\n";
+        stream.write_all(request.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        match status.split(' ').nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "HTTP request failed: {}",
+                status.trim_end()
+            ))),
+        }
+    }
+}
+
+/// Requests queued by the batching backends
+///
+/// Requests hold up to `batch_size` entries. Up to `max_batches` full requests are kept while
+/// the server is unreachable, the oldest entries being dropped beyond that.
+#[derive(Clone, Debug)]
+pub struct BatchQueue<T> {
+    pub entries: Vec<T>,
+    batch_size: usize,
+    max_batches: usize,
+}
+
+impl<T> BatchQueue<T> {
+    pub fn new(batch_size: usize, max_batches: usize) -> BatchQueue<T> {
+        BatchQueue {
+            entries: Vec::new(),
+            batch_size: batch_size.max(1),
+            max_batches,
+        }
+    }
+
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// queues `entry`, returning whether a full batch is waiting
+    pub fn push(&mut self, entry: T) -> bool {
+        let limit = self.batch_size * self.max_batches;
+        if self.entries.len() >= limit {
+            self.entries.drain(..self.entries.len() + 1 - limit);
+        }
+        self.entries.push(entry);
+        self.entries.len() >= self.batch_size
+    }
+}
+
+/// Reads one request from `stream` and answers it with `status`, returning the request headers
+/// and body
+#[cfg(test)]
+pub fn serve_one(stream: TcpStream, status: &str) -> (String, Vec<u8>) {
+    use std::io::Read;
+
+    let mut reader = BufReader::new(stream);
+    let mut headers = String::new();
+    while !headers.ends_with("\r\n\r\n") {
+        reader.read_line(&mut headers).unwrap();
+    }
+    let length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("Content-Length: "))
+        .map_or(0, |length| length.parse().unwrap());
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n",
+        status
+    )
+    .unwrap();
+    (headers, body)
+}
+
+#[test]
+fn test_post() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let mut endpoint = HttpEndpoint::parse(&url, "/ingest").unwrap();
+    endpoint.add_header("Authorization", "Bearer token");
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let first = serve_one(stream, "200 OK");
+        let (stream, _) = listener.accept().unwrap();
+        serve_one(stream, "503 Service Unavailable");
+        first
+    });
+    endpoint.post("text/plain", b"hello").unwrap();
+    assert!(endpoint.post("text/plain", b"again").is_err());
+
+    let (headers, body) = server.join().unwrap();
+    assert!(headers.starts_with("POST /ingest HTTP/1.1\r\n"));
+    assert!(headers.contains("\r\nAuthorization: Bearer token\r\n"));
+    assert_eq!(body, b"hello");
+}
@@ -0,0 +1,259 @@
+//! JSON payloads in the MSG part, and helpers for the formatters and backends producing JSON
+use std::fmt::{self, Arguments, Display};
+use std::io::Write;
+
+use errors::*;
+use facility::Facility;
+use format::{LogFormat, Severity, WithFields};
+use hostname::HostnameSource;
+use payload::Bytes;
+use {Formatter3164, Formatter5424, CEE_COOKIE};
+
+/// A string written as a quoted and escaped JSON string
+pub struct JsonStr<'a>(pub &'a str);
+
+impl<'a> Display for JsonStr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"")?;
+        let mut start = 0;
+        for (i, c) in self.0.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if (c as u32) < 0x20 => "",
+                _ => continue,
+            };
+            f.write_str(&self.0[start..i])?;
+            if escaped.is_empty() {
+                write!(f, "\\u{:04x}", c as u32)?;
+            } else {
+                f.write_str(escaped)?;
+            }
+            start = i + c.len_utf8();
+        }
+        f.write_str(&self.0[start..])?;
+        f.write_str("\"")
+    }
+}
+
+/// Sends the message and its metadata as a JSON object in the MSG, in the frame produced by
+/// `formatter`
+///
+/// Collectors parsing JSON payloads (Fluent Bit, Vector...) get the severity, facility,
+/// hostname and process as members, and the fields of a `WithFields` message as well:
+/// `{"msg":"reading","severity":"info","facility":"user","hostname":"esp32","process":"main",
+/// "temp":"23.5"}`. Messages can be `&str`, `String`, `fmt::Arguments` or `WithFields`.
+///
+/// A frame over `max_message_len` is truncated or split like any other, which breaks the JSON.
+#[derive(Clone, Debug, Default)]
+pub struct FormatterJson<F> {
+    pub formatter: F,
+    /// prefixes the object with the `@cee:` cookie, so rsyslog's mmjsonparse parses it, see
+    /// `FormatterCee`; `false` by default
+    pub cee: bool,
+}
+
+/// Wire formatters able to frame a JSON payload
+pub trait JsonFrame {
+    fn format_json<W: Write>(&self, w: &mut W, severity: Severity, event: JsonEvent) -> Result<()>;
+}
+
+impl JsonFrame for Formatter3164 {
+    fn format_json<W: Write>(&self, w: &mut W, severity: Severity, event: JsonEvent) -> Result<()> {
+        let payload = JsonPayload {
+            event,
+            severity,
+            facility: self.facility,
+            hostname: self.hostname,
+            process: &self.process,
+        };
+        self.format(w, severity, payload)
+    }
+}
+
+impl JsonFrame for Formatter5424 {
+    fn format_json<W: Write>(&self, w: &mut W, severity: Severity, event: JsonEvent) -> Result<()> {
+        let payload = JsonPayload {
+            event,
+            severity,
+            facility: self.facility,
+            hostname: self.hostname,
+            process: &self.process,
+        };
+        self.format(
+            w,
+            severity,
+            WithFields {
+                message: payload,
+                fields: &[],
+            },
+        )
+    }
+}
+
+/// The message and fields of a JSON payload
+pub struct JsonEvent<'a> {
+    message: String,
+    fields: &'a [(&'a str, &'a str)],
+    cee: bool,
+}
+
+struct JsonPayload<'a, 'b> {
+    event: JsonEvent<'a>,
+    severity: Severity,
+    facility: Facility,
+    hostname: HostnameSource,
+    process: &'b str,
+}
+
+impl<'a, 'b> Display for JsonPayload<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.event.cee {
+            f.write_str(CEE_COOKIE)?;
+        }
+        self.hostname.with(|hostname| {
+            write!(
+                f,
+                "{{\"msg\":{},\"severity\":\"{}\",\"facility\":\"{}\",\
+                 \"hostname\":{},\"process\":{}",
+                JsonStr(&self.event.message),
+                self.severity.name(),
+                self.facility.name(),
+                JsonStr(hostname),
+                JsonStr(self.process)
+            )
+        })?;
+        for &(name, value) in self.event.fields {
+            write!(f, ",{}:{}", JsonStr(name), JsonStr(value))?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl<'a, F: JsonFrame> LogFormat<&'a str> for FormatterJson<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a str) -> Result<()> {
+        let event = JsonEvent {
+            message: message.to_string(),
+            fields: &[],
+            cee: self.cee,
+        };
+        self.formatter.format_json(w, severity, event)
+    }
+}
+
+impl<F: JsonFrame> LogFormat<String> for FormatterJson<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: String) -> Result<()> {
+        let event = JsonEvent {
+            message,
+            fields: &[],
+            cee: self.cee,
+        };
+        self.formatter.format_json(w, severity, event)
+    }
+}
+
+impl<'a, F: JsonFrame> LogFormat<Arguments<'a>> for FormatterJson<F> {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: Arguments<'a>,
+    ) -> Result<()> {
+        let event = JsonEvent {
+            message: fmt::format(message),
+            fields: &[],
+            cee: self.cee,
+        };
+        self.formatter.format_json(w, severity, event)
+    }
+}
+
+/// Sends the bytes as UTF-8 text, see `BytesEncoding::Lossy`
+impl<'a, F: JsonFrame> LogFormat<&'a [u8]> for FormatterJson<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: &'a [u8]) -> Result<()> {
+        let event = JsonEvent {
+            message: Bytes::lossy(message).to_string(),
+            fields: &[],
+            cee: self.cee,
+        };
+        self.formatter.format_json(w, severity, event)
+    }
+}
+
+impl<F: JsonFrame> LogFormat<Vec<u8>> for FormatterJson<F> {
+    fn format<W: Write>(&self, w: &mut W, severity: Severity, message: Vec<u8>) -> Result<()> {
+        self.format(w, severity, &message[..])
+    }
+}
+
+impl<'a, F: JsonFrame, T: Display> LogFormat<WithFields<'a, T>> for FormatterJson<F> {
+    fn format<W: Write>(
+        &self,
+        w: &mut W,
+        severity: Severity,
+        message: WithFields<'a, T>,
+    ) -> Result<()> {
+        let event = JsonEvent {
+            message: message.message.to_string(),
+            fields: message.fields,
+            cee: self.cee,
+        };
+        self.formatter.format_json(w, severity, event)
+    }
+}
+
+#[test]
+fn test_json_str() {
+    assert_eq!(
+        JsonStr("say \"hi\"\n\tC:\\ \u{1} é").to_string(),
+        r#""say \"hi\"\n\tC:\\ \u0001 é""#
+    );
+}
+
+#[test]
+fn test_json_payload() {
+    let formatter = FormatterJson {
+        formatter: Formatter5424 {
+            facility: Facility::LOG_LOCAL0,
+            ..Default::default()
+        },
+        cee: false,
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(
+            &mut buf,
+            Severity::LOG_WARNING,
+            WithFields {
+                message: "low \"battery\"",
+                fields: &[("volts", "3.1")],
+            },
+        )
+        .unwrap();
+    let message = String::from_utf8(buf).unwrap();
+    assert!(message.starts_with("<132>1 "));
+    // the hostname is left out, other tests may change it meanwhile
+    assert!(message.contains(
+        " main 0 - - {\"msg\":\"low \\\"battery\\\"\",\"severity\":\"warning\",\
+         \"facility\":\"local0\",\"hostname\":\""
+    ));
+    assert!(message.ends_with("\",\"process\":\"main\",\"volts\":\"3.1\"}"));
+}
+
+#[test]
+fn test_json_cee() {
+    let formatter = FormatterJson {
+        formatter: Formatter3164::default(),
+        cee: true,
+    };
+    let mut buf = Vec::new();
+    formatter
+        .format(&mut buf, Severity::LOG_INFO, "hello")
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("main[0]: @cee:{\"msg\":\"hello\",\"severity\":\"info\","));
+}
@@ -0,0 +1,81 @@
+//! Periodic MARK messages on idle connections
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+use sync::Lock;
+use {BasicLogger, Formatter3164, Logger, LoggerBackend, INITIALIZED, STATE};
+
+/// The message sent by `BasicLogger::with_keepalive`, as syslogd's `-m` option sends it
+pub const MARK: &str = "-- MARK --";
+
+impl BasicLogger {
+    /// sends `MARK` at `LOG_INFO` whenever no message was sent for `interval`, from a new
+    /// thread
+    ///
+    /// Keeps NAT mappings and idle TCP sessions open, and lets the collector tell a quiet
+    /// device from a dead one. Nothing is sent until the network is available, see
+    /// `set_network_available`. The thread exits within `interval` of the last clone of this
+    /// logger being dropped.
+    pub fn with_keepalive(self, interval: Duration) -> BasicLogger {
+        let logger = Arc::downgrade(&self.logger);
+        thread::Builder::new()
+            .name("syslog-keepalive".into())
+            .spawn(move || keepalive_loop(logger, interval))
+            .expect("failed to spawn the syslog keepalive thread");
+        self
+    }
+}
+
+fn keepalive_loop(logger: Weak<Lock<Logger<LoggerBackend, Formatter3164>>>, interval: Duration) {
+    let mut wait = interval;
+    loop {
+        thread::sleep(wait);
+        let logger = match logger.upgrade() {
+            Some(logger) => logger,
+            None => return,
+        };
+        let mut logger = logger.lock();
+        let idle = logger.idle();
+        wait = if idle < interval {
+            interval - idle
+        } else {
+            if STATE.load(Ordering::Relaxed) == INITIALIZED {
+                // Failures are counted in the stats, like those of any message
+                let _ = logger.info(MARK);
+            }
+            interval
+        };
+    }
+}
+
+#[test]
+fn test_keepalive() {
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use {set_network_available, Transport};
+
+    struct Frames(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl Transport for Frames {
+        fn send(&mut self, frame: &[u8]) -> ::std::io::Result<()> {
+            self.0.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let backend = LoggerBackend::custom(Frames(frames.clone()));
+    let logger = BasicLogger::new(Logger::new(backend, Formatter3164::default()))
+        .with_keepalive(Duration::from_millis(20));
+    set_network_available();
+
+    let started = Instant::now();
+    while frames.lock().unwrap().is_empty() {
+        assert!(started.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(frames.lock().unwrap()[0].ends_with(b"main[0]: -- MARK --"));
+    assert!(logger.stats().sent >= 1);
+}
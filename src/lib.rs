@@ -9,18 +9,35 @@
 //!
 //! # Example
 //!
-//! ```rust
-//! use esp_syslog::{Facility, Formatter3164, TcpStream, LoggerBackend, BufWriter};
+//! `Syslog::builder()` wires up the formatter, the transport and the logger options:
+//!
+//! ```rust,no_run
+//! use esp_syslog::{Facility, Framing, Message5424, Syslog};
+//!
+//! let mut logger = Syslog::builder()
+//!     .facility(Facility::LOG_USER)
+//!     .hostname("esp32")
+//!     .transport_tcp(("127.0.0.1", 601))
+//!     .framing(Framing::OctetCounted)
+//!     .format_5424()
+//!     .build()
+//!     .expect("could not connect to syslog");
+//! logger.info(Message5424::new("hello world")).expect("could not write message");
+//! ```
+//!
+//! The functions it calls can be used directly too:
+//!
+//! ```rust,no_run
+//! use esp_syslog::{Facility, Formatter3164};
 //!
 //! let formatter = Formatter3164 {
 //!     facility: Facility::LOG_USER,
 //!     process: "myprogram".into(),
-//!     pid: 0,
+//!     pid: None,
+//!     ..Default::default()
 //! };
 //!
-//! let tcp_server = TcpStream::connect(("127.0.0.1", 601)).map(|s| LoggerBackend::Tcp(BufWriter::new(s)));
-//!
-//! match esp_syslog::tcp(formatter, tcp_server) {
+//! match esp_syslog::tcp(formatter, ("127.0.0.1", 601)) {
 //!     Err(e) => println!("impossible to connect to syslog: {:?}", e),
 //!     Ok(mut writer) => {
 //!         writer.err("hello world").expect("could not write error message");
@@ -30,135 +47,778 @@
 //!
 //! It can be used directly with the log crate as follows:
 //!
-//! ```rust
+//! ```rust,no_run
 //! extern crate log;
 //!
-//! use esp_syslog::{Facility, Formatter3164, BasicLogger, TcpStream, LoggerBackend, BufWriter};
-//! use log::{SetLoggerError, LevelFilter, info};
+//! use esp_syslog::{Facility, Formatter3164, BasicLogger};
+//! use log::{LevelFilter, info};
 //!
 //! let formatter = Formatter3164 {
 //!     facility: Facility::LOG_USER,
 //!     process: "myprogram".into(),
-//!     pid: 0,
+//!     pid: None,
+//!     ..Default::default()
 //! };
 //!
-//! let tcp_server = TcpStream::connect(("127.0.0.1", 601)).map(|s| LoggerBackend::Tcp(BufWriter::new(s)));
-//!
-//! let logger = match esp_syslog::tcp(formatter, tcp_server) {
+//! let logger = match esp_syslog::tcp(formatter, ("127.0.0.1", 601)) {
 //!     Err(e) => { println!("impossible to connect to syslog: {:?}", e); return; },
 //!     Ok(logger) => logger,
 //! };
-//! log::set_boxed_logger(Box::new(BasicLogger::new(logger)))
-//!         .map(|()| log::set_max_level(LevelFilter::Info));
+//! esp_syslog::install(BasicLogger::new(logger), LevelFilter::Info).unwrap();
 //!
 //! info!("hello world");
-//!
+//! ```
 #![crate_type = "lib"]
 
-#[macro_use]
-extern crate error_chain;
 extern crate log;
+#[cfg(feature = "timestamps")]
 extern crate time;
+#[cfg(target_os = "espidf")]
 extern crate esp_idf_svc;
+extern crate esp_syslog_core;
+#[cfg(feature = "derive")]
+extern crate esp_syslog_derive;
+#[cfg(feature = "encryption")]
+extern crate aes_gcm;
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+#[cfg(feature = "embassy")]
+extern crate embassy_sync;
+#[cfg(feature = "embedded-nal")]
+extern crate embedded_nal;
+#[cfg(all(feature = "capture-stdio", unix))]
+extern crate libc;
+#[cfg(feature = "quic")]
+extern crate quinn;
+#[cfg(feature = "tls")]
+extern crate rustls;
+#[cfg(any(feature = "quic", feature = "async"))]
+extern crate tokio;
 
 use std::fmt::{self, Arguments};
 use std::io::{self, BufWriter, Write};
+use std::mem;
 use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicUsize;
+#[cfg(all(unix, not(target_os = "espidf")))]
+use std::os::unix::net::{UnixDatagram, UnixStream};
+#[cfg(all(unix, not(target_os = "espidf")))]
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use log::{Level, Log, Metadata, Record};
+#[cfg(test)]
+use log::Level;
+use log::{LevelFilter, Log, Metadata, Record};
 
+#[cfg(not(feature = "host-sim"))]
+use address::first_ok;
+use address::resolve_first;
+use filter::{severity_filter, TargetLevels, BUILD_FILTER};
+use rebind::RebindCheck;
+use sync::{Lock, LockGuard};
+
+mod address;
+mod annotate;
+#[cfg(feature = "async")]
+mod async_logger;
+mod batching;
+mod bind;
+mod boot;
+mod buffered;
+mod builder;
+#[cfg(feature = "http")]
+mod bulk;
+mod cee;
+#[cfg(feature = "embassy")]
+mod channel;
+#[cfg(feature = "timestamps")]
+mod clock;
+mod config;
+mod console;
+mod control;
+mod early;
+mod emergency;
 mod errors;
 mod facility;
+mod failover;
+mod fallback;
+mod filter;
+#[cfg(feature = "fluentd")]
+mod fluent;
 mod format;
+mod gelf;
+mod hex;
+mod hostname;
+#[cfg(any(feature = "otlp", feature = "splunk", feature = "http"))]
+mod http;
+mod json;
+mod keepalive;
+#[macro_use]
+mod macros;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "embedded-nal")]
+mod nal;
+mod network;
+mod offline;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod payload;
+mod process;
+#[cfg(feature = "quic")]
+mod quic;
+mod ratelimit;
+mod rebind;
+mod resolve;
+mod sanitize;
+mod scope;
+mod shutdown;
+#[cfg(feature = "host-sim")]
+pub mod sim;
+#[cfg(feature = "splunk")]
+mod splunk;
+mod spool;
+#[cfg(all(feature = "capture-stdio", unix))]
+mod stdio;
+mod sync;
+mod tcp;
+#[cfg(feature = "tls")]
+mod tls;
+mod transport;
+mod validate;
+mod wifi;
+pub use address::AddressPreference;
+pub use annotate::{set_annotator, Annotator, MessageFields};
+#[cfg(feature = "async")]
+pub use async_logger::{
+    async_tcp, async_udp, AsyncBackend, AsyncLogger, Connect, Flush, SendFrame,
+};
+pub use batching::{BatchingBackend, UDP_BATCH_SIZE};
+pub use bind::UdpBind;
+pub use boot::boot_id;
+pub use buffered::BufferedLogger;
+pub use builder::{Syslog, SyslogBuilder};
+#[cfg(feature = "http")]
+pub use bulk::HttpBackend;
+pub use cee::{FormatterCee, CEE_COOKIE};
+#[cfg(feature = "embassy")]
+pub use channel::{run as run_channel, ChannelLogger, Entry, LogChannel};
+#[cfg(feature = "timestamps")]
+pub use clock::{Clock, TimeSource};
+pub use config::{init, reconfigure, Server, SyslogConfig};
+pub use console::ConsoleFormatter;
+pub use control::LoggerControl;
+#[cfg(feature = "esp-println")]
+pub use console::console_line;
+pub use early::StaticLogger;
+pub use emergency::emergency;
 pub use errors::*;
 pub use facility::Facility;
+pub use failover::FailoverBackend;
+pub use fallback::FallbackBackend;
+#[cfg(target_os = "espidf")]
+pub use filter::filter_from_nvs;
 pub use format::Severity;
+pub use esp_syslog_core::Framing;
+pub use gelf::{FormatterGelf, GelfUdp, GELF_CHUNK_SIZE};
+pub use hostname::{HostnameProvider, HostnameSource};
 
-pub use format::{Formatter3164, Formatter5424, LogFormat};
+#[cfg(feature = "fluentd")]
+pub use fluent::FluentForward;
+pub use format::{
+    Formatter3164, Formatter5424, LogFormat, Message5424, MsgId, Oversize, RecordMessage,
+    SdElement, StructuredDataBuilder, TimeZone, WithFields, WithProcess, DEFAULT_MAX_MESSAGE_LEN,
+    FIELDS_SD_ID, MAX_SECOND_DIGITS,
+};
+#[doc(hidden)]
+pub use format::write_sd_param;
+#[cfg(feature = "derive")]
+pub use esp_syslog_derive::StructuredData;
+pub use json::FormatterJson;
+pub use payload::{Bytes, BytesEncoding, Debugged};
+pub use keepalive::MARK;
+#[cfg(feature = "mqtt")]
+pub use mqtt::MqttBackend;
+#[cfg(feature = "embedded-nal")]
+pub use nal::{NalTcp, NalUdp};
+pub use network::network_available;
+pub use offline::SpoolBackend;
+#[cfg(feature = "otlp")]
+pub use otlp::OtlpExporter;
+#[cfg(feature = "quic")]
+pub use quic::QuicStream;
+pub use process::ProcessLogger;
+pub use ratelimit::RateLimitedLogger;
+pub use rebind::rebind;
+pub use resolve::{ResolvingUdpSocket, DEFAULT_RESOLVE_INTERVAL};
+pub use scope::Scope;
+#[cfg(feature = "splunk")]
+pub use splunk::SplunkHec;
+pub use spool::{
+    FileStorage, LimitedStorage, MemoryStorage, Overflow, PersistPolicy, SpoolStorage,
+    TieredStorage,
+};
+#[cfg(feature = "encryption")]
+pub use spool::EncryptedStorage;
+#[cfg(all(feature = "encryption", target_os = "espidf"))]
+pub use spool::key_from_nvs;
+#[cfg(all(feature = "capture-stdio", unix))]
+pub use stdio::capture_stdio;
+pub use tcp::{Backoff, ReconnectingTcpStream, Timeouts};
+#[cfg(feature = "tls")]
+pub use tls::TlsStream;
+pub use transport::Transport;
+pub use validate::{validate, ParsedMessage, ParsedStructuredData, Protocol};
+pub use wifi::{wifi_signal, WifiInterface, WifiSignal};
 
 pub type Priority = u8;
 
-static mut HOSTNAME: Option<String> = None;
-static STATE: AtomicUsize = AtomicUsize::new(0);
+static HOSTNAME: Lock<Option<String>> = Lock::new(None);
+static STATE: AtomicUsize = AtomicUsize::new(UNINITIALIZED);
+static INSTALLED: Lock<Option<BasicLogger>> = Lock::new(None);
 
 // There are two different states that we care about: the logger's
 // UNINITIALIZED (no network connection), the network is available (INITIALIZED)
 const UNINITIALIZED: usize = 0;
 const INITIALIZED: usize = 1;
 
+/// every `LevelFilter`, indexed by its `usize` value
+const LEVEL_FILTERS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// When a `Logger` flushes its backend after sending a message
+#[derive(Copy, Clone, Debug, Default)]
+pub enum FlushPolicy {
+    /// never flush explicitly, letting the backend batch messages (the TCP backend buffers
+    /// them until its buffer is full)
+    #[default]
+    Batched,
+    /// flush right after every message at this severity or above, lower severities keep the
+    /// batched path
+    AtOrAbove(Severity),
+}
+
+/// What happened to the messages sent by a `Logger`, see `Logger::stats`
+///
+/// Handy to report the health of the logging in a heartbeat message. `reconnects` and
+/// `overflowed` are counted by the backend, and `reentrant` by the `BasicLogger`: they are only
+/// filled in by `BasicLogger::stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// messages formatted and written to the backend
+    pub sent: usize,
+    /// bytes written to the backend, delimiters excluded
+    pub bytes: usize,
+    /// messages lost to a formatting or backend error
+    pub dropped: usize,
+    /// connections re-established by reconnecting TCP backends, see `LoggerBackend::reconnects`
+    pub reconnects: usize,
+    /// messages dropped because an in-RAM buffer was full, see `LoggerBackend::overflowed`
+    pub overflowed: usize,
+    /// messages dropped because they were logged while the logger was sending another one, by
+    /// its backend or an error callback
+    pub reentrant: usize,
+}
+
 /// Main logging structure
 pub struct Logger<Backend: Write, Formatter> {
     pub formatter: Formatter,
     pub backend: Backend,
+    pub flush_policy: FlushPolicy,
+    /// messages less severe than this are dropped, `LOG_DEBUG` (none) by default
+    pub min_severity: Severity,
+    /// whether send failures are only counted, see `set_fire_and_forget`
+    pub fire_and_forget: bool,
+    stats: Stats,
+    /// when the last message was sent, or the logger created
+    last_sent: Instant,
 }
 
 impl<W: Write, F> Logger<W, F> {
     pub fn new(backend: W, formatter: F) -> Self {
-        Logger { backend, formatter }
+        Logger {
+            backend,
+            formatter,
+            flush_policy: Default::default(),
+            min_severity: Severity::LOG_DEBUG,
+            fire_and_forget: false,
+            stats: Stats::default(),
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// swaps the backend, returning the previous one
+    ///
+    /// The previous backend isn't flushed, so messages it still buffers can be flushed or
+    /// drained by the caller.
+    pub fn replace_backend(&mut self, backend: W) -> W {
+        mem::replace(&mut self.backend, backend)
+    }
+
+    /// returns the backend
+    pub fn backend(&self) -> &W {
+        &self.backend
+    }
+
+    /// returns the backend, e.g. to flush it or change its settings
+    pub fn backend_mut(&mut self) -> &mut W {
+        &mut self.backend
+    }
+
+    /// returns the formatter
+    pub fn formatter(&self) -> &F {
+        &self.formatter
+    }
+
+    /// returns the formatter, e.g. to change the process name
+    pub fn formatter_mut(&mut self) -> &mut F {
+        &mut self.formatter
+    }
+
+    /// takes the logger apart, returning the backend, unflushed, and the formatter
+    pub fn into_parts(self) -> (W, F) {
+        (self.backend, self.formatter)
+    }
+
+    /// drops the messages less severe than `severity`, independently of the `log` levels
+    pub fn set_min_severity(&mut self, severity: Severity) {
+        self.min_severity = severity;
+    }
+
+    /// makes the logging methods return `Ok` even when a message can't be sent, the failures
+    /// being counted in `stats` instead
+    ///
+    /// For firmware where logging must never get in the way of the device's main function. A
+    /// write still blocks as long as the backend does: bound that with short TCP `Timeouts`,
+    /// or send from another thread with `BufferedLogger`. Off by default.
+    pub fn set_fire_and_forget(&mut self, fire_and_forget: bool) {
+        self.fire_and_forget = fire_and_forget;
+    }
+
+    /// returns how many messages and bytes were sent, and messages dropped, so far, whether or
+    /// not in fire and forget mode; messages below `min_severity` count as neither
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// returns the time since the last message was sent, or since the logger was created
+    pub fn idle(&self) -> Duration {
+        self.last_sent.elapsed()
+    }
+
+    /// sends what the backend still buffers or batches, e.g. before a deep sleep
+    pub fn flush(&mut self) -> Result<()> {
+        self.backend.flush().map_err(Error::from)
+    }
+
+    fn send<T>(&mut self, severity: Severity, message: T) -> Result<()>
+    where
+        F: LogFormat<T>,
+    {
+        if severity as u8 > self.min_severity as u8 {
+            return Ok(());
+        }
+        match self.try_send(severity, message) {
+            Ok(()) => {
+                self.stats.sent += 1;
+                self.last_sent = Instant::now();
+                Ok(())
+            }
+            Err(_) if self.fire_and_forget => {
+                self.stats.dropped += 1;
+                Ok(())
+            }
+            Err(e) => {
+                self.stats.dropped += 1;
+                Err(e)
+            }
+        }
+    }
+
+    fn try_send<T>(&mut self, severity: Severity, message: T) -> Result<()>
+    where
+        F: LogFormat<T>,
+    {
+        let mut backend = Counting {
+            inner: &mut self.backend,
+            bytes: 0,
+        };
+        let formatted = self.formatter.format(&mut backend, severity, message);
+        self.stats.bytes += backend.bytes;
+        formatted?;
+        match self.flush_policy {
+            FlushPolicy::AtOrAbove(threshold) if severity as u8 <= threshold as u8 => {
+                self.backend.flush()?
+            }
+            _ => {}
+        }
+        Ok(())
     }
 
     pub fn emerg<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.emerg(&mut self.backend, message)
+        self.send(Severity::LOG_EMERG, message)
     }
 
     pub fn alert<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.alert(&mut self.backend, message)
+        self.send(Severity::LOG_ALERT, message)
     }
 
     pub fn crit<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.crit(&mut self.backend, message)
+        self.send(Severity::LOG_CRIT, message)
     }
 
     pub fn err<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.err(&mut self.backend, message)
+        self.send(Severity::LOG_ERR, message)
     }
 
     pub fn warning<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.warning(&mut self.backend, message)
+        self.send(Severity::LOG_WARNING, message)
     }
 
     pub fn notice<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.notice(&mut self.backend, message)
+        self.send(Severity::LOG_NOTICE, message)
     }
 
     pub fn info<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.info(&mut self.backend, message)
+        self.send(Severity::LOG_INFO, message)
     }
 
     pub fn debug<T>(&mut self, message: T) -> Result<()>
     where
         F: LogFormat<T>,
     {
-        self.formatter.debug(&mut self.backend, message)
+        self.send(Severity::LOG_DEBUG, message)
+    }
+
+    /// sends an emergency message, then blocks until the backend confirms its delivery or
+    /// `timeout` elapses, see `Confirm`
+    ///
+    /// Meant for the last messages before the firmware intentionally reboots.
+    pub fn emerg_confirm<T>(&mut self, message: T, timeout: Duration) -> Result<()>
+    where
+        F: LogFormat<T>,
+        W: Confirm,
+    {
+        self.send(Severity::LOG_EMERG, message)?;
+        self.backend.confirm(timeout).map_err(Error::from)
+    }
+
+    /// sends an alert message, then blocks until the backend confirms its delivery or `timeout`
+    /// elapses, see `Confirm`
+    pub fn alert_confirm<T>(&mut self, message: T, timeout: Duration) -> Result<()>
+    where
+        F: LogFormat<T>,
+        W: Confirm,
+    {
+        self.send(Severity::LOG_ALERT, message)?;
+        self.backend.confirm(timeout).map_err(Error::from)
+    }
+
+    pub fn emerg_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_EMERG, WithFields { message, fields })
+    }
+
+    pub fn alert_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_ALERT, WithFields { message, fields })
+    }
+
+    pub fn crit_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_CRIT, WithFields { message, fields })
+    }
+
+    pub fn err_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_ERR, WithFields { message, fields })
+    }
+
+    pub fn warning_with<'a, T>(
+        &mut self,
+        message: T,
+        fields: &'a [(&'a str, &'a str)],
+    ) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_WARNING, WithFields { message, fields })
+    }
+
+    pub fn notice_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_NOTICE, WithFields { message, fields })
+    }
+
+    pub fn info_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_INFO, WithFields { message, fields })
+    }
+
+    pub fn debug_with<'a, T>(&mut self, message: T, fields: &'a [(&'a str, &'a str)]) -> Result<()>
+    where
+        F: LogFormat<WithFields<'a, T>>,
+    {
+        self.send(Severity::LOG_DEBUG, WithFields { message, fields })
+    }
+
+    /// sends a hex and ASCII dump of `bytes` at `LOG_DEBUG`, one message per 16 bytes
+    ///
+    /// Only the first 1024 bytes are dumped, a last message telling how many were left out.
+    pub fn debug_hex(&mut self, label: &str, bytes: &[u8]) -> Result<()>
+    where
+        F: LogFormat<String>,
+    {
+        for line in hex::hex_lines(label, bytes) {
+            self.send(Severity::LOG_DEBUG, line.to_string())?;
+        }
+        if bytes.len() > hex::HEX_MAX_BYTES {
+            let omitted = bytes.len() - hex::HEX_MAX_BYTES;
+            self.send(
+                Severity::LOG_DEBUG,
+                format!("{label} ... {omitted} more bytes"),
+            )?;
+        }
+        Ok(())
     }
 }
 
 pub enum LoggerBackend {
+    /// An unconnected UDP socket, sending every message to the address with `send_to`
     Udp(UdpSocket, SocketAddr),
+    /// A UDP socket connected to its server, see `udp`
+    UdpConnected(UdpSocket),
+    /// A UDP socket following the address of its server's name, see `udp_resolving`
+    ResolvingUdp(ResolvingUdpSocket),
     Tcp(BufWriter<TcpStream>),
+    /// A TCP connection re-established when the server closes it, see `tcp`
+    ReconnectingTcp(ReconnectingTcpStream),
+    /// The host's local syslog socket, only available on desktop builds
+    #[cfg(all(unix, not(target_os = "espidf")))]
+    Unix(UnixDatagram),
+    /// The host's local syslog socket when it is a stream one, every frame being terminated by
+    /// a NUL byte as `syslog(3)` does
+    #[cfg(all(unix, not(target_os = "espidf")))]
+    UnixStream(UnixStream),
+    /// An OpenTelemetry collector, see `otlp`
+    #[cfg(feature = "otlp")]
+    Otlp(OtlpExporter),
+    /// A Fluentd or Fluent Bit `forward` input, see `fluentd`
+    #[cfg(feature = "fluentd")]
+    Fluent(FluentForward),
+    /// A Splunk HTTP Event Collector, see `splunk`
+    #[cfg(feature = "splunk")]
+    Splunk(SplunkHec),
+    /// An HTTP ingestion endpoint taking newline-delimited batches, see `http_bulk`
+    #[cfg(feature = "http")]
+    Http(HttpBackend),
+    /// A QUIC stream, see `quic`
+    #[cfg(feature = "quic")]
+    Quic(QuicStream),
+    /// A TLS connection (RFC 5425), see `tls`
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+    /// Another backend, writing to a local fallback what it fails to send, see `with_fallback`
+    Fallback(Box<FallbackBackend<LoggerBackend, Box<dyn Write + Send>>>),
+    /// Redundant servers, see `tcp_failover`
+    Failover(FailoverBackend),
+    /// A Graylog GELF UDP input, see `gelf`
+    Gelf(GelfUdp),
+    /// Another backend, storing what it fails to send until it can, see `with_spool`
+    Spool(Box<SpoolBackend<LoggerBackend, Box<dyn SpoolStorage + Send>>>),
+    /// Another backend, sent several frames at once, see `with_batching`
+    Batching(Box<BatchingBackend<LoggerBackend>>),
+    /// The in-process fake server of the `host-sim` feature
+    #[cfg(feature = "host-sim")]
+    Sim(sim::SimSocket),
+    /// Keeps frames in RAM until a network backend is attached, see `init_early`
+    Memory(LimitedStorage<MemoryStorage>),
+    /// A transport of the application, see `custom`
+    Custom(Box<dyn Transport + Send>),
+}
+
+/// Backends able to confirm that the messages written so far were delivered
+pub trait Confirm {
+    /// flushes the backend, then blocks until everything written so far is delivered, as far as
+    /// the transport can tell, or until `timeout` elapses
+    fn confirm(&mut self, timeout: Duration) -> io::Result<()>;
+}
+
+impl Confirm for LoggerBackend {
+    /// Neither UDP nor TCP acknowledge syslog messages: datagram backends confirm right away, and
+    /// the TCP backend once its buffer is handed over to the network stack, failing if that takes
+    /// longer than `timeout`. The wrapping backends hand what they hold over to the backend they
+    /// wrap, which confirms it: the spool replays its frames, and failover backends confirm with
+    /// the active one.
+    fn confirm(&mut self, timeout: Duration) -> io::Result<()> {
+        match *self {
+            LoggerBackend::Tcp(ref mut socket) => {
+                let previous = socket.get_ref().write_timeout()?;
+                socket.get_ref().set_write_timeout(Some(timeout))?;
+                let flushed = socket.flush();
+                socket.get_ref().set_write_timeout(previous)?;
+                flushed
+            }
+            LoggerBackend::ReconnectingTcp(ref mut socket) => {
+                let previous = match socket.get_ref() {
+                    Some(stream) => {
+                        let previous = stream.write_timeout()?;
+                        stream.set_write_timeout(Some(timeout))?;
+                        previous
+                    }
+                    None => None,
+                };
+                let flushed = socket.flush();
+                if let Some(stream) = socket.get_ref() {
+                    stream.set_write_timeout(previous)?;
+                }
+                flushed
+            }
+            #[cfg(feature = "fluentd")]
+            LoggerBackend::Fluent(ref mut forward) => forward.confirm(timeout),
+            LoggerBackend::Spool(ref mut spool) => {
+                spool.replay()?;
+                spool.network.confirm(timeout)
+            }
+            LoggerBackend::Batching(ref mut batching) => {
+                batching.send_batch()?;
+                batching.inner.confirm(timeout)
+            }
+            LoggerBackend::Fallback(ref mut fallback) => {
+                let _ = fallback.secondary.flush();
+                fallback.primary.confirm(timeout)
+            }
+            LoggerBackend::Failover(ref mut failover) => failover.active_mut().confirm(timeout),
+            _ => self.flush(),
+        }
+    }
+}
+
+impl LoggerBackend {
+    /// returns the underlying socket of a TCP backend, to adjust its options
+    pub fn tcp_stream(&self) -> Option<&TcpStream> {
+        match *self {
+            LoggerBackend::Tcp(ref socket) => Some(socket.get_ref()),
+            LoggerBackend::ReconnectingTcp(ref socket) => socket.get_ref(),
+            _ => None,
+        }
+    }
+
+    /// enables or disables Nagle's algorithm (`TCP_NODELAY`) on a TCP backend
+    ///
+    /// Turn it on (`true`) for interactive debugging, so every message leaves immediately; keep
+    /// it off for batched bulk delivery. Fails on other backends.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        match self.tcp_stream() {
+            Some(socket) => socket.set_nodelay(nodelay),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "TCP_NODELAY only applies to TCP backends",
+            )),
+        }
+    }
+
+    /// returns how many times the TCP connections of the backend were re-established, see
+    /// `ReconnectingTcpStream::reconnects`
+    pub fn reconnects(&self) -> usize {
+        match *self {
+            LoggerBackend::ReconnectingTcp(ref socket) => socket.reconnects(),
+            LoggerBackend::Fallback(ref backend) => backend.primary.reconnects(),
+            LoggerBackend::Spool(ref backend) => backend.network.reconnects(),
+            LoggerBackend::Batching(ref backend) => backend.inner.reconnects(),
+            LoggerBackend::Failover(ref backend) => backend
+                .backends()
+                .iter()
+                .map(LoggerBackend::reconnects)
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    /// returns how many frames the backend dropped because its RAM buffer was full, as the
+    /// one of `init_early` does
+    pub fn overflowed(&self) -> usize {
+        match *self {
+            LoggerBackend::Memory(ref frames) => frames.dropped(),
+            LoggerBackend::Fallback(ref backend) => backend.primary.overflowed(),
+            LoggerBackend::Batching(ref backend) => backend.inner.overflowed(),
+            _ => 0,
+        }
+    }
+
+    /// returns `stats` of a logger with the counters of the backend filled in
+    pub(crate) fn with_counters(&self, stats: Stats) -> Stats {
+        Stats {
+            reconnects: self.reconnects(),
+            overflowed: self.overflowed(),
+            ..stats
+        }
+    }
+
+    /// sets the timeouts of a TCP backend, see `Timeouts`
+    ///
+    /// The connect timeout only applies to backends that reconnect. Redundant servers all get
+    /// them, and a backend with a fallback, a spool or batching passes them on to its network
+    /// backend. Fails on other backends.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) -> io::Result<()> {
+        match *self {
+            LoggerBackend::Tcp(ref socket) => timeouts.apply(socket.get_ref()),
+            LoggerBackend::ReconnectingTcp(ref mut socket) => socket.set_timeouts(timeouts),
+            LoggerBackend::Fallback(ref mut backend) => backend.primary.set_timeouts(timeouts),
+            LoggerBackend::Spool(ref mut backend) => backend.network.set_timeouts(timeouts),
+            LoggerBackend::Batching(ref mut backend) => backend.inner.set_timeouts(timeouts),
+            LoggerBackend::Failover(ref mut backend) => backend
+                .backends_mut()
+                .iter_mut()
+                .try_for_each(|backend| backend.set_timeouts(timeouts)),
+            #[cfg(feature = "host-sim")]
+            LoggerBackend::Sim(_) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "timeouts only apply to TCP backends",
+            )),
+        }
+    }
 }
 
 impl Write for LoggerBackend {
@@ -166,7 +826,39 @@ impl Write for LoggerBackend {
     fn write(&mut self, message: &[u8]) -> io::Result<usize> {
         match *self {
             LoggerBackend::Udp(ref socket, ref addr) => socket.send_to(message, addr),
+            LoggerBackend::UdpConnected(ref socket) => socket.send(message),
+            LoggerBackend::ResolvingUdp(ref mut socket) => socket.write(message),
             LoggerBackend::Tcp(ref mut socket) => socket.write(message),
+            LoggerBackend::ReconnectingTcp(ref mut socket) => socket.write(message),
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::Unix(ref socket) => socket.send(message),
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::UnixStream(ref mut socket) => socket
+                .write_all(&nul_terminated(message))
+                .map(|()| message.len()),
+            #[cfg(feature = "otlp")]
+            LoggerBackend::Otlp(ref mut exporter) => exporter.write(message),
+            #[cfg(feature = "fluentd")]
+            LoggerBackend::Fluent(ref mut forward) => forward.write(message),
+            #[cfg(feature = "splunk")]
+            LoggerBackend::Splunk(ref mut hec) => hec.write(message),
+            #[cfg(feature = "http")]
+            LoggerBackend::Http(ref mut backend) => backend.write(message),
+            #[cfg(feature = "quic")]
+            LoggerBackend::Quic(ref mut stream) => stream.write(message),
+            #[cfg(feature = "tls")]
+            LoggerBackend::Tls(ref mut stream) => stream.write(message),
+            LoggerBackend::Fallback(ref mut fallback) => fallback.write(message),
+            LoggerBackend::Failover(ref mut failover) => failover.write(message),
+            LoggerBackend::Gelf(ref mut socket) => socket.write(message),
+            LoggerBackend::Spool(ref mut spool) => spool.write(message),
+            LoggerBackend::Batching(ref mut batching) => batching.write(message),
+            #[cfg(feature = "host-sim")]
+            LoggerBackend::Sim(ref mut socket) => socket.write(message),
+            LoggerBackend::Memory(ref mut frames) => frames.push(message).map(|()| message.len()),
+            LoggerBackend::Custom(ref mut transport) => {
+                transport.send(message).map(|()| message.len())
+            }
         }
     }
 
@@ -176,161 +868,1700 @@ impl Write for LoggerBackend {
                 let message = fmt::format(args);
                 socket.send_to(message.as_bytes(), addr).map(|_| ())
             }
+            LoggerBackend::UdpConnected(ref socket) => {
+                let message = fmt::format(args);
+                socket.send(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::ResolvingUdp(ref mut socket) => {
+                let message = fmt::format(args);
+                socket.write(message.as_bytes()).map(|_| ())
+            }
             LoggerBackend::Tcp(ref mut socket) => socket.write_fmt(args),
+            // A framed stream takes every write as a whole frame
+            LoggerBackend::ReconnectingTcp(ref mut socket) => {
+                let message = fmt::format(args);
+                socket.write_all(message.as_bytes())
+            }
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::Unix(ref socket) => {
+                let message = fmt::format(args);
+                socket.send(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::UnixStream(ref mut socket) => {
+                let message = fmt::format(args);
+                socket.write_all(&nul_terminated(message.as_bytes()))
+            }
+            #[cfg(feature = "otlp")]
+            LoggerBackend::Otlp(ref mut exporter) => {
+                let message = fmt::format(args);
+                exporter.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "fluentd")]
+            LoggerBackend::Fluent(ref mut forward) => {
+                let message = fmt::format(args);
+                forward.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "splunk")]
+            LoggerBackend::Splunk(ref mut hec) => {
+                let message = fmt::format(args);
+                hec.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "http")]
+            LoggerBackend::Http(ref mut backend) => {
+                let message = fmt::format(args);
+                backend.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "quic")]
+            LoggerBackend::Quic(ref mut stream) => {
+                let message = fmt::format(args);
+                stream.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "tls")]
+            LoggerBackend::Tls(ref mut stream) => {
+                let message = fmt::format(args);
+                stream.write(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::Fallback(ref mut fallback) => {
+                let message = fmt::format(args);
+                fallback.write(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::Failover(ref mut failover) => {
+                let message = fmt::format(args);
+                failover.write(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::Gelf(ref mut socket) => {
+                let message = fmt::format(args);
+                socket.write(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::Spool(ref mut spool) => {
+                let message = fmt::format(args);
+                spool.write(message.as_bytes()).map(|_| ())
+            }
+            LoggerBackend::Batching(ref mut batching) => {
+                let message = fmt::format(args);
+                batching.write(message.as_bytes()).map(|_| ())
+            }
+            #[cfg(feature = "host-sim")]
+            LoggerBackend::Sim(ref mut socket) => socket.write_fmt(args),
+            LoggerBackend::Memory(ref mut frames) => frames.push(fmt::format(args).as_bytes()),
+            LoggerBackend::Custom(ref mut transport) => {
+                transport.send(fmt::format(args).as_bytes())
+            }
         }
     }
 
     fn flush(&mut self) -> io::Result<()> {
         match *self {
             LoggerBackend::Udp(_, _) => Ok(()),
+            LoggerBackend::UdpConnected(_) => Ok(()),
+            LoggerBackend::ResolvingUdp(_) => Ok(()),
             LoggerBackend::Tcp(ref mut socket) => socket.flush(),
+            LoggerBackend::ReconnectingTcp(ref mut socket) => socket.flush(),
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::Unix(_) => Ok(()),
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::UnixStream(ref mut socket) => socket.flush(),
+            #[cfg(feature = "otlp")]
+            LoggerBackend::Otlp(ref mut exporter) => exporter.flush(),
+            #[cfg(feature = "fluentd")]
+            LoggerBackend::Fluent(ref mut forward) => forward.flush(),
+            #[cfg(feature = "splunk")]
+            LoggerBackend::Splunk(ref mut hec) => hec.flush(),
+            #[cfg(feature = "http")]
+            LoggerBackend::Http(ref mut backend) => backend.flush(),
+            #[cfg(feature = "quic")]
+            LoggerBackend::Quic(ref mut stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            LoggerBackend::Tls(ref mut stream) => stream.flush(),
+            LoggerBackend::Fallback(ref mut fallback) => fallback.flush(),
+            LoggerBackend::Failover(ref mut failover) => failover.flush(),
+            LoggerBackend::Gelf(_) => Ok(()),
+            LoggerBackend::Spool(ref mut spool) => spool.flush(),
+            LoggerBackend::Batching(ref mut batching) => batching.flush(),
+            #[cfg(feature = "host-sim")]
+            LoggerBackend::Sim(ref mut socket) => socket.flush(),
+            LoggerBackend::Memory(_) => Ok(()),
+            LoggerBackend::Custom(ref mut transport) => transport.flush(),
         }
     }
 }
 
 /// returns a UDP logger connecting `local` and `server`
+///
+/// The socket is connected to `server` once, which spares lwIP a route lookup per message,
+/// and makes an unreachable server show up as an error (`ConnectionRefused`) on the following
+/// messages. Use `udp_unconnected` to keep a socket able to send anywhere. The addresses of
+/// `server` are tried in turn, the first one of the family of `local` being used, see
+/// `AddressPreference`.
+#[cfg(not(feature = "host-sim"))]
 pub fn udp<T: ToSocketAddrs, F>(
     formatter: F,
     local: T,
     server: T,
 ) -> Result<Logger<LoggerBackend, F>> {
-    server
-        .to_socket_addrs()
-        .chain_err(|| ErrorKind::Initialization)
-        .and_then(|mut server_addr_opt| {
-            server_addr_opt
-                .next()
-                .chain_err(|| ErrorKind::Initialization)
-        })
-        .and_then(|server_addr| {
-            UdpSocket::bind(local)
-                .chain_err(|| ErrorKind::Initialization)
-                .map(|socket| {
-                    socket.connect(server_addr.clone()).unwrap();
-                    Logger {
-                        formatter,
-                        backend: LoggerBackend::Udp(socket, server_addr),
-                    }
-                })
-        })
+    let servers = AddressPreference::Any
+        .resolve(server)
+        .map_err(Error::Resolution)?;
+    UdpSocket::bind(local)
+        .and_then(|socket| first_ok(&servers, |server| socket.connect(server)).map(|()| socket))
+        .map_err(Error::from)
+        .map(|socket| Logger::new(LoggerBackend::UdpConnected(socket), formatter))
 }
 
-/// returns a TCP logger connecting `local` and `server`
-pub fn tcp<T: ToSocketAddrs, F>(formatter: F, server: T) -> Result<Logger<LoggerBackend, F>> {
-    TcpStream::connect(server)
-        .chain_err(|| ErrorKind::Initialization)
-        .map(|socket| Logger {
-            formatter,
-            backend: LoggerBackend::Tcp(BufWriter::new(socket)),
+/// returns a UDP logger bound as `bind` says, connecting to `server`, see `udp`
+///
+/// The socket is bound for the family of the first address of `server`, or the family of
+/// `bind.address`, the addresses of another family being skipped.
+pub fn udp_bound<T: ToSocketAddrs, F>(
+    formatter: F,
+    bind: UdpBind,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    let mut servers = AddressPreference::Any
+        .resolve(server)
+        .map_err(Error::Resolution)?;
+    let local = bind.local_addr(&servers[0]);
+    servers.retain(|server| server.is_ipv4() == local.is_ipv4());
+    udp(formatter, &[local][..], &servers[..])
+}
+
+/// returns a UDP logger connecting `local` and the address `server` resolves to, resolving it
+/// again every `interval` and after a failed send, see `ResolvingUdpSocket`
+///
+/// For a collector behind a dynamic DNS name, e.g. `logs.example.net:514`, whose address
+/// changes while the device runs.
+#[cfg(not(feature = "host-sim"))]
+pub fn udp_resolving<T: ToSocketAddrs, F>(
+    formatter: F,
+    local: T,
+    server: &str,
+    interval: Duration,
+) -> Result<Logger<LoggerBackend, F>> {
+    ResolvingUdpSocket::connect(local, server)
+        .map_err(Error::from)
+        .map(|mut socket| {
+            socket.set_resolve_interval(interval);
+            Logger::new(LoggerBackend::ResolvingUdp(socket), formatter)
         })
 }
 
-#[derive(Clone)]
-pub struct BasicLogger {
-    logger: Arc<Mutex<Logger<LoggerBackend, Formatter3164>>>,
-    esp_logger: Arc<Mutex<esp_idf_svc::log::EspLogger>>,
+/// returns a UDP logger bound to `local`, sending every message to `server` with `send_to`
+#[cfg(not(feature = "host-sim"))]
+pub fn udp_unconnected<T: ToSocketAddrs, F>(
+    formatter: F,
+    local: T,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server).and_then(|server_addr| {
+        UdpSocket::bind(local)
+            .map_err(Error::initialization)
+            .map(|socket| Logger::new(LoggerBackend::Udp(socket, server_addr), formatter))
+    })
 }
 
-impl BasicLogger {
-    pub fn new(logger: Logger<LoggerBackend, Formatter3164>) -> BasicLogger {
-        let esp_logger = esp_idf_svc::log::EspLogger::default();
-        esp_logger.set_target_level("main", log::LevelFilter::Info).expect("Failed to set target level");
-        BasicLogger {
-            logger: Arc::new(Mutex::new(logger)),
-            esp_logger: Arc::new(Mutex::new(esp_logger)),
-        }
+/// returns a UDP logger bound to `local`, broadcasting every message to `server`, e.g.
+/// `255.255.255.255:514` or the broadcast address of the subnet, `192.168.1.255:514`
+///
+/// For a collector on the LAN whose address the device doesn't know: the socket is
+/// `udp_unconnected`'s, with `SO_BROADCAST` set.
+pub fn udp_broadcast<T: ToSocketAddrs, F>(
+    formatter: F,
+    local: T,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    let logger = udp_unconnected(formatter, local, server)?;
+    if let LoggerBackend::Udp(ref socket, _) = logger.backend {
+        socket.set_broadcast(true).map_err(Error::initialization)?;
     }
+    Ok(logger)
 }
 
-#[allow(unused_variables, unused_must_use)]
-impl Log for BasicLogger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= log::max_level() && metadata.level() <= log::STATIC_MAX_LEVEL
+/// returns a UDP logger bound to `local`, sending every message to the multicast `group`,
+/// e.g. `239.255.0.1:514`, for any collector of the LAN that joined it
+///
+/// Sending doesn't join the group. `ttl` is the number of routers an IPv4 message may cross, 1
+/// keeping it on the local network; IPv6 messages have the system's default hop limit, and
+/// leave on the interface it picks. Fails if `group` isn't a multicast address.
+pub fn udp_multicast<T: ToSocketAddrs, F>(
+    formatter: F,
+    local: T,
+    group: T,
+    ttl: u32,
+) -> Result<Logger<LoggerBackend, F>> {
+    if !resolve_first(&group)?.ip().is_multicast() {
+        return Err(Error::initialization("not a multicast address"));
     }
-
-    fn log(&self, record: &Record) {
-        let message = format!("{}", record.args());
-        let esp_logger = self.esp_logger.lock().unwrap();
-        esp_logger.log(record);
-        match STATE.load(std::sync::atomic::Ordering::Relaxed) {
-            INITIALIZED => {
-                let mut logger = self.logger.lock().unwrap();
-                match record.level() {
-                    Level::Error => logger.err(message),
-                    Level::Warn => logger.warning(message),
-                    Level::Info => logger.info(message),
-                    Level::Debug => logger.debug(message),
-                    Level::Trace => logger.debug(message),
-                };
-            },
-            UNINITIALIZED => {}, // uninitialized
-            _ => {} // uninitialized
+    let logger = udp_unconnected(formatter, local, group)?;
+    if let LoggerBackend::Udp(ref socket, group_addr) = logger.backend {
+        if group_addr.is_ipv4() {
+            socket
+                .set_multicast_ttl_v4(ttl)
+                .map_err(Error::initialization)?;
         }
-
     }
+    Ok(logger)
+}
 
-    fn flush(&self) {
-        let _ = self.logger.lock().unwrap().backend.flush();
-    }
+/// returns a TCP logger connecting `local` and `server`
+///
+/// The connection is probed before writing (every 5 seconds at most), and re-established if
+/// the server closed it. Connecting and writing time out after 5 seconds, see
+/// `tcp_with_timeouts`.
+#[cfg(not(feature = "host-sim"))]
+pub fn tcp<T: ToSocketAddrs, F>(formatter: F, server: T) -> Result<Logger<LoggerBackend, F>> {
+    tcp_with_timeouts(formatter, server, Timeouts::default())
 }
 
-/// UDP Logger init function compatible with log crate
-pub fn init_udp<T: ToSocketAddrs>(
-    local: T,
+/// returns a TCP logger like `tcp`, giving up on the network after `timeouts`
+///
+/// The addresses of `server`, IPv6 and IPv4 ones, are tried in turn until a connection is
+/// made, which reconnects to the same address from then on; see `AddressPreference` to change
+/// their order.
+#[cfg(not(feature = "host-sim"))]
+pub fn tcp_with_timeouts<T: ToSocketAddrs, F>(
+    formatter: F,
     server: T,
-    facility: Facility,
-    log_level: log::LevelFilter,
-    process: String,
-    pid: u32,
-) -> Result<()> {
-    let formatter = Formatter3164 {
-        facility,
-        process,
-        pid,
-    };
-    let logger = udp(formatter, local, server).unwrap();
-    let basic_logger = Box::new(BasicLogger::new(logger));
-    log::set_logger(Box::leak(basic_logger)).chain_err(|| ErrorKind::Initialization)?;
-
-    log::set_max_level(log_level);
-    Ok(())
+    timeouts: Timeouts,
+) -> Result<Logger<LoggerBackend, F>> {
+    AddressPreference::Any
+        .resolve(server)
+        .and_then(|servers| {
+            first_ok(&servers, |server| {
+                ReconnectingTcpStream::connect_with_timeouts(server, timeouts)
+            })
+        })
+        .map_err(Error::from)
+        .map(|socket| Logger::new(LoggerBackend::ReconnectingTcp(socket), formatter))
 }
 
-/// TCP Logger init function compatible with log crate
-pub fn init_tcp<T: ToSocketAddrs>(
+#[cfg(all(feature = "host-sim", feature = "fluentd"))]
+pub use sim::fluentd;
+#[cfg(all(feature = "host-sim", feature = "quic"))]
+pub use sim::quic;
+#[cfg(feature = "host-sim")]
+use sim::tcp_lazy_backend;
+#[cfg(all(feature = "host-sim", feature = "tls"))]
+pub use sim::tls;
+#[cfg(feature = "host-sim")]
+pub use sim::{gelf, tcp, tcp_failover, tcp_with_timeouts, udp, udp_resolving, udp_unconnected};
+
+/// returns a TCP logger like `tcp`, delimiting every message with `framing` (RFC 6587)
+///
+/// rsyslog and syslog-ng accept both framings on their TCP inputs. The syslog formatters send
+/// every message on a single line, so either framing delimits them; `Framing::OctetCounted` is
+/// the one that also carries the frames of custom formatters unchanged.
+pub fn tcp_with_framing<T: ToSocketAddrs, F>(
+    formatter: F,
     server: T,
-    facility: Facility,
-    log_level: log::LevelFilter,
-    process: String,
-    pid: u32,
-) -> Result<()> {
-    let formatter = Formatter3164 {
-        facility,
-        process,
-        pid,
-    };
+    framing: Framing,
+) -> Result<Logger<LoggerBackend, F>> {
+    let mut logger = tcp(formatter, server)?;
+    if let LoggerBackend::ReconnectingTcp(ref mut stream) = logger.backend {
+        stream.set_framing(Some(framing));
+    }
+    Ok(logger)
+}
 
-    let logger = tcp(formatter, server).unwrap();
-    let basic_logger = Box::new(BasicLogger::new(logger));
-    log::set_logger(Box::leak(basic_logger)).chain_err(|| ErrorKind::Initialization)?;
+/// returns a TCP logger connecting to `server` on the first message rather than right away,
+/// keeping up to `buffer_bytes` of the messages it can't send yet
+///
+/// For a logger registered at boot, before the Wi-Fi is up: the messages logged until the
+/// server can be reached are spooled in RAM, the oldest ones being dropped when full, and sent
+/// in order once it is, see `SpoolBackend`. Connection attempts are spaced with `Backoff`, and
+/// try the addresses of `server` in turn, see `ReconnectingTcpStream::lazy_any`. `server` is
+/// resolved here, so give an address if no DNS server can be reached yet.
+pub fn tcp_lazy<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    buffer_bytes: usize,
+) -> Result<Logger<LoggerBackend, F>> {
+    let servers = AddressPreference::Any
+        .resolve(server)
+        .map_err(Error::Resolution)?;
+    let frames = LimitedStorage::new(MemoryStorage::new(), buffer_bytes);
+    let backend = tcp_lazy_backend(&servers)?.with_spool(frames);
+    Ok(Logger::new(backend, formatter))
+}
+
+/// returns a `ReconnectingTcpStream` backend connecting to the first of `servers` that takes
+/// the connection, on the first message
+#[cfg(not(feature = "host-sim"))]
+pub(crate) fn tcp_lazy_backend(servers: &[SocketAddr]) -> io::Result<LoggerBackend> {
+    ReconnectingTcpStream::lazy_any(servers).map(LoggerBackend::ReconnectingTcp)
+}
+
+/// returns a TCP logger sending to the first server of `servers` that takes the messages, see
+/// `FailoverBackend`
+///
+/// Connections are made on the first message sent to each server, trying its addresses in
+/// turn, see `ReconnectingTcpStream::lazy_any`, so this only fails if a server can't be
+/// resolved, or without any.
+#[cfg(not(feature = "host-sim"))]
+pub fn tcp_failover<T: ToSocketAddrs, F>(
+    formatter: F,
+    servers: &[T],
+) -> Result<Logger<LoggerBackend, F>> {
+    let mut backends = Vec::with_capacity(servers.len());
+    for server in servers {
+        let addrs = AddressPreference::Any
+            .resolve(server)
+            .map_err(Error::Resolution)?;
+        backends.push(tcp_lazy_backend(&addrs)?);
+    }
+    FailoverBackend::new(backends)
+        .map_err(Error::from)
+        .map(|backend| Logger::new(LoggerBackend::Failover(backend), formatter))
+}
+
+/// returns a logger exporting to the OpenTelemetry collector at `url` (OTLP/HTTP), see
+/// `OtlpExporter`
+///
+/// Records are sent in batches: lower `flush_policy` thresholds, or flush the backend, to
+/// send them sooner.
+#[cfg(feature = "otlp")]
+pub fn otlp<F>(formatter: F, url: &str, service_name: &str) -> Result<Logger<LoggerBackend, F>> {
+    OtlpExporter::new(url, service_name)
+        .map_err(Error::initialization)
+        .map(|exporter| Logger::new(LoggerBackend::Otlp(exporter), formatter))
+}
+
+/// returns a logger sending GELF messages to the Graylog GELF UDP input at `server`, chunking
+/// the long ones, see `GelfUdp`
+///
+/// Use it with `FormatterGelf`, so Graylog takes the messages without a syslog relay.
+#[cfg(not(feature = "host-sim"))]
+pub fn gelf<T: ToSocketAddrs, F>(formatter: F, server: T) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server)
+        .and_then(|server_addr| GelfUdp::connect(server_addr).map_err(Error::from))
+        .map(|socket| Logger::new(LoggerBackend::Gelf(socket), formatter))
+}
+
+/// returns a logger sending to the Fluentd or Fluent Bit `forward` input at `server`, tagging
+/// events with `tag`, see `FluentForward`
+#[cfg(all(feature = "fluentd", not(feature = "host-sim")))]
+pub fn fluentd<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    tag: &str,
+) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server)
+        .and_then(|server_addr| FluentForward::connect(server_addr, tag).map_err(Error::from))
+        .map(|forward| Logger::new(LoggerBackend::Fluent(forward), formatter))
+}
+
+/// returns a logger posting to the Splunk HTTP Event Collector at `url`, see `SplunkHec`
+///
+/// `https://` URLs need the `tls` feature: build the `SplunkHec` with its TLS configuration
+/// and pass it to `Logger::new` instead. Events are sent in batches, like with `otlp`.
+#[cfg(feature = "splunk")]
+pub fn splunk<F>(formatter: F, url: &str, token: &str) -> Result<Logger<LoggerBackend, F>> {
+    SplunkHec::new(url, token)
+        .map_err(Error::initialization)
+        .map(|hec| Logger::new(LoggerBackend::Splunk(hec), formatter))
+}
+
+/// returns a logger POSTing batches of newline-delimited frames to `url`, see `HttpBackend`
+///
+/// `https://` URLs need the `tls` feature: build the `HttpBackend` with its TLS configuration
+/// and pass it to `Logger::new` instead.
+#[cfg(feature = "http")]
+pub fn http_bulk<F>(formatter: F, url: &str) -> Result<Logger<LoggerBackend, F>> {
+    HttpBackend::new(url)
+        .map_err(Error::initialization)
+        .map(|backend| Logger::new(LoggerBackend::Http(backend), formatter))
+}
+
+/// returns a logger publishing to `topic` with `publish`, see `MqttBackend`
+#[cfg(feature = "mqtt")]
+pub fn mqtt<F, P>(formatter: F, topic: &str, publish: P) -> Logger<LoggerBackend, F>
+where
+    P: FnMut(&str, &[u8]) -> io::Result<()> + Send + 'static,
+{
+    Logger::new(
+        LoggerBackend::custom(MqttBackend::new(topic, publish)),
+        formatter,
+    )
+}
+
+/// returns a logger sending to `server` over QUIC, see `QuicStream`
+///
+/// The certificate of `server` must be valid for `server_name` according to `config`.
+/// Experimental.
+#[cfg(all(feature = "quic", not(feature = "host-sim")))]
+pub fn quic<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    server_name: &str,
+    config: quinn::ClientConfig,
+) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server)
+        .and_then(|server_addr| {
+            QuicStream::connect(server_addr, server_name, config).map_err(Error::from)
+        })
+        .map(|stream| Logger::new(LoggerBackend::Quic(stream), formatter))
+}
+
+/// returns a logger sending to `server` over TLS (RFC 5425), see `TlsStream`
+///
+/// The certificate of `server` must be valid for `server_name` according to `config`. The
+/// connection is re-established on the next message if it is lost.
+#[cfg(all(feature = "tls", not(feature = "host-sim")))]
+pub fn tls<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    server_name: &str,
+    config: Arc<rustls::ClientConfig>,
+) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server)
+        .and_then(|server_addr| {
+            TlsStream::connect(server_addr, server_name, config).map_err(Error::tls)
+        })
+        .map(|stream| Logger::new(LoggerBackend::Tls(stream), formatter))
+}
+
+/// returns a logger writing to the host's local syslog socket, `/dev/log`
+///
+/// Only available on desktop builds, e.g. when the firmware logic runs on a Linux gateway.
+#[cfg(all(unix, not(target_os = "espidf")))]
+pub fn unix<F>(formatter: F) -> Result<Logger<LoggerBackend, F>> {
+    unix_custom(formatter, "/dev/log")
+}
+
+/// returns a logger writing to the local syslog socket at `path`
+///
+/// The socket is connected as a datagram one first, then as a stream one, for the syslog
+/// daemons listening on a stream socket (e.g. some syslog-ng setups).
+#[cfg(all(unix, not(target_os = "espidf")))]
+pub fn unix_custom<P: AsRef<Path>, F>(formatter: F, path: P) -> Result<Logger<LoggerBackend, F>> {
+    let path = path.as_ref();
+    UnixDatagram::unbound()
+        .and_then(|socket| socket.connect(path).map(|()| LoggerBackend::Unix(socket)))
+        .or_else(|_| UnixStream::connect(path).map(LoggerBackend::UnixStream))
+        .map_err(Error::from)
+        .map(|backend| Logger::new(backend, formatter))
+}
+
+/// returns `frame` followed by a NUL byte, to be sent with a single write
+#[cfg(all(unix, not(target_os = "espidf")))]
+fn nul_terminated(frame: &[u8]) -> Vec<u8> {
+    let mut terminated = Vec::with_capacity(frame.len() + 1);
+    terminated.extend_from_slice(frame);
+    terminated.push(0);
+    terminated
+}
+
+/// A writer counting the bytes written to `inner`, for `Stats::bytes`
+struct Counting<'a, W: 'a> {
+    inner: &'a mut W,
+    bytes: usize,
+}
+
+impl<'a, W: Write> Write for Counting<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// see `BasicLogger::set_on_error`
+type ErrorCallback = Box<dyn FnMut(&Error) + Send>;
+
+#[derive(Clone)]
+pub struct BasicLogger {
+    logger: Arc<Lock<Logger<LoggerBackend, Formatter3164>>>,
+    levels: Arc<Lock<TargetLevels>>,
+    /// a `LevelFilter` as `usize`, see `set_level`
+    level: Arc<AtomicUsize>,
+    annotator: Arc<Lock<Option<Annotator>>>,
+    on_error: Arc<Lock<Option<ErrorCallback>>>,
+    boot_id: bool,
+    wifi_fields: bool,
+    rebind_check: Option<Arc<Lock<RebindCheck>>>,
+    console: Option<ConsoleFormatter>,
+    /// see `scoped`
+    scope: Option<Arc<Scope>>,
+    /// see `Stats::reentrant`
+    reentrant: Arc<AtomicUsize>,
+    #[cfg(target_os = "espidf")]
+    esp_logger: Arc<Lock<esp_idf_svc::log::EspLogger>>,
+}
+
+impl BasicLogger {
+    #[cfg(target_os = "espidf")]
+    pub fn new(logger: Logger<LoggerBackend, Formatter3164>) -> BasicLogger {
+        let esp_logger = esp_idf_svc::log::EspLogger::default();
+        esp_logger.set_target_level("main", log::LevelFilter::Info).expect("Failed to set target level");
+        BasicLogger {
+            logger: Arc::new(Lock::new(logger)),
+            levels: Arc::new(Lock::new(TargetLevels::default())),
+            level: Arc::new(AtomicUsize::new(LevelFilter::Trace as usize)),
+            annotator: Arc::new(Lock::new(None)),
+            on_error: Arc::new(Lock::new(None)),
+            boot_id: false,
+            wifi_fields: false,
+            rebind_check: None,
+            console: None,
+            scope: None,
+            reentrant: Arc::new(AtomicUsize::new(0)),
+            esp_logger: Arc::new(Lock::new(esp_logger)),
+        }
+    }
+
+    #[cfg(not(target_os = "espidf"))]
+    pub fn new(logger: Logger<LoggerBackend, Formatter3164>) -> BasicLogger {
+        BasicLogger {
+            logger: Arc::new(Lock::new(logger)),
+            levels: Arc::new(Lock::new(TargetLevels::default())),
+            level: Arc::new(AtomicUsize::new(LevelFilter::Trace as usize)),
+            annotator: Arc::new(Lock::new(None)),
+            on_error: Arc::new(Lock::new(None)),
+            boot_id: false,
+            wifi_fields: false,
+            rebind_check: None,
+            console: None,
+            scope: None,
+            reentrant: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// prints every message on the console with `formatter`, independently of the wire
+    /// formatter
+    ///
+    /// On the ESP32 this replaces the `EspLogger` output; elsewhere nothing is printed
+    /// otherwise.
+    pub fn with_console(mut self, formatter: ConsoleFormatter) -> BasicLogger {
+        self.console = Some(formatter);
+        self
+    }
+
+    /// sends the ID of the current boot, see `boot_id`, as the `boot` field of every message
+    pub fn with_boot_id(mut self) -> BasicLogger {
+        self.boot_id = true;
+        self
+    }
+
+    /// sends the `rssi` and `channel` of the access point, sampled as every message is logged,
+    /// see `wifi_signal`
+    ///
+    /// Handy to tell whether messages arrive in bursts, or not at all, because of the signal.
+    pub fn with_wifi_fields(mut self) -> BasicLogger {
+        self.wifi_fields = true;
+        self
+    }
+
+    /// checks, at most once per `interval` as messages are logged, that the local address of
+    /// the backend is still the one reaching the server, and rebinds it otherwise, see
+    /// `LoggerBackend::rebind`
+    pub fn with_rebind_interval(mut self, interval: Duration) -> BasicLogger {
+        self.rebind_check = Some(Arc::new(Lock::new(RebindCheck::new(interval))));
+        self
+    }
+
+    /// applies the `RUST_LOG`-style filter `spec`, e.g. `wifi=debug,mqtt=warn,info`, to the
+    /// targets of the messages, see `set_filter`
+    ///
+    /// Unlike `set_filter`, `log::max_level` is left alone until the logger is registered.
+    pub fn with_filter(self, spec: &str) -> BasicLogger {
+        self.levels.lock().apply(spec);
+        self
+    }
+
+    #[cfg(feature = "esp-println")]
+    fn log_console(&self, record: &Record) {
+        console::console_line(|| self.print_console(record))
+    }
+
+    #[cfg(not(feature = "esp-println"))]
+    fn log_console(&self, record: &Record) {
+        self.print_console(record)
+    }
+
+    fn print_console(&self, record: &Record) {
+        match self.console {
+            Some(ref console) => {
+                let _ = console.format(&mut std::io::stdout(), record);
+            }
+            #[cfg(target_os = "espidf")]
+            None => self.esp_logger.lock().log(record),
+            #[cfg(not(target_os = "espidf"))]
+            None => {}
+        }
+    }
+
+    /// swaps the backend of the shared logger, returning the previous one
+    ///
+    /// Every clone of this `BasicLogger` switches at once; messages logged concurrently go
+    /// either entirely to the old backend or entirely to the new one.
+    pub fn replace_backend(&self, backend: LoggerBackend) -> LoggerBackend {
+        self.logger.lock().replace_backend(backend)
+    }
+
+    /// changes the level of `target` and every target below it (`wifi` also covers
+    /// `wifi::scan`), leaving the other targets alone
+    ///
+    /// `log::max_level` is raised if needed so the more verbose messages reach this logger.
+    /// Until an override is set, the other targets keep the `log::max_level` in effect at
+    /// that point.
+    pub fn set_target_level(&self, target: &str, level: LevelFilter) -> Result<()> {
+        let mut levels = self.levels.lock();
+        if !levels.has_default() {
+            levels.set_default(log::max_level());
+        }
+        levels.set(target, level);
+        log::set_max_level(levels.max());
+
+        #[cfg(target_os = "espidf")]
+        self.esp_logger
+            .lock()
+            .set_target_level(target, level)
+            .map_err(Error::initialization)?;
+        Ok(())
+    }
+
+    /// calls `annotator` for every message, to attach fields the call sites don't know about
+    /// (current SSID, battery voltage...), replacing the previous annotator
+    ///
+    /// Every clone of this `BasicLogger` uses it. `annotator` must not log, which would call it
+    /// again.
+    pub fn set_annotator<A: Fn(&mut MessageFields) + Send + Sync + 'static>(&self, annotator: A) {
+        *self.annotator.lock() = Some(Arc::new(annotator));
+    }
+
+    /// calls `callback` with the error of every message that fails to be formatted or sent,
+    /// replacing the previous callback, e.g. to count the failures, blink an LED or reconnect
+    ///
+    /// Every clone of this `BasicLogger` uses it. Failures aren't reported in fire and forget
+    /// mode, see `Logger::set_fire_and_forget`. `callback` must not log, which could fail again.
+    pub fn set_on_error<E: FnMut(&Error) + Send + 'static>(&self, callback: E) {
+        *self.on_error.lock() = Some(Box::new(callback));
+    }
+
+    /// applies the `RUST_LOG`-style filter `spec`, e.g. `warn,wifi=debug`, over the current
+    /// levels of this logger and every clone of it
+    ///
+    /// Targets and the default level named by `spec` change, the others are left alone, and
+    /// directives that don't parse are skipped. `log::max_level` follows the most verbose
+    /// level, as with `set_target_level`. `init` and the `init_*` functions apply the spec of
+    /// the `ESP_SYSLOG_FILTER` environment variable at build time; on the ESP32 a spec can
+    /// also be kept per device in NVS, see `filter_from_nvs`.
+    pub fn set_filter(&self, spec: &str) {
+        let mut levels = self.levels.lock();
+        if !levels.has_default() {
+            levels.set_default(log::max_level());
+        }
+        levels.apply(spec);
+        log::set_max_level(levels.max());
+    }
+
+    /// caps the level of the messages sent by this logger and every clone of it, on top of
+    /// the target levels, leaving the other loggers of the `log` crate alone
+    ///
+    /// `log::max_level` is raised if needed so the more verbose messages reach this logger.
+    /// Lock-free, so it can be called from an interrupt handler or while logging.
+    pub fn set_level(&self, level: LevelFilter) {
+        self.level.store(level as usize, Ordering::Relaxed);
+        if level > log::max_level() {
+            log::set_max_level(level);
+        }
+    }
+
+    /// drops the messages less severe than `severity` at the syslog layer, for every clone of
+    /// this logger, see `Logger::set_min_severity`
+    pub fn set_min_severity(&self, severity: Severity) {
+        self.logger.lock().set_min_severity(severity);
+    }
+
+    /// sends `message` at `severity`, as formatted into the frame, past the levels of the `log`
+    /// crate, see `syslog_info!` and friends
+    ///
+    /// The scope of the logger applies, but its console output and the fields `Log::log` adds
+    /// don't.
+    pub fn send(&self, severity: Severity, message: Arguments) -> Result<()> {
+        let mut logger = match self.lock_logger() {
+            Some(logger) => logger,
+            None => return Ok(()),
+        };
+        if !accepting(&logger.backend) {
+            return Ok(());
+        }
+        match self.scope {
+            Some(ref scope) => {
+                let message = WithFields {
+                    message,
+                    fields: &[],
+                };
+                scope.send(&mut logger, severity, message)
+            }
+            None => logger.send(severity, message),
+        }
+    }
+
+    /// returns how many messages were sent and dropped so far, see `Logger::stats`, with the
+    /// reconnections and overflows of the backend
+    ///
+    /// The `log` crate has no way to report errors, so the messages a `BasicLogger` fails to
+    /// send are only ever counted here.
+    pub fn stats(&self) -> Stats {
+        let logger = self.logger.lock();
+        Stats {
+            reentrant: self.reentrant.load(Ordering::Relaxed),
+            ..logger.backend.with_counters(logger.stats())
+        }
+    }
+
+    /// locks the logger, waiting for other threads sending, unless the current thread or core
+    /// is the one sending: a message logged meanwhile by the backend is dropped and counted as
+    /// `Stats::reentrant` rather than deadlocking, or panicking with the `critical-section`
+    /// feature
+    fn lock_logger(&self) -> Option<LockGuard<'_, Logger<LoggerBackend, Formatter3164>>> {
+        let logger = self.logger.lock_unless_reentered();
+        if logger.is_none() {
+            self.reentrant.fetch_add(1, Ordering::Relaxed);
+        }
+        logger
+    }
+
+    /// sends what the backend still buffers or batches, see `Logger::flush`
+    ///
+    /// Unlike `log::logger().flush()`, reports whether it could.
+    pub fn flush(&self) -> Result<()> {
+        self.logger.lock().flush()
+    }
+
+    /// drops the messages of `target`, and of the targets nested below it, less severe than
+    /// `severity`, overriding the filter for them
+    ///
+    /// Messages are sent at the severity of their level, see `From<Level> for Severity`, so
+    /// `LOG_NOTICE` keeps `Info` and above, and `LOG_CRIT` and above drop everything.
+    pub fn set_target_severity(&self, target: &str, severity: Severity) {
+        let mut levels = self.levels.lock();
+        if !levels.has_default() {
+            levels.set_default(log::max_level());
+        }
+        levels.set(target, severity_filter(severity));
+        log::set_max_level(levels.max());
+    }
+
+    /// returns the level set by `set_level`, `LevelFilter::Trace` until then
+    pub fn level(&self) -> LevelFilter {
+        LEVEL_FILTERS[self.level.load(Ordering::Relaxed)]
+    }
+}
+
+#[allow(unused_variables, unused_must_use)]
+impl Log for BasicLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && metadata.level() <= log::STATIC_MAX_LEVEL
+            && metadata.level() <= self.level()
+            && metadata.level() <= self.levels.lock().level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Formatted straight into the frame, without a `String` per message
+        let message = *record.args();
+        let mut fields = MessageFields::new(record.level(), record.target());
+        fields.add_key_values(record.key_values());
+        if self.boot_id {
+            fields.add("boot", boot_id());
+        }
+        if self.wifi_fields {
+            wifi::add_fields(&mut fields, wifi_signal());
+        }
+        if let Some(ref scope) = self.scope {
+            for (name, value) in &scope.fields {
+                fields.add(name, value);
+            }
+        }
+        let annotator = self.annotator.lock().clone();
+        if let Some(annotator) = annotator {
+            annotator(&mut fields);
+        }
+        let fields = fields.pairs();
+        #[cfg(all(target_os = "espidf", feature = "capture-stdio"))]
+        stdio::passthrough(|| self.log_console(record));
+        #[cfg(not(all(target_os = "espidf", feature = "capture-stdio")))]
+        self.log_console(record);
+        let mut logger = match self.lock_logger() {
+            Some(logger) => logger,
+            None => return,
+        };
+        if accepting(&logger.backend) {
+            if let Some(ref check) = self.rebind_check {
+                if check.lock().due() {
+                    let _ = logger.backend.rebind();
+                }
+            }
+            let severity = Severity::from(record.level());
+            let message = WithFields {
+                message,
+                fields: &fields,
+            };
+            let sent = match self.scope {
+                Some(ref scope) => scope.send(&mut logger, severity, message),
+                None => logger.send(severity, message),
+            };
+            drop(logger);
+            if let Err(ref e) = sent {
+                // an error while the callback logs isn't reported to it again
+                if let Some(mut on_error) = self.on_error.lock_unless_reentered() {
+                    if let Some(ref mut callback) = *on_error {
+                        callback(e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let _ = BasicLogger::flush(self);
+    }
+}
+
+/// returns whether `backend` takes messages: until the network is available they are dropped,
+/// unless they are being captured in RAM by `init_early`, or spooled
+fn accepting(backend: &LoggerBackend) -> bool {
+    matches!(*backend, LoggerBackend::Memory(_) | LoggerBackend::Spool(_))
+        || STATE.load(std::sync::atomic::Ordering::Relaxed) == INITIALIZED
+}
+
+/// UDP Logger init function compatible with log crate
+///
+/// Returns a `LoggerControl` to change the installed logger at runtime.
+pub fn init_udp<T: ToSocketAddrs>(
+    local: T,
+    server: T,
+    facility: Facility,
+    log_level: log::LevelFilter,
+    process: String,
+    pid: Option<u32>,
+) -> Result<LoggerControl> {
+    let formatter = Formatter3164 {
+        facility,
+        process,
+        pid,
+        ..Default::default()
+    };
+    let logger = udp(formatter, local, server)?;
+    install(BasicLogger::new(logger), log_level)
+}
+
+/// UDP Logger init function compatible with log crate, which doesn't connect the socket
+///
+/// Messages are sent with `send_to`, see `udp_unconnected`, so an unreachable server only
+/// costs the messages sent meanwhile. Fails if `local` can't be bound or `server` doesn't
+/// resolve.
+pub fn init_udp_lazy<T: ToSocketAddrs>(
+    local: T,
+    server: T,
+    facility: Facility,
+    log_level: log::LevelFilter,
+    process: String,
+    pid: Option<u32>,
+) -> Result<LoggerControl> {
+    let formatter = Formatter3164 {
+        facility,
+        process,
+        pid,
+        ..Default::default()
+    };
+    let logger = udp_unconnected(formatter, local, server)?;
+    install(BasicLogger::new(logger), log_level)
+}
+
+/// TCP Logger init function compatible with log crate
+///
+/// Returns a `LoggerControl` to change the installed logger at runtime.
+pub fn init_tcp<T: ToSocketAddrs>(
+    server: T,
+    facility: Facility,
+    log_level: log::LevelFilter,
+    process: String,
+    pid: Option<u32>,
+) -> Result<LoggerControl> {
+    let formatter = Formatter3164 {
+        facility,
+        process,
+        pid,
+        ..Default::default()
+    };
 
-    log::set_max_level(log_level);
+    let logger = tcp(formatter, server)?;
+    install(BasicLogger::new(logger), log_level)
+}
+
+/// TCP Logger init function compatible with log crate, which connects on the first message
+///
+/// The connection is made by the first message rather than here, and retried by the following
+/// ones while the server can't be reached, see `ReconnectingTcpStream::lazy`. Fails if `server`
+/// doesn't resolve.
+pub fn init_tcp_lazy<T: ToSocketAddrs>(
+    server: T,
+    facility: Facility,
+    log_level: log::LevelFilter,
+    process: String,
+    pid: Option<u32>,
+) -> Result<LoggerControl> {
+    let formatter = Formatter3164 {
+        facility,
+        process,
+        pid,
+        ..Default::default()
+    };
+
+    let server = resolve_first(server)?;
+    let stream = ReconnectingTcpStream::lazy(server);
+    let logger = Logger::new(LoggerBackend::ReconnectingTcp(stream), formatter);
+    install(BasicLogger::new(logger), log_level)
+}
+
+/// Early-boot Logger init function compatible with log crate
+///
+/// Installs a logger that keeps up to `buffer_bytes` of formatted messages in RAM (dropping the
+/// oldest ones when full), so logging can start before the network or even Wi-Fi exist. Call
+/// `attach_network` once a backend is available.
+pub fn init_early(
+    facility: Facility,
+    log_level: log::LevelFilter,
+    process: String,
+    pid: Option<u32>,
+    buffer_bytes: usize,
+) -> Result<LoggerControl> {
+    let formatter = Formatter3164 {
+        facility,
+        process,
+        pid,
+        ..Default::default()
+    };
+
+    let frames = LimitedStorage::new(MemoryStorage::new(), buffer_bytes);
+    let logger = Logger::new(LoggerBackend::Memory(frames), formatter);
+    install(BasicLogger::new(logger), log_level)
+}
+
+/// switches the installed logger to `backend`, sending every message buffered since
+/// `init_early` first, in order, then marks the network as available
+///
+/// Messages logged meanwhile wait for the buffer to be drained. If sending a buffered message
+/// fails, the remaining ones are dropped and the error is returned; the logger still switches
+/// to `backend`.
+pub fn attach_network(backend: LoggerBackend) -> Result<()> {
+    let installed = installed().ok_or(Error::NotInstalled)?;
+    let mut logger = installed.logger.lock();
+    let previous = logger.replace_backend(backend);
+    set_network_available();
+
+    if let LoggerBackend::Memory(mut frames) = previous {
+        drain_frames(&mut frames, &mut logger.backend)?;
+    }
+    Ok(())
+}
+
+/// sends every frame of `frames` to `backend`, in order, dropping the remaining ones if
+/// sending fails
+fn drain_frames(
+    frames: &mut LimitedStorage<MemoryStorage>,
+    backend: &mut LoggerBackend,
+) -> Result<()> {
+    while let Some(frame) = frames.front()? {
+        backend.write_all(&frame).map_err(Error::from)?;
+        frames.pop_front()?;
+    }
+    backend.flush().map_err(Error::from)
+}
+
+/// installs `logger` as the global logger, at `log_level` and the `ESP_SYSLOG_FILTER` levels,
+/// as the `init_*` functions do with the loggers they build
+///
+/// The logger is kept in a static, rather than leaked in a `Box` as `log::set_boxed_logger`
+/// would, so nothing is allocated past the logger itself. Only one logger can be installed:
+/// change the installed one with the returned `LoggerControl`, or `reconfigure`.
+///
+/// ```no_run
+/// # extern crate log;
+/// # extern crate esp_syslog;
+/// # use esp_syslog::Syslog;
+/// # use log::LevelFilter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let logger = Syslog::builder().transport_udp("logs.lan:514").build_basic()?;
+/// let control = esp_syslog::install(logger, LevelFilter::Info)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn install(logger: BasicLogger, log_level: log::LevelFilter) -> Result<LoggerControl> {
+    {
+        let mut levels = logger.levels.lock();
+        levels.set_default(log_level);
+        if let Some(spec) = BUILD_FILTER {
+            levels.apply(spec);
+        }
+    }
+    register(logger.clone())?;
+    Ok(LoggerControl::new(logger))
+}
+
+/// sets `logger` as the global logger, with the levels it already has
+///
+/// Nothing is changed when the `log` crate already has a logger, whether installed by this
+/// crate or another one.
+fn register(logger: BasicLogger) -> Result<()> {
+    log::set_logger(&Installed).map_err(Error::initialization)?;
+    log::set_max_level(logger.levels.lock().max());
+    *INSTALLED.lock() = Some(logger);
     Ok(())
 }
 
+/// The logger registered with the `log` crate, which keeps a `&'static` to it: it forwards the
+/// records to the installed `BasicLogger`
+struct Installed;
+
+impl Log for Installed {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        installed().is_some_and(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        // a clone, so the lock isn't held while the record is sent
+        if let Some(logger) = installed() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = installed() {
+            Log::flush(&logger);
+        }
+    }
+}
+
+/// changes at runtime the level of `target` on the logger installed by one of the `init_*`
+/// functions, see `BasicLogger::set_target_level`
+///
+/// e.g. `set_target_level("wifi", LevelFilter::Debug)` to debug the Wi-Fi code alone.
+pub fn set_target_level(target: &str, level: LevelFilter) -> Result<()> {
+    installed()
+        .ok_or(Error::NotInstalled)?
+        .set_target_level(target, level)
+}
+
+/// applies at runtime the filter `spec` to the logger installed by one of the `init_*`
+/// functions, see `BasicLogger::set_filter`
+pub fn set_filter(spec: &str) -> Result<()> {
+    installed().ok_or(Error::NotInstalled)?.set_filter(spec);
+    Ok(())
+}
+
+/// caps at runtime the level of the logger installed by one of the `init_*` functions, see
+/// `BasicLogger::set_level`
+pub fn set_level(level: LevelFilter) -> Result<()> {
+    installed().ok_or(Error::NotInstalled)?.set_level(level);
+    Ok(())
+}
+
+/// drops at runtime the messages less severe than `severity` on the logger installed by one of
+/// the `init_*` functions, see `BasicLogger::set_min_severity`
+pub fn set_min_severity(severity: Severity) -> Result<()> {
+    installed()
+        .ok_or(Error::NotInstalled)?
+        .set_min_severity(severity);
+    Ok(())
+}
+
+/// sends `message` at `severity` through the logger installed by one of the `init_*`
+/// functions, see `BasicLogger::send`; the `syslog_*!` macros do without a logger argument
+pub fn send(severity: Severity, message: Arguments) -> Result<()> {
+    installed()
+        .ok_or(Error::NotInstalled)?
+        .send(severity, message)
+}
+
+/// returns a handle to the logger installed by one of the `init_*` functions
+fn installed() -> Option<BasicLogger> {
+    INSTALLED.lock().clone()
+}
+
 pub fn set_network_available() {
     STATE.store(INITIALIZED, std::sync::atomic::Ordering::Relaxed);
 }
 
+/// sets the HOSTNAME of the messages sent from now on
+///
+/// # Safety
+///
+/// None is needed any longer, the hostname being kept behind a lock: this stays `unsafe` for
+/// compatibility only, see `LoggerControl::set_hostname`.
 pub unsafe fn set_hostname(hostname: String) {
-    HOSTNAME = Some(hostname);
+    store_hostname(hostname);
+}
+
+pub(crate) fn store_hostname(hostname: String) {
+    *HOSTNAME.lock() = Some(hostname);
 }
 
 pub fn get_hostname() -> String {
-    unsafe {
-        match HOSTNAME.clone() {
-            None => "esp32-unknown".to_string(),
-            Some(s) => s.clone(),
+    with_hostname(str::to_string)
+}
+
+/// runs `f` with the HOSTNAME of the messages, without copying it
+///
+/// The lock is held meanwhile: `f` mustn't set the hostname.
+pub(crate) fn with_hostname<R, F: FnOnce(&str) -> R>(f: F) -> R {
+    match *HOSTNAME.lock() {
+        None => f("esp32-unknown"),
+        Some(ref s) => f(s),
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_tcp_failover_addresses() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    // the primary resolves to an address that is down, then to one that is up
+    let down = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let primary = [down, listener.local_addr().unwrap()];
+    let mut logger = tcp_failover(Formatter3164::default(), &[&primary[..]]).unwrap();
+    logger.info("on the second address").unwrap();
+    logger.flush().unwrap();
+    match logger.backend {
+        LoggerBackend::Failover(ref failover) => assert_eq!(failover.active(), 0),
+        _ => panic!("not a failover backend"),
+    }
+
+    let (mut server, _) = listener.accept().unwrap();
+    drop(logger);
+    let mut received = String::new();
+    server.read_to_string(&mut received).unwrap();
+    assert!(received.ends_with("main[0]: on the second address"));
+}
+
+#[test]
+fn test_flush_policy() {
+    struct Flushes(Vec<u8>, usize);
+
+    impl Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.1 += 1;
+            Ok(())
         }
     }
+
+    let mut logger = Logger::new(Flushes(Vec::new(), 0), Formatter3164::default());
+    logger.err("batched").unwrap();
+    assert_eq!(logger.backend.1, 0);
+
+    logger.flush_policy = FlushPolicy::AtOrAbove(Severity::LOG_ERR);
+    logger.info("still batched").unwrap();
+    logger.err("flushed").unwrap();
+    logger.emerg("flushed").unwrap();
+    assert_eq!(logger.backend.1, 2);
+}
+
+#[test]
+fn test_flush() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct Frames(Arc<Mutex<Vec<u8>>>);
+
+    impl Transport for Frames {
+        fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+            self.0.lock().unwrap().extend_from_slice(frame);
+            Ok(())
+        }
+    }
+
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let backend =
+        LoggerBackend::custom(Frames(frames.clone())).with_batching(1024, Duration::from_secs(60));
+    let mut logger = Logger::new(backend, Formatter3164::default());
+    logger.info("batched").unwrap();
+    assert!(frames.lock().unwrap().is_empty());
+    logger.flush().unwrap();
+    assert!(frames.lock().unwrap().ends_with(b"main[0]: batched\n"));
+
+    let logger = BasicLogger::new(logger);
+    logger.logger.lock().info("again").unwrap();
+    logger.flush().unwrap();
+    assert!(frames.lock().unwrap().ends_with(b"main[0]: again\n"));
+}
+
+#[test]
+fn test_into_parts() {
+    let mut logger = Logger::new(Vec::new(), Formatter3164::default());
+    logger.formatter_mut().process = "sensor".into();
+    logger.info("first").unwrap();
+    logger.backend_mut().clear();
+    logger.info("second").unwrap();
+
+    let (backend, formatter) = logger.into_parts();
+    assert!(backend.ends_with(b"sensor[0]: second"));
+    assert!(!backend.windows(5).any(|window| window == b"first"));
+    assert_eq!(formatter.process, "sensor");
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_tcp_lazy() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let server = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut logger = tcp_lazy(Formatter3164::default(), server, 1024).unwrap();
+    if let LoggerBackend::Spool(ref mut spool) = logger.backend {
+        if let LoggerBackend::ReconnectingTcp(ref mut stream) = spool.network {
+            stream.set_backoff(Backoff {
+                initial: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+        }
+    }
+    logger.info("before the network").unwrap();
+
+    let listener = TcpListener::bind(server).unwrap();
+    logger.info("after").unwrap();
+    drop(logger);
+    let (mut accepted, _) = listener.accept().unwrap();
+    let mut received = String::new();
+    accepted.read_to_string(&mut received).unwrap();
+    assert!(received.contains("main[0]: before the network"));
+    assert!(received.ends_with("main[0]: after"));
+}
+
+#[test]
+#[cfg(not(feature = "host-sim"))]
+fn test_confirm_wrapped() {
+    use std::net::TcpListener;
+
+    let wrappers: [fn(LoggerBackend) -> LoggerBackend; 3] = [
+        |backend| backend.with_fallback(io::sink()),
+        |backend| backend.with_batching(1024, Duration::from_secs(60)),
+        |backend| backend.with_spool(MemoryStorage::new()),
+    ];
+    for wrap in &wrappers {
+        // a collector that never reads, so the frame can't be handed over
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+        let frame = vec![b'x'; 16 << 20];
+        let socket = BufWriter::with_capacity(2 * frame.len(), stream);
+        let mut backend = wrap(LoggerBackend::Tcp(socket));
+        backend.write_all(&frame).unwrap();
+
+        // the wrapped TCP backend times out, instead of blocking until the collector reads
+        assert!(backend.confirm(Duration::from_millis(100)).is_err());
+        drop(accepted);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "host-sim"))]
+fn test_udp_broadcast_multicast() {
+    let logger =
+        udp_broadcast(Formatter3164::default(), "0.0.0.0:0", "255.255.255.255:514").unwrap();
+    match logger.backend {
+        LoggerBackend::Udp(ref socket, _) => assert!(socket.broadcast().unwrap()),
+        _ => panic!("not an unconnected UDP logger"),
+    }
+
+    let logger =
+        udp_multicast(Formatter3164::default(), "0.0.0.0:0", "239.255.0.1:514", 1).unwrap();
+    match logger.backend {
+        LoggerBackend::Udp(ref socket, group) => {
+            assert_eq!(socket.multicast_ttl_v4().unwrap(), 1);
+            assert_eq!(group, "239.255.0.1:514".parse().unwrap());
+        }
+        _ => panic!("not an unconnected UDP logger"),
+    }
+    assert!(udp_multicast(Formatter3164::default(), "0.0.0.0:0", "192.0.2.1:514", 1).is_err());
+}
+
+#[test]
+#[cfg(not(feature = "host-sim"))]
+fn test_udp_connected() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap().to_string();
+    let mut logger = udp(Formatter3164::default(), "127.0.0.1:0".into(), server_addr).unwrap();
+    assert!(matches!(logger.backend, LoggerBackend::UdpConnected(_)));
+
+    logger.info("connected").unwrap();
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: connected"));
+}
+
+#[test]
+fn test_early_capture_handoff() {
+    init_early(
+        Facility::LOG_USER,
+        log::LevelFilter::Info,
+        "early".into(),
+        Some(0),
+        1024,
+    )
+    .unwrap();
+    log::info!("before network");
+    log::debug!("filtered out");
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    attach_network(LoggerBackend::Udp(socket, server.local_addr().unwrap())).unwrap();
+    log::warn!("live");
+
+    let mut buf = [0; 256];
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"early[0]: before network"));
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"early[0]: live"));
+
+    set_target_level("wifi", log::LevelFilter::Debug).unwrap();
+    log::debug!("still filtered out");
+    log::debug!(target: "wifi::scan", "verbose");
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"early[0]: verbose"));
+
+    // The `log` crate has a logger already, so a second one isn't installed
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let second = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ));
+    assert!(install(second.clone(), log::LevelFilter::Info).is_err());
+    assert!(!Arc::ptr_eq(&installed().unwrap().logger, &second.logger));
+    log::warn!("still installed");
+    let len = server.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"early[0]: still installed"));
+}
+
+#[test]
+fn test_set_level() {
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let logger = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ));
+    log::set_max_level(LevelFilter::Trace);
+    logger.clone().set_level(LevelFilter::Warn);
+    assert_eq!(logger.level(), LevelFilter::Warn);
+
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("quiet"))
+            .build(),
+    );
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .args(format_args!("loud"))
+            .build(),
+    );
+
+    let mut logger = logger.logger.lock();
+    let frames = match logger.backend {
+        LoggerBackend::Memory(ref mut frames) => frames,
+        _ => unreachable!(),
+    };
+    assert!(frames.front().unwrap().unwrap().ends_with(b"main[0]: loud"));
+    frames.pop_front().unwrap();
+    assert!(frames.front().unwrap().is_none());
+}
+
+#[test]
+fn test_key_values() {
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let logger = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ));
+    log::set_max_level(LevelFilter::Trace);
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("reading"))
+            .key_values(&[("temp", 23.5), ("humidity", 41.0)])
+            .build(),
+    );
+
+    let frame = match logger.logger.lock().backend {
+        LoggerBackend::Memory(ref mut frames) => frames.front().unwrap().unwrap(),
+        _ => unreachable!(),
+    };
+    assert!(frame.ends_with(b"main[0]: reading temp=23.5 humidity=41"));
+}
+
+#[test]
+fn test_min_severity() {
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let mut logger = Logger::new(LoggerBackend::Memory(frames), Formatter3164::default());
+    logger.set_min_severity(Severity::LOG_WARNING);
+    logger.info("chatty").unwrap();
+    logger.err("failed").unwrap();
+
+    let logger = BasicLogger::new(logger);
+    log::set_max_level(LevelFilter::Trace);
+    logger.set_min_severity(Severity::LOG_DEBUG);
+    logger.set_target_severity("wifi", Severity::LOG_NOTICE);
+    for &(target, level) in &[("wifi::scan", Level::Debug), ("app", Level::Debug)] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("from {}", target))
+                .build(),
+        );
+    }
+
+    let mut logger = logger.logger.lock();
+    let frames = match logger.backend {
+        LoggerBackend::Memory(ref mut frames) => frames,
+        _ => unreachable!(),
+    };
+    let frame = frames.front().unwrap().unwrap();
+    assert!(frame.ends_with(b"main[0]: failed"));
+    frames.pop_front().unwrap();
+    let frame = frames.front().unwrap().unwrap();
+    assert!(frame.ends_with(b"main[0]: from app"));
+    frames.pop_front().unwrap();
+    assert!(frames.front().unwrap().is_none());
+}
+
+#[cfg(all(unix, not(target_os = "espidf")))]
+#[test]
+fn test_unix_sockets() {
+    use std::io::Read;
+    use std::os::unix::net::UnixListener;
+
+    let dir = std::env::temp_dir();
+    let datagram_path = dir.join(format!("esp_syslog-{}-dgram.sock", std::process::id()));
+    let stream_path = dir.join(format!("esp_syslog-{}-stream.sock", std::process::id()));
+    let _ = std::fs::remove_file(&datagram_path);
+    let _ = std::fs::remove_file(&stream_path);
+
+    let datagram = UnixDatagram::bind(&datagram_path).unwrap();
+    let mut logger = unix_custom(Formatter3164::default(), &datagram_path).unwrap();
+    assert!(matches!(logger.backend, LoggerBackend::Unix(_)));
+    logger.info("datagram").unwrap();
+    let mut buf = [0; 256];
+    let len = datagram.recv(&mut buf).unwrap();
+    assert!(buf[..len].ends_with(b"main[0]: datagram"));
+
+    let listener = UnixListener::bind(&stream_path).unwrap();
+    let mut logger = unix_custom(Formatter3164::default(), &stream_path).unwrap();
+    assert!(matches!(logger.backend, LoggerBackend::UnixStream(_)));
+    logger.info("stream").unwrap();
+    drop(logger);
+    let mut received = Vec::new();
+    listener
+        .accept()
+        .unwrap()
+        .0
+        .read_to_end(&mut received)
+        .unwrap();
+    assert!(received.ends_with(b"main[0]: stream\0"));
+
+    let _ = std::fs::remove_file(&datagram_path);
+    let _ = std::fs::remove_file(&stream_path);
+}
+
+#[test]
+fn test_fire_and_forget() {
+    struct Down;
+
+    impl Write for Down {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut logger = Logger::new(Down, Formatter3164::default());
+    assert!(logger.info("lost").is_err());
+    logger.set_fire_and_forget(true);
+    logger.info("lost too").unwrap();
+    assert_eq!(
+        logger.stats(),
+        Stats {
+            sent: 0,
+            bytes: 0,
+            dropped: 2,
+            ..Stats::default()
+        }
+    );
+
+    let mut logger = Logger::new(Vec::new(), Formatter3164::default());
+    logger.set_min_severity(Severity::LOG_INFO);
+    logger.info("sent").unwrap();
+    logger.debug("filtered").unwrap();
+    assert_eq!(
+        logger.stats(),
+        Stats {
+            sent: 1,
+            bytes: logger.backend.len(),
+            dropped: 0,
+            ..Stats::default()
+        }
+    );
+}
+
+#[test]
+fn test_with_filter() {
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let logger = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ))
+    .with_filter("wifi=debug,mqtt=warn,info");
+
+    let levels = logger.levels.lock();
+    assert_eq!(levels.level("wifi::scan"), LevelFilter::Debug);
+    assert_eq!(levels.level("mqtt"), LevelFilter::Warn);
+    assert_eq!(levels.level("app"), LevelFilter::Info);
+}
+
+#[test]
+fn test_on_error() {
+    /// a full flash partition
+    struct Full;
+
+    impl SpoolStorage for Full {
+        fn push(&mut self, _: &[u8]) -> io::Result<()> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+
+        fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+
+        fn pop_front(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn len(&self) -> usize {
+            0
+        }
+
+        fn size(&self) -> usize {
+            0
+        }
+    }
+
+    struct Down;
+
+    impl Transport for Down {
+        fn send(&mut self, _: &[u8]) -> io::Result<()> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+    }
+
+    let backend = LoggerBackend::custom(Down).with_spool(Full);
+    let logger = BasicLogger::new(Logger::new(backend, Formatter3164::default()));
+    let failures = Arc::new(AtomicUsize::new(0));
+    let counted = failures.clone();
+    logger.set_on_error(move |_| {
+        counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    });
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .args(format_args!("lost"))
+            .build(),
+    );
+    assert_eq!(failures.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_reentrant_logging() {
+    /// logs through the logger it is the backend of
+    struct Chatty(Arc<std::sync::OnceLock<BasicLogger>>);
+
+    impl Transport for Chatty {
+        fn send(&mut self, _: &[u8]) -> io::Result<()> {
+            if let Some(logger) = self.0.get() {
+                logger.log(&Record::builder().args(format_args!("sent")).build());
+                logger
+                    .send(Severity::LOG_DEBUG, format_args!("sent"))
+                    .unwrap();
+            }
+            Ok(())
+        }
+    }
+
+    let shared = Arc::new(std::sync::OnceLock::new());
+    let backend = LoggerBackend::custom(Chatty(shared.clone()));
+    let logger = BasicLogger::new(Logger::new(backend, Formatter3164::default()));
+    set_network_available();
+    assert!(shared.set(logger.clone()).is_ok());
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .args(format_args!("outer"))
+            .build(),
+    );
+    let stats = logger.stats();
+    assert_eq!((stats.sent, stats.reentrant), (1, 2));
+}
+
+#[test]
+fn test_concurrent_logging() {
+    /// takes its time sending, so the other thread logs meanwhile
+    struct Slow(Arc<Lock<Vec<Vec<u8>>>>);
+
+    impl Transport for Slow {
+        fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+            std::thread::sleep(Duration::from_millis(1));
+            self.0.lock().push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    let sent = Arc::new(Lock::new(Vec::new()));
+    let backend = LoggerBackend::custom(Slow(sent.clone()));
+    let logger = BasicLogger::new(Logger::new(backend, Formatter3164::default()));
+    set_network_available();
+    let threads: Vec<_> = (0..2)
+        .map(|task| {
+            let logger = logger.clone();
+            std::thread::spawn(move || {
+                for i in 0..20 {
+                    logger.log(
+                        &Record::builder()
+                            .level(Level::Error)
+                            .args(format_args!("task {task} message {i}"))
+                            .build(),
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let stats = logger.stats();
+    assert_eq!((stats.sent, stats.reentrant), (40, 0));
+    assert_eq!(sent.lock().len(), 40);
 }
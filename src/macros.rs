@@ -0,0 +1,110 @@
+//! `format!`-style macros sending at a severity, without going through the `log` crate
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __syslog {
+    ($method:ident, $severity:ident, logger: $logger:expr, $($arg:tt)+) => {
+        $logger.$method(format_args!($($arg)+))
+    };
+    ($method:ident, $severity:ident, $($arg:tt)+) => {
+        $crate::send($crate::Severity::$severity, format_args!($($arg)+))
+    };
+}
+
+/// Sends at `LOG_EMERG`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_emerg {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(emerg, LOG_EMERG, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_ALERT`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_alert {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(alert, LOG_ALERT, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_CRIT`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_crit {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(crit, LOG_CRIT, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_ERR`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_err {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(err, LOG_ERR, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_WARNING`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_warning {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(warning, LOG_WARNING, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_NOTICE`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_notice {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(notice, LOG_NOTICE, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_INFO`
+///
+/// The arguments are formatted lazily, straight into the frame, so no `String` is built for
+/// the message. Without a logger, they are sent through the installed one, see `send`; with a
+/// `logger:` argument, through any logger with the severity methods, e.g. a `Logger` or a
+/// `ProcessLogger`. Returns the `Result` of the send.
+///
+/// ```no_run
+/// # #[macro_use] extern crate esp_syslog;
+/// # use esp_syslog::Formatter3164;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut logger = esp_syslog::udp(Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514")?;
+/// # let (ssid, elapsed, retries, slot) = ("lan", 1200, 3, 1);
+/// syslog_info!("connected to {} in {} ms", ssid, elapsed)?;
+/// syslog_err!(logger: logger, "modem timeout after {} retries", retries)?;
+/// syslog_warning!(logger: logger.with_process("ota"), "slot {} invalid", slot)?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! syslog_info {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(info, LOG_INFO, $($arg)+)
+    };
+}
+
+/// Sends at `LOG_DEBUG`, see `syslog_info!`
+#[macro_export]
+macro_rules! syslog_debug {
+    ($($arg:tt)+) => {
+        $crate::__syslog!(debug, LOG_DEBUG, $($arg)+)
+    };
+}
+
+#[test]
+fn test_macros() {
+    use {Formatter3164, Logger};
+
+    let mut logger = Logger::new(Vec::new(), Formatter3164::default());
+    let retries = 3;
+    syslog_err!(logger: logger, "modem timeout after {} retries", retries).unwrap();
+    syslog_info!(logger: logger.with_process("ota"), "slot {}", "ota_1").unwrap();
+    let sent = String::from_utf8(logger.backend).unwrap();
+    assert!(sent.starts_with("<11>"));
+    assert!(sent.contains("main[0]: modem timeout after 3 retries<14>"));
+    assert!(sent.ends_with("ota[0]: slot ota_1"));
+    // through the installed logger, only built: the one another test installs would send it
+    let _ = || syslog_notice!("uptime {} s", 60);
+}
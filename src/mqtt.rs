@@ -0,0 +1,111 @@
+//! MQTT backend, enabled by the `mqtt` feature
+//!
+//! Publishes every frame through the application's MQTT client, so the device keeps a single
+//! broker connection. The crate doesn't depend on a client: the backend takes a callback
+//! publishing a payload to a topic, e.g. with `esp-idf-svc`'s `EspMqttClient` or `rumqttc`.
+use std::fmt;
+use std::io;
+
+use format::Severity;
+use spool::priority;
+use transport::Transport;
+
+type Publish = Box<dyn FnMut(&str, &[u8]) -> io::Result<()> + Send>;
+
+/// A transport publishing every frame, as formatted, as one MQTT message
+///
+/// With `with_severity_subtopic`, messages go to `<topic>/<severity>` (`syslog/err`,
+/// `syslog/info`...), for the subscribers to filter on severity:
+///
+/// ```ignore
+/// let mut client = EspMqttClient::new_cb("mqtt://broker:1883", &conf, |_| {})?;
+/// let backend = MqttBackend::new("devices/esp32/syslog", move |topic, payload| {
+///     client
+///         .enqueue(topic, QoS::AtMostOnce, false, payload)
+///         .map(|_| ())
+///         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+/// })
+/// .with_severity_subtopic();
+/// let logger = Logger::new(LoggerBackend::custom(backend), Formatter5424::default());
+/// ```
+pub struct MqttBackend {
+    topic: String,
+    severity_subtopic: bool,
+    publish: Publish,
+}
+
+impl MqttBackend {
+    /// publishes to `topic` with `publish`, called with the topic and the frame
+    pub fn new<P>(topic: &str, publish: P) -> MqttBackend
+    where
+        P: FnMut(&str, &[u8]) -> io::Result<()> + Send + 'static,
+    {
+        MqttBackend {
+            topic: topic.trim_end_matches('/').to_string(),
+            severity_subtopic: false,
+            publish: Box::new(publish),
+        }
+    }
+
+    /// publishes to a subtopic named after the severity of the message
+    pub fn with_severity_subtopic(mut self) -> MqttBackend {
+        self.severity_subtopic = true;
+        self
+    }
+
+    /// returns the topic of `frame`
+    fn topic(&self, frame: &[u8]) -> String {
+        match priority(frame) {
+            Some(priority) if self.severity_subtopic => {
+                format!("{}/{}", self.topic, Severity::of(priority).name())
+            }
+            _ => self.topic.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for MqttBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MqttBackend")
+            .field("topic", &self.topic)
+            .field("severity_subtopic", &self.severity_subtopic)
+            .finish()
+    }
+}
+
+impl Transport for MqttBackend {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let topic = self.topic(frame);
+        (self.publish)(&topic, frame)
+    }
+}
+
+#[test]
+fn test_mqtt() {
+    use std::sync::{Arc, Mutex};
+    use {Formatter3164, Logger, LoggerBackend};
+
+    let published = Arc::new(Mutex::new(Vec::new()));
+    let messages = published.clone();
+    let backend = MqttBackend::new("devices/esp32/syslog/", move |topic, payload| {
+        messages
+            .lock()
+            .unwrap()
+            .push((topic.to_string(), payload.to_vec()));
+        Ok(())
+    });
+    let mut logger = Logger::new(LoggerBackend::custom(backend), Formatter3164::default());
+    logger.info("one").unwrap();
+
+    let backend = MqttBackend::new("syslog", |_, _| Ok(())).with_severity_subtopic();
+    assert_eq!(
+        backend.topic(b"<11>Jan  1 00:00:00 esp32 main: two"),
+        "syslog/err"
+    );
+    assert_eq!(backend.topic(b"two"), "syslog");
+
+    let published = published.lock().unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].0, "devices/esp32/syslog");
+    assert!(published[0].1.ends_with(b"main[0]: one"));
+}
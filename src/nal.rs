@@ -0,0 +1,182 @@
+//! Backends over `embedded-nal` network stacks, enabled by the `embedded-nal` feature
+//!
+//! On bare-metal esp-hal builds there is no esp-idf, hence no `std::net`: the network is a
+//! smoltcp-based stack, such as `esp-wifi`'s, implementing the `embedded-nal` client traits.
+//! `NalUdp` and `NalTcp` send frames through any such stack, and being `io::Write` they are
+//! used as the writer of a `Logger` like the `std` sockets.
+//!
+//! These are the transports of the `esp_syslog_core` crate, which only needs `core` and
+//! `alloc`, behind `io::Write`. The rest of this crate still depends on `std` (`std::io`,
+//! `String`), so they need a `std` port of the target; without one, use `esp_syslog_core`
+//! itself.
+//!
+//! ```no_run
+//! # extern crate embedded_nal;
+//! # extern crate esp_syslog;
+//! # use esp_syslog::Formatter3164;
+//! # fn run<S: embedded_nal::UdpClientStack>(stack: S) -> Result<(), Box<dyn std::error::Error>> {
+//! let backend = esp_syslog::NalUdp::connect(stack, "192.168.1.10:514".parse()?)?;
+//! let mut logger = esp_syslog::Logger::new(backend, Formatter3164::default());
+//! # Ok(())
+//! # }
+//! # fn main() {}
+//! ```
+use std::fmt::Debug;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+
+use embedded_nal::{TcpClientStack, UdpClientStack};
+use esp_syslog_core::{Framing, NalError, NalTcp as CoreTcp, NalUdp as CoreUdp, Transport};
+
+/// A UDP socket of an `embedded-nal` stack, connected to its server
+pub struct NalUdp<S: UdpClientStack>(CoreUdp<S>);
+
+impl<S: UdpClientStack> NalUdp<S> {
+    /// opens a socket of `stack`, connected to `server`
+    pub fn connect(stack: S, server: SocketAddr) -> io::Result<NalUdp<S>> {
+        CoreUdp::connect(stack, server)
+            .map(NalUdp)
+            .map_err(nal_error)
+    }
+
+    /// closes the socket, and gives the stack back
+    pub fn close(self) -> io::Result<S> {
+        self.0.close().map_err(nal_error)
+    }
+}
+
+impl<S: UdpClientStack> Write for NalUdp<S> {
+    /// Sends `frame` as one datagram, blocking while the stack is busy
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.0.send(frame).map_err(nal_error)?;
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A TCP connection of an `embedded-nal` stack, every frame being delimited with a framing
+pub struct NalTcp<S: TcpClientStack>(CoreTcp<S>);
+
+impl<S: TcpClientStack> NalTcp<S> {
+    /// opens a socket of `stack`, and blocks until it is connected to `server`
+    pub fn connect(stack: S, server: SocketAddr, framing: Framing) -> io::Result<NalTcp<S>> {
+        CoreTcp::connect(stack, server, framing)
+            .map(NalTcp)
+            .map_err(nal_error)
+    }
+
+    /// closes the connection, and gives the stack back
+    pub fn close(self) -> io::Result<S> {
+        self.0.close().map_err(nal_error)
+    }
+}
+
+impl<S: TcpClientStack> Write for NalTcp<S> {
+    /// Sends `frame` as one syslog message, blocking until the stack took all of it
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.0.send(frame).map_err(nal_error)?;
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `embedded-nal` errors only implement `Debug`
+fn nal_error<E: Debug>(e: NalError<E>) -> io::Error {
+    match e {
+        NalError::Stack(e) => io::Error::other(format!("network stack error: {e:?}")),
+        NalError::WriteZero => io::ErrorKind::WriteZero.into(),
+    }
+}
+
+#[test]
+fn test_nal_backends() {
+    use embedded_nal::{nb, TcpError, TcpErrorKind};
+
+    #[derive(Debug)]
+    struct Closed;
+
+    impl TcpError for Closed {
+        fn kind(&self) -> TcpErrorKind {
+            TcpErrorKind::PipeClosed
+        }
+    }
+
+    /// sends 4 bytes at a time, being busy every other call
+    #[derive(Default)]
+    struct MockStack {
+        sent: Vec<Vec<u8>>,
+        busy: bool,
+    }
+
+    impl UdpClientStack for MockStack {
+        type UdpSocket = ();
+        type Error = Closed;
+
+        fn socket(&mut self) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn connect(&mut self, _: &mut (), _: SocketAddr) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<(), Closed> {
+            self.sent.push(buffer.to_vec());
+            Ok(())
+        }
+
+        fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<(usize, SocketAddr), Closed> {
+            Err(nb::Error::WouldBlock)
+        }
+
+        fn close(&mut self, _: ()) -> Result<(), Closed> {
+            Ok(())
+        }
+    }
+
+    impl TcpClientStack for MockStack {
+        type TcpSocket = ();
+        type Error = Closed;
+
+        fn socket(&mut self) -> Result<(), Closed> {
+            Ok(())
+        }
+
+        fn connect(&mut self, _: &mut (), _: SocketAddr) -> nb::Result<(), Closed> {
+            Ok(())
+        }
+
+        fn send(&mut self, _: &mut (), buffer: &[u8]) -> nb::Result<usize, Closed> {
+            self.busy = !self.busy;
+            if self.busy {
+                return Err(nb::Error::WouldBlock);
+            }
+            let len = buffer.len().min(4);
+            self.sent.push(buffer[..len].to_vec());
+            Ok(len)
+        }
+
+        fn receive(&mut self, _: &mut (), _: &mut [u8]) -> nb::Result<usize, Closed> {
+            Err(nb::Error::WouldBlock)
+        }
+
+        fn close(&mut self, _: ()) -> Result<(), Closed> {
+            Ok(())
+        }
+    }
+
+    let server = "192.168.1.10:514".parse().unwrap();
+    let mut udp = NalUdp::connect(MockStack::default(), server).unwrap();
+    udp.write_all(b"<14>datagram").unwrap();
+    assert_eq!(udp.close().unwrap().sent, vec![b"<14>datagram".to_vec()]);
+
+    let mut tcp = NalTcp::connect(MockStack::default(), server, Framing::OctetCounted).unwrap();
+    tcp.write_all(b"<14>stream").unwrap();
+    assert_eq!(tcp.close().unwrap().sent.concat(), b"10 <14>stream");
+}
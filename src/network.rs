@@ -0,0 +1,90 @@
+//! Notification of the logger when the network comes up
+//!
+//! A reconnecting backend only retries when a message is logged, once its backoff allows it, so
+//! the messages spooled while the Wi-Fi was down can wait for minutes after it is back. The
+//! firmware knows better: calling `network_available` from the handler of the ESP-IDF
+//! `IP_EVENT_STA_GOT_IP` event connects the backend and sends them right away.
+use std::io;
+
+use errors::*;
+use {installed, set_network_available, BasicLogger, LoggerBackend};
+
+impl LoggerBackend {
+    /// connects the backend now that the network is up, and sends the frames spooled while it
+    /// was down, returning how many were sent
+    ///
+    /// The sockets are rebound first, see `rebind`, as the new address may not be the last
+    /// one. A reconnecting TCP backend connects without waiting for its backoff, and a UDP one
+    /// following a name resolves it again.
+    pub fn network_available(&mut self) -> io::Result<usize> {
+        self.rebind()?;
+        self.connect_now()
+    }
+
+    fn connect_now(&mut self) -> io::Result<usize> {
+        match *self {
+            LoggerBackend::ReconnectingTcp(ref mut stream) => stream.connect_now().map(|()| 0),
+            LoggerBackend::ResolvingUdp(ref mut socket) => socket.resolve().map(|_| 0),
+            LoggerBackend::Spool(ref mut spool) => {
+                spool.network.connect_now()?;
+                spool.replay()
+            }
+            LoggerBackend::Fallback(ref mut fallback) => fallback.primary.connect_now(),
+            LoggerBackend::Batching(ref mut batching) => batching.inner.connect_now(),
+            LoggerBackend::Failover(ref mut failover) => failover.active_mut().connect_now(),
+            _ => Ok(0),
+        }
+    }
+}
+
+impl BasicLogger {
+    /// marks the network as available, see `set_network_available`, and has the backend
+    /// connect and send what it spooled meanwhile, see `LoggerBackend::network_available`
+    pub fn network_available(&self) -> Result<usize> {
+        set_network_available();
+        self.logger
+            .lock()
+            .backend
+            .network_available()
+            .map_err(Error::from)
+    }
+}
+
+/// notifies the logger installed by `init` or one of the `init_*` functions that the network
+/// is up, see `BasicLogger::network_available`
+pub fn network_available() -> Result<usize> {
+    installed().ok_or(Error::NotInstalled)?.network_available()
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_network_available() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use {Backoff, Formatter3164};
+
+    let server = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut logger = ::tcp_lazy(Formatter3164::default(), server, 1024).unwrap();
+    if let LoggerBackend::Spool(ref mut spool) = logger.backend {
+        if let LoggerBackend::ReconnectingTcp(ref mut stream) = spool.network {
+            stream.set_backoff(Backoff {
+                initial: Duration::from_secs(60),
+                max: Duration::from_secs(60),
+            });
+        }
+    }
+    logger.info("while offline").unwrap();
+
+    let listener = TcpListener::bind(server).unwrap();
+    let logger = BasicLogger::new(logger);
+    assert_eq!(logger.network_available().unwrap(), 1);
+    let (mut accepted, _) = listener.accept().unwrap();
+    drop(logger);
+    let mut received = String::new();
+    accepted.read_to_string(&mut received).unwrap();
+    assert!(received.ends_with("main[0]: while offline"));
+}
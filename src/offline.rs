@@ -0,0 +1,144 @@
+//! Spooling of the messages the network backend can't send, replayed once it can
+use std::io::{self, Write};
+
+use spool::SpoolStorage;
+use LoggerBackend;
+
+/// A backend storing in `storage` the frames `network` fails to send, and sending them, oldest
+/// first, once it can again
+///
+/// The storage is replayed before every frame is sent, so frames never overtake the spooled
+/// ones: while the network is down, every write spools its frame after a failed attempt to
+/// replay the oldest one. With a `ReconnectingTcpStream` that attempt fails right away until
+/// the next reconnection is due. Call `replay` to send the spooled frames without waiting for
+/// the next message, e.g. once the Wi-Fi reconnects.
+///
+/// A write only fails if the frame can neither be sent nor spooled. With `FileStorage` on a
+/// SPIFFS or LittleFS partition the frames survive a reboot, or a deep sleep:
+///
+/// ```no_run
+/// # use esp_syslog::{FileStorage, Formatter3164, LimitedStorage};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (formatter, server) = (Formatter3164::default(), "192.168.1.10:601");
+/// let storage = LimitedStorage::new(FileStorage::open("/spiffs/syslog")?, 64 * 1024);
+/// let backend = esp_syslog::tcp(formatter, server)?.backend.with_spool(storage);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SpoolBackend<W, S> {
+    pub network: W,
+    pub storage: S,
+    spooled: usize,
+    replayed: usize,
+}
+
+impl<W: Write, S: SpoolStorage> SpoolBackend<W, S> {
+    pub fn new(network: W, storage: S) -> Self {
+        SpoolBackend {
+            network,
+            storage,
+            spooled: 0,
+            replayed: 0,
+        }
+    }
+
+    /// sends the spooled frames in order, stopping at the first one the network fails to send,
+    /// and returns how many were sent
+    pub fn replay(&mut self) -> io::Result<usize> {
+        let mut sent = 0;
+        while let Some(frame) = self.storage.front()? {
+            self.network.write_all(&frame)?;
+            self.storage.pop_front()?;
+            sent += 1;
+            self.replayed += 1;
+        }
+        if sent > 0 {
+            self.network.flush()?;
+        }
+        Ok(sent)
+    }
+
+    /// returns the number of frames stored so far, replayed or not
+    pub fn spooled(&self) -> usize {
+        self.spooled
+    }
+
+    /// returns the number of spooled frames sent so far
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.replay()?;
+        self.network.write_all(frame)
+    }
+}
+
+impl<W: Write, S: SpoolStorage> Write for SpoolBackend<W, S> {
+    /// Sends `frame` as one syslog message, after the spooled ones, or spools it
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if let Err(e) = self.send(frame) {
+            self.storage.push(frame).map_err(|_| e)?;
+            self.spooled += 1;
+        }
+        Ok(frame.len())
+    }
+
+    /// Replays the spooled frames, if the network is back, then flushes the network backend
+    fn flush(&mut self) -> io::Result<()> {
+        self.replay()?;
+        self.network.flush()
+    }
+}
+
+impl LoggerBackend {
+    /// wraps the backend into a `SpoolBackend` storing in `storage` what it fails to send
+    pub fn with_spool<S: SpoolStorage + Send + 'static>(self, storage: S) -> LoggerBackend {
+        LoggerBackend::Spool(Box::new(SpoolBackend::new(self, Box::new(storage))))
+    }
+}
+
+#[test]
+fn test_spool_backend() {
+    use MemoryStorage;
+
+    /// a network backend going up and down
+    struct Link {
+        up: bool,
+        sent: Vec<Vec<u8>>,
+    }
+
+    impl Write for Link {
+        fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+            if !self.up {
+                return Err(io::ErrorKind::NotConnected.into());
+            }
+            self.sent.push(frame.to_vec());
+            Ok(frame.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let link = Link {
+        up: false,
+        sent: Vec::new(),
+    };
+    let mut backend = SpoolBackend::new(link, MemoryStorage::new());
+    backend.write_all(b"<14>one").unwrap();
+    backend.write_all(b"<14>two").unwrap();
+    assert_eq!(backend.spooled(), 2);
+    assert!(backend.replay().is_err());
+
+    backend.network.up = true;
+    backend.write_all(b"<14>three").unwrap();
+    assert_eq!(
+        backend.network.sent,
+        [&b"<14>one"[..], b"<14>two", b"<14>three"]
+    );
+    assert!(backend.storage.is_empty());
+    assert_eq!(backend.replayed(), 2);
+    assert_eq!(backend.replay().unwrap(), 0);
+}
@@ -0,0 +1,238 @@
+//! OpenTelemetry logs exporter, enabled by the `otlp` feature
+//!
+//! Frames written by the formatters are parsed back and mapped to OTLP LogRecords: the
+//! severity to `severityNumber`, the RFC 5424 timestamp to `timeUnixNano`, the message to the
+//! body, and the header fields and SD-PARAMs to attributes (`syslog.appname`,
+//! `exampleSDID@32473.iut`...). Records are batched and sent to an OTel collector with
+//! OTLP/HTTP in its JSON encoding. Frames `validate` rejects, e.g. those of a custom formatter,
+//! are exported as the body of a record with no severity or attributes.
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+#[cfg(feature = "tls")]
+use rustls::ClientConfig;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use http::{BatchQueue, HttpEndpoint};
+use json::JsonStr;
+use validate::{validate, ParsedMessage};
+
+/// number of records sent in one request by default
+const DEFAULT_BATCH_SIZE: usize = 32;
+/// how many batches are kept while the collector is unreachable, the oldest records being
+/// dropped beyond that
+const MAX_PENDING_BATCHES: usize = 4;
+
+/// OTel severity number and text of each syslog severity
+const SEVERITIES: [(u8, &str); 8] = [
+    (21, "FATAL"),
+    (19, "ERROR3"),
+    (18, "ERROR2"),
+    (17, "ERROR"),
+    (13, "WARN"),
+    (10, "INFO2"),
+    (9, "INFO"),
+    (5, "DEBUG"),
+];
+
+/// A backend exporting every frame as an OTLP LogRecord
+pub struct OtlpExporter {
+    endpoint: HttpEndpoint,
+    service_name: String,
+    records: BatchQueue<String>,
+}
+
+impl OtlpExporter {
+    /// exports to the collector at `url`, e.g. `http://collector:4318`, as service
+    /// `service_name`
+    ///
+    /// The standard `/v1/logs` path is used unless `url` has one.
+    pub fn new(url: &str, service_name: &str) -> io::Result<OtlpExporter> {
+        Ok(OtlpExporter {
+            endpoint: HttpEndpoint::parse(url, "/v1/logs")?,
+            service_name: service_name.to_string(),
+            records: BatchQueue::new(DEFAULT_BATCH_SIZE, MAX_PENDING_BATCHES),
+        })
+    }
+
+    /// adds a header sent with every request, e.g. an `Authorization` token
+    pub fn with_header(mut self, name: &str, value: &str) -> OtlpExporter {
+        self.endpoint.add_header(name, value);
+        self
+    }
+
+    /// sets the TLS configuration used for `https://` URLs, with the root certificates
+    /// trusted for the collector
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>) -> OtlpExporter {
+        self.endpoint.set_tls_config(config);
+        self
+    }
+
+    /// sets how many records are batched in one request (32 by default)
+    ///
+    /// Records are also sent when the backend is flushed.
+    pub fn with_batch_size(mut self, batch_size: usize) -> OtlpExporter {
+        self.records.set_batch_size(batch_size);
+        self
+    }
+
+    fn request(&self) -> String {
+        let mut body = String::new();
+        let _ = write!(
+            body,
+            "{{\"resourceLogs\":[{{\"resource\":{{\"attributes\":[{}]}},\"scopeLogs\":[{{\"scope\":{{\"name\":\"esp_syslog\"}},\"logRecords\":[",
+            Attribute("service.name", &self.service_name)
+        );
+        for (i, record) in self.records.entries.iter().enumerate() {
+            if i > 0 {
+                body.push(',');
+            }
+            body += record;
+        }
+        body += "]}]}]}";
+        body
+    }
+}
+
+struct Attribute<'a>(&'a str, &'a str);
+
+impl<'a> ::std::fmt::Display for Attribute<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(
+            f,
+            "{{\"key\":{},\"value\":{{\"stringValue\":{}}}}}",
+            JsonStr(self.0),
+            JsonStr(self.1)
+        )
+    }
+}
+
+fn unix_nanos(timestamp: OffsetDateTime) -> i128 {
+    timestamp.unix_timestamp_nanos()
+}
+
+/// returns the JSON LogRecord of `frame`
+fn record(frame: &[u8]) -> String {
+    let observed = unix_nanos(OffsetDateTime::now_utc());
+    let mut record = String::new();
+    match validate(frame) {
+        Ok(message) => {
+            let time = message
+                .timestamp
+                .as_ref()
+                .and_then(|timestamp| OffsetDateTime::parse(timestamp, &Rfc3339).ok())
+                .map_or(observed, unix_nanos);
+            let (number, text) = SEVERITIES[message.severity() as usize];
+            let _ = write!(
+                record,
+                "{{\"timeUnixNano\":\"{}\",\"observedTimeUnixNano\":\"{}\",\"severityNumber\":{},\"severityText\":\"{}\",\"body\":{{\"stringValue\":{}}},\"attributes\":[",
+                time,
+                observed,
+                number,
+                text,
+                JsonStr(&message.message)
+            );
+            attributes(&mut record, &message);
+            record.push_str("]}");
+        }
+        // Not a syslog frame, export it as is
+        Err(_) => {
+            let _ = write!(
+                record,
+                "{{\"timeUnixNano\":\"{}\",\"observedTimeUnixNano\":\"{}\",\"body\":{{\"stringValue\":{}}}}}",
+                observed,
+                observed,
+                JsonStr(&String::from_utf8_lossy(frame))
+            );
+        }
+    }
+    record
+}
+
+fn attributes(record: &mut String, message: &ParsedMessage) {
+    let facility = message.facility().to_string();
+    let mut attributes = vec![("syslog.facility".to_string(), &facility[..])];
+    let fields = [
+        ("host.name", &message.hostname),
+        ("syslog.appname", &message.app_name),
+        ("syslog.procid", &message.proc_id),
+        ("syslog.msgid", &message.msg_id),
+    ];
+    for &(key, value) in &fields {
+        if let Some(ref value) = *value {
+            attributes.push((key.to_string(), value));
+        }
+    }
+    for (id, params) in &message.structured_data {
+        for (name, value) in params {
+            attributes.push((format!("{}.{}", id, name), value));
+        }
+    }
+
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        if i > 0 {
+            record.push(',');
+        }
+        let _ = write!(record, "{}", Attribute(key, value));
+    }
+}
+
+impl Write for OtlpExporter {
+    /// Queues `frame` as one LogRecord, sending the batch once it is full
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if self.records.push(record(frame)) {
+            self.flush()?;
+        }
+        Ok(frame.len())
+    }
+
+    /// Sends the queued records, which are kept for the next attempt if that fails
+    fn flush(&mut self) -> io::Result<()> {
+        if self.records.entries.is_empty() {
+            return Ok(());
+        }
+        self.endpoint
+            .post("application/json", self.request().as_bytes())?;
+        self.records.entries.clear();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_otlp_export() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let mut exporter = OtlpExporter::new(&url, "thermostat")
+        .unwrap()
+        .with_batch_size(2);
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        ::http::serve_one(stream, "200 OK")
+    });
+
+    exporter
+        .write_all(
+            b"<11>1 2024-05-01T10:00:00Z esp32 app 12 boot [reading@32473 temp=\"23.5\"] hello",
+        )
+        .unwrap();
+    exporter.write_all(b"not syslog").unwrap();
+
+    let (headers, body) = server.join().unwrap();
+    let body = String::from_utf8(body).unwrap();
+    assert!(headers.starts_with("POST /v1/logs HTTP/1.1\r\n"));
+    assert!(body.contains("{\"key\":\"service.name\",\"value\":{\"stringValue\":\"thermostat\"}}"));
+    assert!(body.contains("\"timeUnixNano\":\"1714557600000000000\""));
+    assert!(body.contains("\"severityNumber\":17,\"severityText\":\"ERROR\""));
+    assert!(body.contains("\"body\":{\"stringValue\":\"hello\"}"));
+    assert!(body.contains("{\"key\":\"syslog.msgid\",\"value\":{\"stringValue\":\"boot\"}}"));
+    assert!(body.contains("{\"key\":\"reading@32473.temp\",\"value\":{\"stringValue\":\"23.5\"}}"));
+    assert!(body.contains("\"body\":{\"stringValue\":\"not syslog\"}}"));
+    assert!(exporter.records.entries.is_empty());
+}
@@ -0,0 +1,125 @@
+//! Messages that aren't text of their own: byte payloads, and values only implementing `Debug`
+//!
+//! Both display, so every formatter sends them as it sends any other message:
+//!
+//! ```no_run
+//! # use esp_syslog::{Bytes, Debugged, Formatter3164};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let mut logger = esp_syslog::udp(Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514")?;
+//! # let (response, modem_frame, state) = (vec![0u8], [0x7eu8], Some("idle"));
+//! logger.info(Bytes::lossy(&response))?;
+//! logger.debug(Bytes::hex(&modem_frame))?;
+//! logger.warning(Debugged(&state))?;
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::{self, Debug, Display, Write};
+
+/// How `Bytes` are sent
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// as UTF-8 text, every invalid sequence being replaced by `U+FFFD`, as
+    /// `String::from_utf8_lossy` does
+    #[default]
+    Lossy,
+    /// as lowercase hex digits, a space between the bytes: `48 65 6c`
+    Hex,
+}
+
+/// A byte payload, e.g. a modem response, displayed without failing on invalid UTF-8
+#[derive(Copy, Clone, Debug)]
+pub struct Bytes<'a> {
+    pub bytes: &'a [u8],
+    pub encoding: BytesEncoding,
+}
+
+impl<'a> Bytes<'a> {
+    pub fn lossy(bytes: &'a [u8]) -> Self {
+        Bytes {
+            bytes,
+            encoding: BytesEncoding::Lossy,
+        }
+    }
+
+    pub fn hex(bytes: &'a [u8]) -> Self {
+        Bytes {
+            bytes,
+            encoding: BytesEncoding::Hex,
+        }
+    }
+}
+
+impl<'a> From<&'a [u8]> for Bytes<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        Bytes::lossy(bytes)
+    }
+}
+
+impl<'a> Display for Bytes<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.encoding {
+            BytesEncoding::Lossy => self.bytes.utf8_chunks().try_for_each(|chunk| {
+                f.write_str(chunk.valid())?;
+                match chunk.invalid() {
+                    [] => Ok(()),
+                    _ => f.write_char(char::REPLACEMENT_CHARACTER),
+                }
+            }),
+            BytesEncoding::Hex => {
+                for (i, byte) in self.bytes.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(' ')?;
+                    }
+                    write!(f, "{byte:02x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A value sent as its `Debug` representation, for the types without a `Display` one
+#[derive(Copy, Clone)]
+pub struct Debugged<T>(pub T);
+
+impl<T: Debug> Debug for Debugged<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Debug> Display for Debugged<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[test]
+fn test_payloads() {
+    use {Formatter3164, FormatterGelf, LogFormat, Severity};
+
+    let modem = b"+CSQ: 17\xff\xfe,99";
+    assert_eq!(
+        Bytes::lossy(modem).to_string(),
+        "+CSQ: 17\u{fffd}\u{fffd},99"
+    );
+    assert_eq!(Bytes::hex(&modem[..4]).to_string(), "2b 43 53 51");
+    assert_eq!(Bytes::hex(&[]).to_string(), "");
+    assert_eq!(Debugged(Some("idle")).to_string(), "Some(\"idle\")");
+
+    let mut buf = Vec::new();
+    Formatter3164::default()
+        .format(&mut buf, Severity::LOG_INFO, Bytes::lossy(modem))
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .ends_with(": +CSQ: 17\u{fffd}\u{fffd},99"));
+
+    let mut buf = Vec::new();
+    FormatterGelf::default()
+        .format(&mut buf, Severity::LOG_INFO, modem.to_vec())
+        .unwrap();
+    assert!(String::from_utf8(buf)
+        .unwrap()
+        .contains("\"short_message\":\"+CSQ: 17\u{fffd}\u{fffd},99\""));
+}
@@ -0,0 +1,126 @@
+//! Messages attributed to the subsystems of the firmware, each one as its own process
+use std::io::Write;
+
+use errors::*;
+use format::{LogFormat, Severity, WithProcess};
+use Logger;
+
+/// A logger sending its messages as from another process, see `Logger::with_process`
+pub struct ProcessLogger<'a, W: Write + 'a, F: 'a> {
+    logger: &'a mut Logger<W, F>,
+    process: &'a str,
+}
+
+impl<W: Write, F> Logger<W, F> {
+    /// returns a handle sending the messages through this logger as from `process`, e.g. `ota`,
+    /// rather than the formatter's `process`
+    ///
+    /// ```no_run
+    /// # use esp_syslog::Formatter3164;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut logger = esp_syslog::udp(Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514")?;
+    /// logger.with_process("ota").info("downloading 1.4.2")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_process<'a>(&'a mut self, process: &'a str) -> ProcessLogger<'a, W, F> {
+        ProcessLogger {
+            logger: self,
+            process,
+        }
+    }
+}
+
+impl<'a, W: Write, F> ProcessLogger<'a, W, F> {
+    /// returns the process the messages are sent as from
+    pub fn process(&self) -> &str {
+        self.process
+    }
+
+    fn send<T>(&mut self, severity: Severity, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        let process = self.process;
+        self.logger.send(severity, WithProcess { process, message })
+    }
+
+    pub fn emerg<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_EMERG, message)
+    }
+
+    pub fn alert<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_ALERT, message)
+    }
+
+    pub fn crit<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_CRIT, message)
+    }
+
+    pub fn err<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_ERR, message)
+    }
+
+    pub fn warning<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_WARNING, message)
+    }
+
+    pub fn notice<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_NOTICE, message)
+    }
+
+    pub fn info<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_INFO, message)
+    }
+
+    pub fn debug<T>(&mut self, message: T) -> Result<()>
+    where
+        F: LogFormat<WithProcess<'a, T>>,
+    {
+        self.send(Severity::LOG_DEBUG, message)
+    }
+}
+
+#[test]
+fn test_with_process() {
+    use {Formatter3164, Formatter5424, Message5424};
+
+    let mut logger = Logger::new(Vec::new(), Formatter3164::default());
+    logger.with_process("ota").info("downloading").unwrap();
+    logger.info("done").unwrap();
+    let sent = String::from_utf8(logger.backend.clone()).unwrap();
+    assert!(sent.contains("ota[0]: downloading<"));
+    assert!(sent.ends_with("main[0]: done"));
+
+    let mut logger = Logger::new(Vec::new(), Formatter5424::default());
+    let mut wifi = logger.with_process("wifi");
+    assert_eq!(wifi.process(), "wifi");
+    wifi.warning("disconnected").unwrap();
+    logger
+        .info(Message5424::new("rebooting").process("ota"))
+        .unwrap();
+    let sent = String::from_utf8(logger.backend).unwrap();
+    assert!(sent.contains(" wifi 0 - - disconnected<"));
+    assert!(sent.ends_with(" ota 0 - - rebooting"));
+}
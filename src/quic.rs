@@ -0,0 +1,125 @@
+//! QUIC transport, enabled by the experimental `quic` feature
+//!
+//! Frames are sent on a single unidirectional stream, each one prefixed by its length in
+//! decimal and a space, as RFC 6587 octet counting does over TCP. QUIC brings TLS 1.3, and
+//! survives lossy and roaming links (cellular, Wi-Fi handovers) much better than TCP; when the
+//! connection is lost anyway, it is re-established on the next write.
+//!
+//! The TLS configuration, certificates included, is up to the caller: build a
+//! `quinn::ClientConfig` with the root certificates of the server.
+use std::error::Error;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use esp_syslog_core::Framing;
+use quinn::{ClientConfig, Connection, Endpoint, SendStream};
+use tokio::runtime::{self, Runtime};
+use tokio::time;
+
+/// how long dropping a `QuicStream` waits for the server to receive the last frames
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A QUIC stream carrying syslog frames to a server
+pub struct QuicStream {
+    runtime: Runtime,
+    endpoint: Endpoint,
+    server: SocketAddr,
+    server_name: String,
+    stream: Option<(Connection, SendStream)>,
+}
+
+fn other<E: Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::other(e)
+}
+
+impl QuicStream {
+    /// connects to `server`, whose certificate must be valid for `server_name`
+    pub fn connect(
+        server: SocketAddr,
+        server_name: &str,
+        config: ClientConfig,
+    ) -> io::Result<QuicStream> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let local: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let mut endpoint = {
+            let _guard = runtime.enter();
+            Endpoint::client(local)?
+        };
+        endpoint.set_default_client_config(config);
+
+        let mut stream = QuicStream {
+            runtime,
+            endpoint,
+            server,
+            server_name: server_name.to_string(),
+            stream: None,
+        };
+        stream.reconnect()?;
+        Ok(stream)
+    }
+
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.stream = None;
+        let _guard = self.runtime.enter();
+        let connecting = self
+            .endpoint
+            .connect(self.server, &self.server_name)
+            .map_err(other)?;
+        let connection = self.runtime.block_on(connecting)?;
+        let stream = self.runtime.block_on(connection.open_uni())?;
+        self.stream = Some((connection, stream));
+        Ok(())
+    }
+
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let stream = match self.stream {
+            Some((_, ref mut stream)) => stream,
+            None => return Err(io::ErrorKind::NotConnected.into()),
+        };
+        let counted = Framing::OctetCounted.encode(frame);
+        self.runtime
+            .block_on(stream.write_all(&counted))
+            .map_err(other)
+    }
+}
+
+impl Write for QuicStream {
+    /// Sends `frame` as one syslog message, re-establishing the connection once if needed
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if self.stream.is_none() {
+            self.reconnect()?;
+        }
+        if self.send(frame).is_err() {
+            self.reconnect()?;
+            self.send(frame)?;
+        }
+        Ok(frame.len())
+    }
+
+    /// QUIC sends data as soon as possible, so there is nothing to flush
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for QuicStream {
+    fn drop(&mut self) {
+        if let Some((connection, mut stream)) = self.stream.take() {
+            let _guard = self.runtime.enter();
+            if stream.finish().is_ok() {
+                let _ = self
+                    .runtime
+                    .block_on(time::timeout(CLOSE_TIMEOUT, stream.stopped()));
+            }
+            connection.close(0u32.into(), b"");
+            self.runtime.block_on(self.endpoint.wait_idle());
+        }
+    }
+}
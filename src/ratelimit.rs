@@ -0,0 +1,162 @@
+//! Throttling of the messages of a logger stuck in a loop
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Metadata, Record};
+
+use sync::Lock;
+
+/// A `log` implementation passing at most `per_second` messages a second on to `inner`
+///
+/// Messages are let through by a token bucket: it holds up to `burst` tokens, refilled at
+/// `per_second` tokens a second, and every message takes one. Messages arriving while it is
+/// empty are dropped and counted; the next one let through is preceded by a `suppressed N
+/// messages` warning, which takes no token, so a firmware bug logging in a tight loop can't
+/// flood the Wi-Fi link yet still shows.
+///
+/// ```no_run
+/// # use esp_syslog::{BasicLogger, RateLimitedLogger, Formatter3164};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let logger = esp_syslog::udp(Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514")?;
+/// let logger = RateLimitedLogger::new(BasicLogger::new(logger), 20, 100);
+/// log::set_boxed_logger(Box::new(logger))?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RateLimitedLogger<L> {
+    inner: L,
+    bucket: Lock<TokenBucket>,
+}
+
+struct TokenBucket {
+    /// the time it takes to earn a token
+    period: Duration,
+    burst: u32,
+    tokens: u32,
+    /// when the last token was earned, or when the bucket was last found full
+    refilled: Instant,
+    suppressed: usize,
+}
+
+impl TokenBucket {
+    /// takes a token, returning whether there was one
+    fn take(&mut self) -> bool {
+        let earned = (self.refilled.elapsed().as_nanos() / self.period.as_nanos().max(1))
+            .min(u32::MAX as u128) as u32;
+        if self.tokens.saturating_add(earned) >= self.burst {
+            self.tokens = self.burst;
+            self.refilled = Instant::now();
+        } else if earned > 0 {
+            self.tokens += earned;
+            self.refilled += self.period * earned;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+impl<L: Log> RateLimitedLogger<L> {
+    /// `per_second` and `burst` are raised to 1 if 0
+    pub fn new(inner: L, per_second: u32, burst: u32) -> Self {
+        let burst = burst.max(1);
+        RateLimitedLogger {
+            inner,
+            bucket: Lock::new(TokenBucket {
+                period: Duration::from_secs(1) / per_second.max(1),
+                burst,
+                tokens: burst,
+                refilled: Instant::now(),
+                suppressed: 0,
+            }),
+        }
+    }
+
+    /// returns the number of messages dropped since the last `suppressed N messages` warning
+    pub fn suppressed(&self) -> usize {
+        self.bucket.lock().suppressed
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L: Log> Log for RateLimitedLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let suppressed = {
+            let mut bucket = self.bucket.lock();
+            if !bucket.take() {
+                bucket.suppressed += 1;
+                return;
+            }
+            ::std::mem::replace(&mut bucket.suppressed, 0)
+        };
+        if suppressed > 0 {
+            self.inner.log(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target(record.target())
+                    .args(format_args!("suppressed {suppressed} messages"))
+                    .build(),
+            );
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[test]
+fn test_rate_limited_logger() {
+    use std::thread;
+
+    use log::LevelFilter;
+
+    struct Collect(Lock<Vec<String>>);
+
+    impl Log for Collect {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    log::set_max_level(LevelFilter::Trace);
+    let logger = RateLimitedLogger::new(Collect(Lock::new(Vec::new())), 100, 2);
+    let log = |message: &str| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("{}", message))
+                .build(),
+        )
+    };
+    for _ in 0..5 {
+        log("loop");
+    }
+    assert_eq!(logger.suppressed(), 3);
+    thread::sleep(Duration::from_millis(20));
+    log("after");
+
+    assert_eq!(
+        *logger.inner().0.lock(),
+        ["loop", "loop", "suppressed 3 messages", "after"]
+    );
+    assert_eq!(logger.suppressed(), 0);
+}
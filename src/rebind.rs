@@ -0,0 +1,178 @@
+//! Rebinding of the sockets when the local address changes
+//!
+//! After roaming to another access point or a DHCP renewal on a new subnet, a socket bound to
+//! the previous address keeps sending from it, and its packets are dropped. The address the
+//! network stack would pick now is found by connecting a fresh UDP socket to the server, which
+//! sends nothing, and compared with the one of the backend.
+//!
+//! A UDP socket is only rebound once its address is no longer one of the device's: one bound
+//! to an interface on purpose, e.g. the access point's while the station is up too, keeps its
+//! binding. The new socket keeps the source port and the options of the previous one.
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use errors::*;
+use {installed, LoggerBackend};
+
+impl LoggerBackend {
+    /// rebinds a UDP backend, or reconnects a reconnecting TCP one, if its local address is no
+    /// longer the one used to reach its server, returning whether it did
+    ///
+    /// Sockets bound to the unspecified address follow the address changes by themselves, and
+    /// the other backends are left alone.
+    pub fn rebind(&mut self) -> io::Result<bool> {
+        match *self {
+            LoggerBackend::Udp(ref mut socket, server) => {
+                let local = socket.local_addr()?;
+                if local.ip().is_unspecified() || is_local(local.ip()) {
+                    return Ok(false);
+                }
+                let source = source_towards(server)?.local_addr()?.ip();
+                *socket = rebound(socket, source)?;
+                Ok(true)
+            }
+            LoggerBackend::UdpConnected(ref mut socket) => {
+                let server = socket.peer_addr()?;
+                let local = socket.local_addr()?;
+                if is_local(local.ip()) {
+                    return Ok(false);
+                }
+                let source = source_towards(server)?.local_addr()?.ip();
+                let replacement = rebound(socket, source)?;
+                replacement.connect(server)?;
+                *socket = replacement;
+                Ok(true)
+            }
+            LoggerBackend::ReconnectingTcp(ref mut stream) => {
+                let local = match stream.get_ref() {
+                    Some(connection) => connection.local_addr()?,
+                    None => return Ok(false),
+                };
+                if source_towards(stream.server())?.local_addr()?.ip() == local.ip() {
+                    return Ok(false);
+                }
+                stream.disconnect();
+                Ok(true)
+            }
+            LoggerBackend::Fallback(ref mut fallback) => fallback.primary.rebind(),
+            LoggerBackend::Spool(ref mut spool) => spool.network.rebind(),
+            LoggerBackend::Batching(ref mut batching) => batching.inner.rebind(),
+            LoggerBackend::Failover(ref mut failover) => failover.active_mut().rebind(),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// returns a UDP socket connected to `server`, bound to the address that reaches it now
+fn source_towards(server: SocketAddr) -> io::Result<UdpSocket> {
+    let unspecified = match server {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind((unspecified, 0))?;
+    socket.connect(server)?;
+    Ok(socket)
+}
+
+/// returns a socket bound to `ip` and the port of `socket`, with its options
+fn rebound(socket: &UdpSocket, ip: IpAddr) -> io::Result<UdpSocket> {
+    let replacement = UdpSocket::bind((ip, socket.local_addr()?.port()))?;
+    replacement.set_broadcast(socket.broadcast()?)?;
+    if ip.is_ipv4() {
+        replacement.set_multicast_ttl_v4(socket.multicast_ttl_v4()?)?;
+        replacement.set_multicast_loop_v4(socket.multicast_loop_v4()?)?;
+    }
+    replacement.set_ttl(socket.ttl()?)?;
+    replacement.set_write_timeout(socket.write_timeout()?)?;
+    Ok(replacement)
+}
+
+/// returns whether `ip` is still an address of one of the interfaces of the ESP32
+#[cfg(target_os = "espidf")]
+fn is_local(ip: IpAddr) -> bool {
+    use WifiInterface;
+
+    [WifiInterface::Station, WifiInterface::AccessPoint]
+        .iter()
+        .any(|interface| interface.address().map(IpAddr::V4) == Some(ip))
+}
+
+/// returns whether `ip` is still an address of the host, which it can be bound to
+#[cfg(not(target_os = "espidf"))]
+fn is_local(ip: IpAddr) -> bool {
+    UdpSocket::bind((ip, 0)).is_ok()
+}
+
+/// How often `BasicLogger` checks its local address, see `BasicLogger::with_rebind_interval`
+pub(crate) struct RebindCheck {
+    interval: Duration,
+    last: Instant,
+}
+
+impl RebindCheck {
+    pub(crate) fn new(interval: Duration) -> RebindCheck {
+        RebindCheck {
+            interval,
+            last: Instant::now(),
+        }
+    }
+
+    /// returns whether the interval elapsed since the last check, starting a new one if so
+    pub(crate) fn due(&mut self) -> bool {
+        if self.last.elapsed() < self.interval {
+            return false;
+        }
+        self.last = Instant::now();
+        true
+    }
+}
+
+/// rebinds the backend of the logger installed by `init` or one of the `init_*` functions if
+/// its local address changed, see `LoggerBackend::rebind`
+///
+/// Meant to be called from the handler of the ESP-IDF `IP_EVENT_STA_GOT_IP` event, so the
+/// backend follows the new address right away.
+pub fn rebind() -> Result<bool> {
+    installed()
+        .ok_or(Error::NotInstalled)?
+        .logger
+        .lock()
+        .backend
+        .rebind()
+        .map_err(Error::from)
+}
+
+#[cfg(all(target_os = "linux", not(feature = "host-sim")))]
+#[test]
+fn test_rebind() {
+    use {Formatter3164, UdpBind};
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // Any 127.0.0.0/8 address is local on Linux, but 127.0.0.1 is the one that reaches the
+    // server: the socket bound to another interface keeps its binding
+    let bind = UdpBind {
+        port: 0,
+        address: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))),
+    };
+    let mut logger =
+        ::udp_bound(Formatter3164::default(), bind, server.local_addr().unwrap()).unwrap();
+    let bound = match logger.backend {
+        LoggerBackend::UdpConnected(ref socket) => socket.local_addr().unwrap(),
+        _ => unreachable!(),
+    };
+    assert!(!logger.backend.rebind().unwrap());
+    logger.info("bound").unwrap();
+    let mut buf = [0; 64];
+    let (_, from) = server.recv_from(&mut buf).unwrap();
+    assert_eq!(from, bound);
+
+    let broadcast = UdpSocket::bind("127.0.0.2:0").unwrap();
+    broadcast.set_broadcast(true).unwrap();
+    let replacement = rebound(&broadcast, IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+    let local = replacement.local_addr().unwrap();
+    assert_eq!(local.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    assert_eq!(local.port(), broadcast.local_addr().unwrap().port());
+    assert!(replacement.broadcast().unwrap());
+    assert!(!is_local("192.0.2.1".parse().unwrap()));
+}
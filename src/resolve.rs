@@ -0,0 +1,117 @@
+//! UDP logging to a server behind a name whose address changes, e.g. a dynamic DNS one
+use std::io::{self, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// how often `ResolvingUdpSocket` resolves the server name again by default
+pub const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A UDP socket connected to the address a server name resolves to, resolving it again
+/// periodically, and after a failed send
+///
+/// The name is resolved before the first write after the interval, which blocks for as long as
+/// the DNS query takes; if that fails, the last address is kept. A send failing with an ICMP
+/// error (`ConnectionRefused`...) also has the name resolved again before the next one, so a
+/// collector whose address changed is found again without waiting for the interval.
+#[derive(Debug)]
+pub struct ResolvingUdpSocket {
+    socket: UdpSocket,
+    server: String,
+    addr: SocketAddr,
+    interval: Duration,
+    resolved_at: Instant,
+    /// set by a failed send
+    stale: bool,
+}
+
+impl ResolvingUdpSocket {
+    /// binds `local`, and connects to `server`, e.g. `logs.example.net:514`
+    pub fn connect<T: ToSocketAddrs>(local: T, server: &str) -> io::Result<ResolvingUdpSocket> {
+        let socket = UdpSocket::bind(local)?;
+        let addr = resolve(server, &socket)?;
+        socket.connect(addr)?;
+        Ok(ResolvingUdpSocket {
+            socket,
+            server: server.to_string(),
+            addr,
+            interval: DEFAULT_RESOLVE_INTERVAL,
+            resolved_at: Instant::now(),
+            stale: false,
+        })
+    }
+
+    /// sets how often the name is resolved again, `DEFAULT_RESOLVE_INTERVAL` by default
+    pub fn set_resolve_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// returns the address the messages are sent to
+    pub fn server_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// resolves the name again, connecting the socket to the new address if it changed, and
+    /// returns whether it did
+    pub fn resolve(&mut self) -> io::Result<bool> {
+        self.resolved_at = Instant::now();
+        let addr = resolve(&self.server, &self.socket)?;
+        self.stale = false;
+        if addr == self.addr {
+            return Ok(false);
+        }
+        self.socket.connect(addr)?;
+        self.addr = addr;
+        Ok(true)
+    }
+}
+
+/// returns the first address of `server` of the family of `socket`
+fn resolve(server: &str, socket: &UdpSocket) -> io::Result<SocketAddr> {
+    let ipv4 = socket.local_addr()?.is_ipv4();
+    server
+        .to_socket_addrs()?
+        .find(|addr| addr.is_ipv4() == ipv4)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{server} has no address of the socket's family"),
+            )
+        })
+}
+
+impl Write for ResolvingUdpSocket {
+    fn write(&mut self, message: &[u8]) -> io::Result<usize> {
+        if self.stale || self.resolved_at.elapsed() >= self.interval {
+            // The last address is kept when the name doesn't resolve
+            let _ = self.resolve();
+        }
+        let sent = self.socket.send(message);
+        if sent.is_err() {
+            self.stale = true;
+        }
+        sent
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_resolving_udp() {
+    let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = format!("localhost:{}", first.local_addr().unwrap().port());
+    let mut socket = ResolvingUdpSocket::connect("127.0.0.1:0", &server).unwrap();
+    assert_eq!(socket.server_addr(), first.local_addr().unwrap());
+    socket.write_all(b"<14>hello").unwrap();
+    let mut buf = [0; 16];
+    let len = first.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"<14>hello");
+
+    socket.set_resolve_interval(Duration::ZERO);
+    assert!(!socket.resolve().unwrap());
+    socket.server = "no such host:514".to_string();
+    socket.write_all(b"<14>still sent").unwrap();
+    let len = first.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"<14>still sent");
+}
@@ -0,0 +1,181 @@
+//! Sanitization of the fields of the messages, which strict collectors reject otherwise
+//!
+//! Every wrapper sanitizes while it is displayed, without building a copy of the value.
+use std::fmt::{self, Display, Write};
+
+pub(crate) const HOSTNAME_MAX: usize = 255;
+pub(crate) const APP_NAME_MAX: usize = 48;
+pub(crate) const MSGID_MAX: usize = 32;
+/// for SD-IDs, and the names of SD-PARAMs
+pub(crate) const SD_NAME_MAX: usize = 32;
+pub(crate) const TAG_MAX: usize = 32;
+/// the TAG of a process without any alphanumeric character, the default process
+const TAG_FALLBACK: &str = "main";
+
+/// A header field (HOSTNAME, APP-NAME, PROCID, MSGID): printable US-ASCII only, truncated to
+/// its maximum length, and NILVALUE when nothing is left
+pub(crate) struct HeaderField<'a>(pub &'a str, pub usize);
+
+impl<'a> Display for HeaderField<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut printable = self.0.chars().filter(|&c| is_printable(c)).take(self.1);
+        match printable.next() {
+            Some(first) => {
+                f.write_char(first)?;
+                printable.try_for_each(|c| f.write_char(c))
+            }
+            None => f.write_char('-'),
+        }
+    }
+}
+
+/// A RFC 3164 TAG: alphanumeric characters only, truncated to 32 of them, and `main` when
+/// nothing is left, as collectors would take the MSG for the TAG otherwise
+pub(crate) struct Tag<'a>(pub &'a str);
+
+impl<'a> Display for Tag<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut alphanumeric = self
+            .0
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .take(TAG_MAX);
+        match alphanumeric.next() {
+            Some(first) => {
+                f.write_char(first)?;
+                alphanumeric.try_for_each(|c| f.write_char(c))
+            }
+            None => f.write_str(TAG_FALLBACK),
+        }
+    }
+}
+
+/// An SD-ID or an SD-NAME: printable US-ASCII but `=`, space, `]` and `"`, truncated
+pub(crate) struct SdName<'a>(pub &'a str);
+
+impl<'a> Display for SdName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0
+            .chars()
+            .filter(|&c| is_printable(c) && !matches!(c, '=' | ']' | '"'))
+            .take(SD_NAME_MAX)
+            .try_for_each(|c| f.write_char(c))
+    }
+}
+
+/// A PARAM-VALUE, with `"`, `\` and `]` escaped by a backslash
+pub(crate) struct SdValue<'a, V: ?Sized>(pub &'a V);
+
+impl<'a, V: Display + ?Sized> Display for SdValue<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(Escaping(f), "{}", self.0)
+    }
+}
+
+/// A MSG on a single line: every run of ASCII control characters, e.g. a line break, is
+/// replaced by a space, and the trailing ones are left out
+pub(crate) struct Msg<T>(pub T);
+
+impl<T: Display> Display for Msg<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut spacing = Spacing { f, control: false };
+        write!(spacing, "{}", self.0)
+    }
+}
+
+fn is_printable(c: char) -> bool {
+    ('!'..='~').contains(&c)
+}
+
+struct Escaping<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> Write for Escaping<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if matches!(c, '"' | '\\' | ']') {
+                self.0.write_char('\\')?;
+            }
+            self.0.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+struct Spacing<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    /// whether control characters were written since the last printable one, the message
+    /// being written in several pieces
+    control: bool,
+}
+
+impl<'a, 'b> Write for Spacing<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, part) in s.split(|c: char| c.is_ascii_control()).enumerate() {
+            self.control |= i > 0;
+            if part.is_empty() {
+                continue;
+            }
+            if self.control {
+                self.f.write_char(' ')?;
+                self.control = false;
+            }
+            self.f.write_str(part)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sanitize() {
+    use {Formatter3164, Formatter5424, LogFormat, Message5424, Severity, StructuredDataBuilder};
+
+    assert_eq!(HeaderField("", APP_NAME_MAX).to_string(), "-");
+    assert_eq!(HeaderField("my app\t", APP_NAME_MAX).to_string(), "myapp");
+    assert_eq!(
+        HeaderField(&"x".repeat(60), APP_NAME_MAX).to_string().len(),
+        48
+    );
+    assert_eq!(SdName("a=b c]\"d").to_string(), "abcd");
+    assert_eq!(Tag("ota-update_2").to_string(), "otaupdate2");
+    assert_eq!(Tag(&"x".repeat(40)).to_string().len(), 32);
+    assert_eq!(Tag("---").to_string(), "main");
+    assert_eq!(
+        SdValue("say \"hi\" [x]\\").to_string(),
+        "say \\\"hi\\\" [x\\]\\\\"
+    );
+    assert_eq!(Msg("line 1\r\nline 2\0").to_string(), "line 1 line 2");
+    assert_eq!(Msg(format_args!("{}\t{}\n", "a\n", "b")).to_string(), "a b");
+
+    let formatter = Formatter5424 {
+        process: String::new(),
+        ..Default::default()
+    };
+    let data = StructuredDataBuilder::new()
+        .element("origin id")
+        .param("ip=", "10.0.0.1");
+    let mut buf = Vec::new();
+    formatter
+        .format(
+            &mut buf,
+            Severity::LOG_INFO,
+            Message5424::new("up\n")
+                .msg_id("a very long message id, well over 32 characters")
+                .structured_data(data),
+        )
+        .unwrap();
+    let message = String::from_utf8(buf).unwrap();
+    assert!(
+        message.ends_with(" - 0 averylongmessageid,wellover32cha [originid ip=\"10.0.0.1\"] up")
+    );
+
+    // a multi-line message stays one frame with the non-transparent framing
+    let mut buf = Vec::new();
+    Formatter3164::default()
+        .format(
+            &mut buf,
+            Severity::LOG_ERR,
+            "panicked at main.rs\noverflow\n",
+        )
+        .unwrap();
+    assert!(buf.ends_with(b"main[0]: panicked at main.rs overflow"));
+}
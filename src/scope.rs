@@ -0,0 +1,148 @@
+//! Scoped `BasicLogger`s, sending the messages of a subsystem with their own facility, process
+//! and fields
+use std::fmt::Display;
+use std::sync::Arc;
+
+use errors::*;
+use facility::Facility;
+use format::{Severity, WithFields, WithProcess};
+use {BasicLogger, Formatter3164, Logger, LoggerBackend};
+
+/// What a scoped logger changes in the messages it sends, see `BasicLogger::scoped`
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    /// the facility of the messages, the formatter's when `None`
+    pub facility: Option<Facility>,
+    /// the process the messages are sent as from, the formatter's when `None`
+    pub process: Option<String>,
+    /// fields added to every message, after the ones of the call site
+    pub fields: Vec<(String, String)>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Scope::default()
+    }
+
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.facility = Some(facility);
+        self
+    }
+
+    pub fn process<S: Into<String>>(mut self, process: S) -> Self {
+        self.process = Some(process.into());
+        self
+    }
+
+    /// adds `name=value` to every message
+    pub fn field<V: Display>(mut self, name: &str, value: V) -> Self {
+        self.fields.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// returns this scope nested in `parent`: what this one leaves unset is the parent's, and
+    /// the fields of both are sent
+    fn inherit(self, parent: &Scope) -> Scope {
+        let mut fields = parent.fields.clone();
+        fields.extend(self.fields);
+        Scope {
+            facility: self.facility.or(parent.facility),
+            process: self.process.or_else(|| parent.process.clone()),
+            fields,
+        }
+    }
+
+    /// sends `message` through `logger`, its formatter set to the facility of the scope
+    /// meanwhile
+    pub(crate) fn send<T: Display>(
+        &self,
+        logger: &mut Logger<LoggerBackend, Formatter3164>,
+        severity: Severity,
+        message: WithFields<T>,
+    ) -> Result<()> {
+        let facility = logger.formatter.facility;
+        if let Some(scoped) = self.facility {
+            logger.formatter.facility = scoped;
+        }
+        let sent = match self.process {
+            Some(ref process) => logger.send(severity, WithProcess { process, message }),
+            None => logger.send(severity, message),
+        };
+        logger.formatter.facility = facility;
+        sent
+    }
+}
+
+impl BasicLogger {
+    /// returns a logger sharing the backend, levels and settings of this one, sending its
+    /// messages with the facility, process and fields of `scope`
+    ///
+    /// A scoped logger is as cheap as a clone, so every subsystem of the firmware can keep
+    /// one; scoping it again inherits the scope, the fields of both being sent. Log through it
+    /// with the `logger:` argument of the `log` macros:
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate log;
+    /// # extern crate esp_syslog;
+    /// # use esp_syslog::{BasicLogger, Formatter3164, Scope};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let logger = BasicLogger::new(esp_syslog::udp(Formatter3164::default(), "0.0.0.0:0", "192.168.1.10:514")?);
+    /// # let version = "1.4.2";
+    /// let ota = logger.scoped(Scope::new().process("ota").field("slot", "ota_1"));
+    /// log::info!(logger: ota, "downloading {}", version);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scoped(&self, scope: Scope) -> BasicLogger {
+        let scope = match self.scope {
+            Some(ref parent) => scope.inherit(parent),
+            None => scope,
+        };
+        BasicLogger {
+            scope: Some(Arc::new(scope)),
+            ..self.clone()
+        }
+    }
+
+    /// returns the scope of the logger, `None` for one that isn't scoped
+    pub fn scope(&self) -> Option<&Scope> {
+        self.scope.as_deref()
+    }
+}
+
+#[test]
+fn test_scoped_logger() {
+    use log::LevelFilter;
+    use {LimitedStorage, MemoryStorage, SpoolStorage};
+
+    let frames = LimitedStorage::new(MemoryStorage::new(), 1024);
+    let logger = BasicLogger::new(Logger::new(
+        LoggerBackend::Memory(frames),
+        Formatter3164::default(),
+    ));
+    let ota = logger.scoped(
+        Scope::new()
+            .facility(Facility::LOG_LOCAL3)
+            .process("ota")
+            .field("slot", "ota_1"),
+    );
+    let verify = ota.scoped(Scope::new().field("step", "verify"));
+    assert_eq!(verify.scope().unwrap().process.as_deref(), Some("ota"));
+    log::set_max_level(LevelFilter::Trace);
+    log::info!(logger: verify, "checksum ok");
+    log::info!(logger: logger, "idle");
+
+    let mut shared = logger.logger.lock();
+    let frames = match shared.backend {
+        LoggerBackend::Memory(ref mut frames) => frames,
+        _ => unreachable!(),
+    };
+    let scoped = frames.front().unwrap().unwrap();
+    // LOG_LOCAL3 | LOG_INFO
+    assert!(scoped.starts_with(b"<158>"));
+    assert!(scoped.ends_with(b"ota[0]: checksum ok slot=ota_1 step=verify"));
+    frames.pop_front().unwrap();
+    let unscoped = frames.front().unwrap().unwrap();
+    assert!(unscoped.starts_with(b"<14>"));
+    assert!(unscoped.ends_with(b"main[0]: idle"));
+}
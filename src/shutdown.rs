@@ -0,0 +1,82 @@
+//! Delivery of the pending messages and closing of the connection, before a deep sleep
+use std::io::{self, Write};
+use std::net::Shutdown;
+use std::time::Duration;
+
+use errors::*;
+use {BasicLogger, Logger, LoggerBackend, Stats, Timeouts};
+
+impl LoggerBackend {
+    /// flushes the backend, then closes its TCP connections
+    ///
+    /// A reconnecting TCP backend connects again on the next write, while a plain `Tcp` one
+    /// fails from then on. Backends wrapping others close them, and connectionless backends
+    /// are only flushed.
+    pub fn close(&mut self) -> io::Result<()> {
+        Write::flush(self)?;
+        match *self {
+            LoggerBackend::Tcp(ref socket) => socket.get_ref().shutdown(Shutdown::Both),
+            LoggerBackend::ReconnectingTcp(ref mut socket) => socket.close(),
+            #[cfg(all(unix, not(target_os = "espidf")))]
+            LoggerBackend::UnixStream(ref socket) => socket.shutdown(Shutdown::Both),
+            LoggerBackend::Fallback(ref mut backend) => backend.primary.close(),
+            LoggerBackend::Spool(ref mut backend) => backend.network.close(),
+            LoggerBackend::Batching(ref mut backend) => backend.inner.close(),
+            LoggerBackend::Failover(ref mut backend) => backend
+                .backends_mut()
+                .iter_mut()
+                .try_for_each(LoggerBackend::close),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<F> Logger<LoggerBackend, F> {
+    /// sends what the backend still buffers or batches, waiting at most `timeout` per connection
+    /// attempt and write, then closes the connection, and returns the messages sent and
+    /// dropped so far
+    ///
+    /// Meant to be called right before `esp_deep_sleep_start`, as nothing in the send buffers
+    /// survives the sleep. `timeout` replaces the TCP timeouts of the backend, from then on.
+    /// Fails if the buffered messages couldn't be sent, `stats` still telling how many others
+    /// were dropped.
+    pub fn shutdown(&mut self, timeout: Duration) -> Result<Stats> {
+        let timeouts = Timeouts {
+            connect: Some(timeout),
+            read: Some(timeout),
+            write: Some(timeout),
+        };
+        // Only TCP backends have timeouts
+        let _ = self.backend.set_timeouts(timeouts);
+        self.backend.close().map_err(Error::from)?;
+        Ok(self.backend.with_counters(self.stats()))
+    }
+}
+
+impl BasicLogger {
+    /// flushes and closes the connection of the installed logger, see `Logger::shutdown`
+    ///
+    /// Messages logged afterwards reconnect a reconnecting TCP backend.
+    pub fn shutdown(&self, timeout: Duration) -> Result<Stats> {
+        self.logger.lock().shutdown(timeout)
+    }
+}
+
+#[cfg(not(feature = "host-sim"))]
+#[test]
+fn test_shutdown() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use Formatter3164;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut logger = ::tcp(Formatter3164::default(), listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    logger.info("before the sleep").unwrap();
+
+    let stats = logger.shutdown(Duration::from_secs(1)).unwrap();
+    assert_eq!((stats.sent, stats.dropped, stats.reconnects), (1, 0, 0));
+    let mut received = String::new();
+    server.read_to_string(&mut received).unwrap();
+    assert!(received.ends_with("main[0]: before the sleep"));
+}
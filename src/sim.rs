@@ -0,0 +1,336 @@
+//! In-process fake syslog server, enabled by the `host-sim` feature
+//!
+//! With `host-sim`, the constructors of the UDP, TCP, GELF, Fluentd, TLS and QUIC loggers, and
+//! `Syslog::builder()`, don't open any socket: every frame the logger sends is recorded here
+//! and pretty-printed to stderr, so firmware logic can be developed and tested on a laptop with
+//! the exact same API. Their arguments are still checked, and server names resolved; the
+//! options of the sockets, e.g. timeouts or TLS configurations, are ignored. The HTTP-based
+//! exporters still post to their URL.
+use std::fmt::{self, Arguments};
+use std::io::{self, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use address::{resolve_first, AddressPreference};
+use errors::*;
+use {Logger, LoggerBackend};
+
+static FRAMES: Mutex<Vec<Frame>> = Mutex::new(Vec::new());
+static ECHO: AtomicBool = AtomicBool::new(true);
+
+const FACILITIES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv",
+    "ftp", "ntp", "audit", "alert", "clock", "local0", "local1", "local2", "local3", "local4",
+    "local5", "local6", "local7",
+];
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// A frame received by the fake server
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub transport: Transport,
+    pub server: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+/// Stand-in for a network socket, recording everything written to it
+pub struct SimSocket {
+    transport: Transport,
+    server: SocketAddr,
+}
+
+impl SimSocket {
+    pub fn new(transport: Transport, server: SocketAddr) -> SimSocket {
+        SimSocket { transport, server }
+    }
+
+    fn receive(&self, data: Vec<u8>) {
+        if ECHO.load(Ordering::Relaxed) {
+            eprintln!("{}", Pretty(self, &data));
+        }
+        FRAMES.lock().unwrap().push(Frame {
+            transport: self.transport,
+            server: self.server,
+            data,
+        });
+    }
+}
+
+impl Write for SimSocket {
+    fn write(&mut self, message: &[u8]) -> io::Result<usize> {
+        self.receive(message.to_vec());
+        Ok(message.len())
+    }
+
+    fn write_fmt(&mut self, args: Arguments) -> io::Result<()> {
+        self.receive(fmt::format(args).into_bytes());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Pretty<'a>(&'a SimSocket, &'a [u8]);
+
+impl<'a> fmt::Display for Pretty<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let transport = match self.0.transport {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+        };
+        write!(f, "[syslog-sim {} {}] ", transport, self.0.server)?;
+
+        let frame = String::from_utf8_lossy(self.1);
+        let priority = frame
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+            .and_then(|(priority, rest)| priority.parse::<usize>().ok().map(|p| (p, rest)));
+        match priority {
+            Some((priority, rest)) if priority >> 3 < FACILITIES.len() => write!(
+                f,
+                "{}.{}: {}",
+                FACILITIES[priority >> 3],
+                SEVERITIES[priority & 7],
+                rest
+            ),
+            _ => write!(f, "{}", frame),
+        }
+    }
+}
+
+/// returns every frame received so far
+pub fn frames() -> Vec<Frame> {
+    FRAMES.lock().unwrap().clone()
+}
+
+/// returns every frame received so far, and forgets about them
+pub fn take_frames() -> Vec<Frame> {
+    ::std::mem::take(&mut *FRAMES.lock().unwrap())
+}
+
+/// enables or disables pretty-printing received frames to stderr (enabled by default)
+pub fn set_echo(echo: bool) {
+    ECHO.store(echo, Ordering::Relaxed);
+}
+
+/// returns a logger sending to the fake server over `transport`, as if it was `server`
+fn logger<T: ToSocketAddrs, F>(
+    transport: Transport,
+    formatter: F,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    resolve_first(server).map(|server_addr| {
+        Logger::new(
+            LoggerBackend::Sim(SimSocket::new(transport, server_addr)),
+            formatter,
+        )
+    })
+}
+
+/// returns a UDP logger sending to the fake server as if it was `server`
+pub fn udp<T: ToSocketAddrs, F>(
+    formatter: F,
+    _local: T,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Udp, formatter, server)
+}
+
+/// returns a UDP logger sending to the fake server as if it was `server`, like `udp`
+pub fn udp_unconnected<T: ToSocketAddrs, F>(
+    formatter: F,
+    local: T,
+    server: T,
+) -> Result<Logger<LoggerBackend, F>> {
+    udp(formatter, local, server)
+}
+
+/// returns a UDP logger sending to the fake server as if it was `server`, like `udp`: the
+/// name is only resolved once
+pub fn udp_resolving<T: ToSocketAddrs, F>(
+    formatter: F,
+    _local: T,
+    server: &str,
+    _interval: Duration,
+) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Udp, formatter, server)
+}
+
+/// returns a TCP logger sending to the fake server as if it was `server`
+pub fn tcp<T: ToSocketAddrs, F>(formatter: F, server: T) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Tcp, formatter, server)
+}
+
+/// returns the backend of `tcp_lazy` and of lazy builders, sending to the fake server as if it
+/// was the first of `servers`
+pub(crate) fn tcp_lazy_backend(servers: &[SocketAddr]) -> io::Result<LoggerBackend> {
+    match servers.first() {
+        Some(&first) => Ok(LoggerBackend::Sim(SimSocket::new(Transport::Tcp, first))),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no server address",
+        )),
+    }
+}
+
+/// returns a TCP logger sending to the fake server as if it was the first address of the first
+/// of `servers`, which never fails over
+///
+/// Every address of every server is resolved, as the real function does.
+pub fn tcp_failover<T: ToSocketAddrs, F>(
+    formatter: F,
+    servers: &[T],
+) -> Result<Logger<LoggerBackend, F>> {
+    let mut resolved = Vec::with_capacity(servers.len());
+    for server in servers {
+        let addrs = AddressPreference::Any
+            .resolve(server)
+            .map_err(Error::Resolution)?;
+        resolved.push(addrs);
+    }
+    match resolved.first() {
+        Some(addrs) => logger(Transport::Tcp, formatter, addrs[0]),
+        None => Err(Error::initialization("no server address")),
+    }
+}
+
+/// returns a logger sending GELF messages to the fake server as if it was `server`, unchunked
+pub fn gelf<T: ToSocketAddrs, F>(formatter: F, server: T) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Udp, formatter, server)
+}
+
+/// returns a logger sending Fluentd forward messages to the fake server as if it was `server`,
+/// without the `tag` the real backend wraps them with
+#[cfg(feature = "fluentd")]
+pub fn fluentd<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    _tag: &str,
+) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Tcp, formatter, server)
+}
+
+/// returns a logger sending to the fake server as if it was `server`, in clear
+#[cfg(feature = "tls")]
+pub fn tls<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    _server_name: &str,
+    _config: Arc<rustls::ClientConfig>,
+) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Tcp, formatter, server)
+}
+
+/// returns a logger sending to the fake server as if it was `server`, in clear
+#[cfg(feature = "quic")]
+pub fn quic<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    _server_name: &str,
+    _config: quinn::ClientConfig,
+) -> Result<Logger<LoggerBackend, F>> {
+    logger(Transport::Udp, formatter, server)
+}
+
+/// returns a TCP logger sending to the fake server as if it was `server`, like `tcp`: the fake
+/// server never times out
+pub fn tcp_with_timeouts<T: ToSocketAddrs, F>(
+    formatter: F,
+    server: T,
+    _timeouts: ::Timeouts,
+) -> Result<Logger<LoggerBackend, F>> {
+    tcp(formatter, server)
+}
+
+/// returns the frame ending with `message`, the one of the test sending it among those other
+/// tests send meanwhile
+#[cfg(test)]
+fn received(message: &str) -> Option<Frame> {
+    frames()
+        .into_iter()
+        .find(|frame| frame.data.ends_with(message.as_bytes()))
+}
+
+#[test]
+fn test_sim_records_frames() {
+    set_echo(false);
+    let mut logger = udp(::Formatter3164::default(), "0.0.0.0:0", "127.0.0.1:514").unwrap();
+    logger.err("hello sim").unwrap();
+
+    let frame = received("main[0]: hello sim").unwrap();
+    assert_eq!(frame.transport, Transport::Udp);
+    assert_eq!(frame.server, "127.0.0.1:514".parse().unwrap());
+    assert!(frame.data.starts_with(b"<11>"));
+}
+
+#[test]
+fn test_sim_constructors() {
+    use std::time::Duration;
+    use {Formatter3164, Syslog, UdpBind};
+
+    set_echo(false);
+    let server: SocketAddr = "192.0.2.10:514".parse().unwrap();
+    let formatter = Formatter3164::default;
+    let loggers = vec![
+        (
+            Transport::Udp,
+            ::udp_bound(formatter(), UdpBind::default(), server),
+        ),
+        (
+            Transport::Udp,
+            ::udp_resolving(
+                formatter(),
+                "0.0.0.0:0",
+                "192.0.2.10:514",
+                Duration::from_secs(60),
+            ),
+        ),
+        (
+            Transport::Udp,
+            ::udp_broadcast(formatter(), "0.0.0.0:0", "192.0.2.10:514"),
+        ),
+        (
+            Transport::Tcp,
+            ::tcp_with_framing(formatter(), server, ::Framing::OctetCounted),
+        ),
+        (Transport::Tcp, ::tcp_lazy(formatter(), server, 1024)),
+        (
+            Transport::Tcp,
+            ::tcp_failover(formatter(), &[server, "192.0.2.11:514".parse().unwrap()]),
+        ),
+        (Transport::Udp, ::gelf(formatter(), server)),
+        (
+            Transport::Tcp,
+            Syslog::builder()
+                .transport_tcp(server)
+                .connect_lazily(1024)
+                .build(),
+        ),
+    ];
+    for (i, (transport, logger)) in loggers.into_iter().enumerate() {
+        let message = format!("constructor {}", i);
+        logger.unwrap().info(&message).unwrap();
+        let frame = received(&message).unwrap();
+        assert_eq!((frame.transport, frame.server), (transport, server));
+    }
+
+    let group = "192.0.2.10:514";
+    assert!(::udp_multicast(formatter(), "0.0.0.0:0", group, 1).is_err());
+    let mut logger = ::udp_multicast(formatter(), "0.0.0.0:0", "239.255.0.1:514", 1).unwrap();
+    logger.info("multicast").unwrap();
+    assert_eq!(received("multicast").unwrap().transport, Transport::Udp);
+}
@@ -0,0 +1,189 @@
+//! Splunk HTTP Event Collector backend, enabled by the `splunk` feature
+//!
+//! Every frame becomes one HEC event whose `event` is the frame as formatted, so Splunk's
+//! syslog extractions keep working. The frame is also parsed back: its timestamp and hostname
+//! become the `time` and `host` of the event, and its severity, facility and SD-PARAMs
+//! (`exampleSDID@32473.iut`...) become indexed `fields`. Frames `validate` rejects, e.g. those
+//! of a custom formatter, are sent without `host` or `fields`, timed when they are sent.
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+#[cfg(feature = "tls")]
+use rustls::ClientConfig;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use http::{BatchQueue, HttpEndpoint};
+use json::JsonStr;
+use validate::validate;
+
+/// number of events sent in one request by default
+const DEFAULT_BATCH_SIZE: usize = 32;
+/// how many batches are kept while the collector is unreachable, the oldest events being
+/// dropped beyond that
+const MAX_PENDING_BATCHES: usize = 4;
+
+/// A backend posting every frame as an event to a Splunk HTTP Event Collector
+pub struct SplunkHec {
+    endpoint: HttpEndpoint,
+    source: Option<String>,
+    sourcetype: String,
+    index: Option<String>,
+    events: BatchQueue<String>,
+}
+
+impl SplunkHec {
+    /// posts to the collector at `url`, e.g. `https://splunk:8088`, authenticating with the
+    /// HEC `token`
+    ///
+    /// The standard `/services/collector/event` path is used unless `url` has one. `https://`
+    /// URLs need the `tls` feature and `with_tls_config`.
+    pub fn new(url: &str, token: &str) -> io::Result<SplunkHec> {
+        let mut endpoint = HttpEndpoint::parse(url, "/services/collector/event")?;
+        endpoint.add_header("Authorization", &format!("Splunk {}", token));
+        Ok(SplunkHec {
+            endpoint,
+            source: None,
+            sourcetype: "syslog".to_string(),
+            index: None,
+            events: BatchQueue::new(DEFAULT_BATCH_SIZE, MAX_PENDING_BATCHES),
+        })
+    }
+
+    /// sets the TLS configuration, with the root certificates trusted for the collector
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>) -> SplunkHec {
+        self.endpoint.set_tls_config(config);
+        self
+    }
+
+    /// sets the `source` of the events, left to the token's default otherwise
+    pub fn with_source(mut self, source: &str) -> SplunkHec {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// sets the `sourcetype` of the events (`syslog` by default)
+    pub fn with_sourcetype(mut self, sourcetype: &str) -> SplunkHec {
+        self.sourcetype = sourcetype.to_string();
+        self
+    }
+
+    /// sets the `index` of the events, left to the token's default otherwise
+    pub fn with_index(mut self, index: &str) -> SplunkHec {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    /// sets how many events are batched in one request (32 by default)
+    ///
+    /// Events are also sent when the backend is flushed.
+    pub fn with_batch_size(mut self, batch_size: usize) -> SplunkHec {
+        self.events.set_batch_size(batch_size);
+        self
+    }
+
+    /// returns the JSON HEC event of `frame`
+    fn event(&self, frame: &[u8]) -> String {
+        let mut event = String::new();
+        let body = String::from_utf8_lossy(frame);
+        let message = validate(frame).ok();
+        let time = message
+            .as_ref()
+            .and_then(|message| message.timestamp.as_ref())
+            .and_then(|timestamp| OffsetDateTime::parse(timestamp, &Rfc3339).ok())
+            .unwrap_or_else(OffsetDateTime::now_utc);
+
+        let _ = write!(
+            event,
+            "{{\"time\":{}.{:03},\"sourcetype\":{},\"event\":{}",
+            time.unix_timestamp(),
+            time.millisecond(),
+            JsonStr(&self.sourcetype),
+            JsonStr(&body)
+        );
+        if let Some(ref source) = self.source {
+            let _ = write!(event, ",\"source\":{}", JsonStr(source));
+        }
+        if let Some(ref index) = self.index {
+            let _ = write!(event, ",\"index\":{}", JsonStr(index));
+        }
+        if let Some(message) = message {
+            if let Some(ref hostname) = message.hostname {
+                let _ = write!(event, ",\"host\":{}", JsonStr(hostname));
+            }
+            let _ = write!(
+                event,
+                ",\"fields\":{{\"severity\":\"{}\",\"facility\":\"{}\"",
+                message.severity(),
+                message.facility()
+            );
+            for (id, params) in &message.structured_data {
+                for (name, value) in params {
+                    let key = format!("{}.{}", id, name);
+                    let _ = write!(event, ",{}:{}", JsonStr(&key), JsonStr(value));
+                }
+            }
+            event.push('}');
+        }
+        event.push('}');
+        event
+    }
+}
+
+impl Write for SplunkHec {
+    /// Queues `frame` as one event, sending the batch once it is full
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        let event = self.event(frame);
+        if self.events.push(event) {
+            self.flush()?;
+        }
+        Ok(frame.len())
+    }
+
+    /// Sends the queued events, which are kept for the next attempt if that fails
+    fn flush(&mut self) -> io::Result<()> {
+        if self.events.entries.is_empty() {
+            return Ok(());
+        }
+        // HEC takes batches as concatenated events
+        let body = self.events.entries.concat();
+        self.endpoint.post("application/json", body.as_bytes())?;
+        self.events.entries.clear();
+        Ok(())
+    }
+}
+
+#[test]
+fn test_splunk_events() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let mut hec = SplunkHec::new(&url, "0000-1111")
+        .unwrap()
+        .with_index("devices")
+        .with_batch_size(2);
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        ::http::serve_one(stream, "200 OK")
+    });
+
+    let frame = "<11>1 2024-05-01T10:00:00.250Z esp32 app 12 - [reading@32473 temp=\"23.5\"] hello";
+    hec.write_all(frame.as_bytes()).unwrap();
+    hec.write_all(b"raw").unwrap();
+
+    let (headers, body) = server.join().unwrap();
+    assert!(headers.starts_with("POST /services/collector/event HTTP/1.1\r\n"));
+    assert!(headers.contains("\r\nAuthorization: Splunk 0000-1111\r\n"));
+    let body = String::from_utf8(body).unwrap();
+    let first = format!(
+        "{{\"time\":1714557600.250,\"sourcetype\":\"syslog\",\"event\":{},\"index\":\"devices\",\"host\":\"esp32\",\"fields\":{{\"severity\":\"3\",\"facility\":\"1\",\"reading@32473.temp\":\"23.5\"}}}}",
+        JsonStr(frame)
+    );
+    assert!(body.starts_with(&first));
+    assert!(body.ends_with(",\"sourcetype\":\"syslog\",\"event\":\"raw\",\"index\":\"devices\"}"));
+}
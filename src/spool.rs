@@ -0,0 +1,875 @@
+//! Storage for messages spooled while the network is unavailable
+//!
+//! Spooled entries are already formatted frames, stored in order. `MemoryStorage` keeps them in
+//! RAM and `FileStorage` in a file (on SPIFFS/LittleFS for instance); other persistent storages
+//! (an NVS blob...) implement `SpoolStorage` the same way. With the `encryption` feature,
+//! `EncryptedStorage` wraps any storage so entries are only ever decrypted when they are about to
+//! be sent. `TieredStorage` splits entries between a persistent storage and a RAM one by
+//! priority.
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+#[cfg(all(feature = "encryption", target_os = "espidf"))]
+use errors::*;
+use facility::Facility;
+use format::Severity;
+use Priority;
+
+/// An ordered queue of spooled entries
+pub trait SpoolStorage {
+    /// appends an entry at the back of the queue
+    fn push(&mut self, entry: &[u8]) -> io::Result<()>;
+
+    /// returns the oldest entry, without removing it
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    /// removes the oldest entry
+    fn pop_front(&mut self) -> io::Result<()>;
+
+    /// returns the number of stored entries
+    fn len(&self) -> usize;
+
+    /// returns the number of bytes used by the stored entries, including any framing overhead
+    fn size(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// returns whether the last `push` dropped the entry it was given instead of storing it,
+    /// e.g. a full `LimitedStorage` with `Overflow::DropNewest`; `false` by default
+    fn last_push_dropped(&self) -> bool {
+        false
+    }
+}
+
+impl<S: SpoolStorage + ?Sized> SpoolStorage for Box<S> {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        (**self).push(entry)
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        (**self).front()
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        (**self).pop_front()
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn last_push_dropped(&self) -> bool {
+        (**self).last_push_dropped()
+    }
+}
+
+/// Keeps spooled entries in RAM
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    entries: VecDeque<Vec<u8>>,
+    size: usize,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        Default::default()
+    }
+}
+
+impl SpoolStorage for MemoryStorage {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        self.entries.push_back(entry.to_vec());
+        self.size += entry.len();
+        Ok(())
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.entries.front().cloned())
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        if let Some(entry) = self.entries.pop_front() {
+            self.size -= entry.len();
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+const RECORD_MAGIC: u8 = 0x5a;
+const RECORD_LIVE: u8 = 0xff;
+const RECORD_SENT: u8 = 0x00;
+const RECORD_HEADER_LEN: u64 = 8;
+/// the bytes of sent, torn or corrupted records a spool file holds at least before it is
+/// compacted
+const COMPACT_AFTER: usize = 4096;
+
+/// Spools entries to a file, e.g. on a SPIFFS or LittleFS partition
+///
+/// Every entry is stored as a record that survives power loss mid-write:
+///
+/// ```text
+/// magic (0x5a) | state (0xff live, 0x00 sent) | length (u16 LE) | CRC-32 (u32 LE) | entry
+/// ```
+///
+/// The CRC covers the length and the entry. When the file is opened, a recovery scan indexes
+/// every live record and skips torn or corrupted ones, resynchronizing on the next valid record;
+/// a torn record at the end of the file is truncated away. Removing the oldest entry only flips
+/// its state byte, and the file is emptied once every entry has been sent. Under a steady
+/// backlog, the file is compacted once the records of sent entries take more room than the
+/// live ones, and at least 4 KiB: the live records are copied to `<path>.tmp`, which then
+/// replaces the spool file.
+pub struct FileStorage {
+    file: File,
+    path: PathBuf,
+    /// offset and length of every live entry
+    index: VecDeque<(u64, u16)>,
+    /// bytes used by the live records
+    size: usize,
+    end: u64,
+    skipped: usize,
+}
+
+impl FileStorage {
+    /// opens (or creates) the spool file at `path`, recovering every intact entry
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FileStorage> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut index = VecDeque::new();
+        let mut skipped = 0;
+        let mut end = 0;
+        let mut offset = 0;
+        let mut resyncing = false;
+        while offset + RECORD_HEADER_LEN as usize <= contents.len() {
+            match parse_record(&contents[offset..]) {
+                Some((state, len)) => {
+                    if resyncing {
+                        skipped += 1;
+                        resyncing = false;
+                    }
+                    if state == RECORD_LIVE {
+                        index.push_back((offset as u64, len));
+                    }
+                    offset += RECORD_HEADER_LEN as usize + len as usize;
+                    end = offset;
+                }
+                None => {
+                    resyncing = true;
+                    offset += 1;
+                }
+            }
+        }
+        if resyncing || end < contents.len() {
+            skipped += 1;
+        }
+        if (end as u64) < contents.len() as u64 {
+            file.set_len(end as u64)?;
+        }
+
+        Ok(FileStorage {
+            file,
+            path,
+            size: index
+                .iter()
+                .map(|&(_, len)| RECORD_HEADER_LEN as usize + len as usize)
+                .sum(),
+            index,
+            end: end as u64,
+            skipped,
+        })
+    }
+
+    /// returns the number of torn or corrupted records skipped when the file was opened
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// rewrites the live records into a new file replacing this one, so the records of sent
+    /// entries stop using flash
+    ///
+    /// A power loss meanwhile leaves either file whole: the temporary one is only renamed once
+    /// written.
+    fn compact(&mut self) -> io::Result<()> {
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut compacted = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp)?;
+        let mut index = VecDeque::with_capacity(self.index.len());
+        let mut end = 0;
+        for &(offset, len) in &self.index {
+            let mut record = vec![0; RECORD_HEADER_LEN as usize + len as usize];
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(&mut record)?;
+            compacted.write_all(&record)?;
+            index.push_back((end, len));
+            end += record.len() as u64;
+        }
+        compacted.sync_all()?;
+        fs::rename(&temp, &self.path)?;
+        self.file = compacted;
+        self.index = index;
+        self.end = end;
+        Ok(())
+    }
+}
+
+/// returns the state and entry length of the record at the start of `input`, if it is intact
+fn parse_record(input: &[u8]) -> Option<(u8, u16)> {
+    if input[0] != RECORD_MAGIC || (input[1] != RECORD_LIVE && input[1] != RECORD_SENT) {
+        return None;
+    }
+    let len = u16::from_le_bytes([input[2], input[3]]);
+    let crc = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
+    let entry = input.get(RECORD_HEADER_LEN as usize..RECORD_HEADER_LEN as usize + len as usize)?;
+    if crc32(&[&input[2..4], entry]) != crc {
+        return None;
+    }
+    Some((input[1], len))
+}
+
+/// CRC-32 (IEEE 802.3) of the concatenation of `chunks`
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for chunk in chunks {
+        for &byte in *chunk {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = (crc >> 1) ^ (0xedb8_8320 & (!(crc & 1)).wrapping_add(1));
+            }
+        }
+    }
+    !crc
+}
+
+impl SpoolStorage for FileStorage {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        if entry.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "spooled entry too long",
+            ));
+        }
+        let len = (entry.len() as u16).to_le_bytes();
+        let mut record = vec![RECORD_MAGIC, RECORD_LIVE, len[0], len[1]];
+        record.extend_from_slice(&crc32(&[&len, entry]).to_le_bytes());
+        record.extend_from_slice(entry);
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&record)?;
+        self.file.flush()?;
+        self.index.push_back((self.end, entry.len() as u16));
+        self.size += record.len();
+        self.end += record.len() as u64;
+        Ok(())
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let (offset, len) = match self.index.front() {
+            None => return Ok(None),
+            Some(&record) => record,
+        };
+        let mut entry = vec![0; len as usize];
+        self.file
+            .seek(SeekFrom::Start(offset + RECORD_HEADER_LEN))?;
+        self.file.read_exact(&mut entry)?;
+        Ok(Some(entry))
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        if let Some((offset, len)) = self.index.pop_front() {
+            self.size -= RECORD_HEADER_LEN as usize + len as usize;
+            if self.index.is_empty() {
+                self.file.set_len(0)?;
+                self.end = 0;
+            } else {
+                self.file.seek(SeekFrom::Start(offset + 1))?;
+                self.file.write_all(&[RECORD_SENT])?;
+                let dead = self.end as usize - self.size;
+                if dead >= COMPACT_AFTER.max(self.size) {
+                    self.compact()?;
+                }
+            }
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Records of sent entries keep using flash until the file is compacted or emptied, so the
+    /// file itself can be larger than this, up to twice as large past the first 4 KiB.
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// What a full `LimitedStorage` does with a new entry
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// drops the oldest entries until the new one fits
+    DropOldest,
+    /// drops the new entry
+    DropNewest,
+}
+
+/// Caps the wrapped storage to `max_bytes`, as reported by `SpoolStorage::size`
+///
+/// RAM and flash storages are wrapped independently, each one with its own limit. A
+/// high-watermark callback can be registered so the application reacts (raising the spooled
+/// severity floor, for instance) before entries start being dropped; it is called once each
+/// time the storage grows past the watermark.
+pub struct LimitedStorage<S> {
+    storage: S,
+    max_bytes: usize,
+    overflow: Overflow,
+    high_watermark: usize,
+    on_high_watermark: Option<Box<dyn FnMut(usize) + Send>>,
+    above_watermark: bool,
+    dropped: usize,
+    last_push_dropped: bool,
+}
+
+impl<S: SpoolStorage> LimitedStorage<S> {
+    /// caps `storage` to `max_bytes`, dropping the oldest entries when it is full
+    pub fn new(storage: S, max_bytes: usize) -> LimitedStorage<S> {
+        LimitedStorage {
+            storage,
+            max_bytes,
+            overflow: Overflow::DropOldest,
+            high_watermark: max_bytes,
+            on_high_watermark: None,
+            above_watermark: false,
+            dropped: 0,
+            last_push_dropped: false,
+        }
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> LimitedStorage<S> {
+        self.overflow = overflow;
+        self
+    }
+
+    /// calls `callback` with the current size when the storage grows past `watermark` bytes
+    pub fn on_high_watermark<C>(mut self, watermark: usize, callback: C) -> LimitedStorage<S>
+    where
+        C: FnMut(usize) + Send + 'static,
+    {
+        self.high_watermark = watermark;
+        self.on_high_watermark = Some(Box::new(callback));
+        self
+    }
+
+    /// returns the number of entries dropped because the storage was full
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn check_watermark(&mut self) {
+        let size = self.storage.size();
+        if size < self.high_watermark {
+            self.above_watermark = false;
+        } else if !self.above_watermark {
+            self.above_watermark = true;
+            if let Some(ref mut callback) = self.on_high_watermark {
+                callback(size);
+            }
+        }
+    }
+}
+
+impl<S: SpoolStorage> SpoolStorage for LimitedStorage<S> {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        self.last_push_dropped = true;
+        if entry.len() > self.max_bytes {
+            self.dropped += 1;
+            return Ok(());
+        }
+        while self.storage.size() + entry.len() > self.max_bytes {
+            if self.overflow == Overflow::DropNewest || self.storage.is_empty() {
+                self.dropped += 1;
+                return Ok(());
+            }
+            self.storage.pop_front()?;
+            self.dropped += 1;
+        }
+        self.storage.push(entry)?;
+        self.last_push_dropped = self.storage.last_push_dropped();
+        self.check_watermark();
+        Ok(())
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.storage.front()
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        self.storage.pop_front()?;
+        self.check_watermark();
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    fn size(&self) -> usize {
+        self.storage.size()
+    }
+
+    fn last_push_dropped(&self) -> bool {
+        self.last_push_dropped
+    }
+}
+
+/// Which spooled entries `TieredStorage` keeps in its persistent storage, by priority
+///
+/// Entries at `threshold` or above (`LOG_WARNING` also persists `LOG_ERR`...) are persisted,
+/// unless the facility of the entry has a threshold of its own.
+#[derive(Clone, Debug)]
+pub struct PersistPolicy {
+    threshold: Severity,
+    facilities: Vec<(Facility, Severity)>,
+}
+
+impl PersistPolicy {
+    /// persists the entries at `threshold` or above
+    pub fn at_or_above(threshold: Severity) -> PersistPolicy {
+        PersistPolicy {
+            threshold,
+            facilities: Vec::new(),
+        }
+    }
+
+    /// persists the entries of `facility` at `threshold` or above instead
+    pub fn facility(mut self, facility: Facility, threshold: Severity) -> PersistPolicy {
+        self.facilities
+            .retain(|&(other, _)| other as u8 != facility as u8);
+        self.facilities.push((facility, threshold));
+        self
+    }
+
+    /// returns whether an entry with `priority` is persisted
+    pub fn persists(&self, priority: Priority) -> bool {
+        let threshold = self
+            .facilities
+            .iter()
+            .find(|&&(facility, _)| facility as u8 == priority & !7)
+            .map_or(self.threshold, |&(_, threshold)| threshold);
+        priority & 7 <= threshold as u8
+    }
+}
+
+/// Spools entries to a persistent storage or a volatile one, depending on their priority
+///
+/// Flash is spared the chatty low-severity entries, which stay in RAM, while the ones that
+/// matter survive a reboot: e.g. a `FileStorage` for `LOG_WARNING` and above, and a
+/// `MemoryStorage` for the rest, each one wrapped in a `LimitedStorage` with its own limit.
+/// Entries come out in the order they were pushed; the ones recovered from the persistent
+/// storage at startup come first. The priority is read from the `<PRI>` of the frame, and
+/// frames without one are persisted.
+pub struct TieredStorage<P, V> {
+    persistent: P,
+    volatile: V,
+    policy: PersistPolicy,
+    /// for each entry, from the oldest, whether it is in `persistent`
+    order: VecDeque<bool>,
+    last_push_dropped: bool,
+}
+
+impl<P: SpoolStorage, V: SpoolStorage> TieredStorage<P, V> {
+    pub fn new(persistent: P, volatile: V, policy: PersistPolicy) -> TieredStorage<P, V> {
+        let mut order: VecDeque<bool> = (0..persistent.len()).map(|_| true).collect();
+        order.extend((0..volatile.len()).map(|_| false));
+        TieredStorage {
+            persistent,
+            volatile,
+            policy,
+            order,
+            last_push_dropped: false,
+        }
+    }
+
+    pub fn into_inner(self) -> (P, V) {
+        (self.persistent, self.volatile)
+    }
+
+    fn tier_len(&self, persistent: bool) -> usize {
+        if persistent {
+            self.persistent.len()
+        } else {
+            self.volatile.len()
+        }
+    }
+
+    /// records an entry pushed to a tier that held `before` entries, forgetting the oldest
+    /// entries of the tier its storage dropped to make room, e.g. a full `LimitedStorage`, so
+    /// `order` matches the storages again
+    fn resync(&mut self, persistent: bool, before: usize) {
+        let stored = if persistent {
+            !self.persistent.last_push_dropped()
+        } else {
+            !self.volatile.last_push_dropped()
+        };
+        if stored {
+            self.order.push_back(persistent);
+        }
+        self.last_push_dropped = !stored;
+        let mut excess = (before + stored as usize).saturating_sub(self.tier_len(persistent));
+        self.order.retain(|&tier| {
+            if tier == persistent && excess > 0 {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+impl<P: SpoolStorage, V: SpoolStorage> SpoolStorage for TieredStorage<P, V> {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        let persistent = priority(entry).is_none_or(|priority| self.policy.persists(priority));
+        let before = self.tier_len(persistent);
+        if persistent {
+            self.persistent.push(entry)?;
+        } else {
+            self.volatile.push(entry)?;
+        }
+        self.resync(persistent, before);
+        Ok(())
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.order.front() {
+            Some(&true) => self.persistent.front(),
+            Some(&false) => self.volatile.front(),
+            None => Ok(None),
+        }
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        match self.order.front() {
+            Some(&true) => self.persistent.pop_front()?,
+            Some(&false) => self.volatile.pop_front()?,
+            None => return Ok(()),
+        }
+        self.order.pop_front();
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn size(&self) -> usize {
+        self.persistent.size() + self.volatile.size()
+    }
+
+    fn last_push_dropped(&self) -> bool {
+        self.last_push_dropped
+    }
+}
+
+/// returns the priority of a frame starting with `<PRI>`, possibly after an octet count
+pub(crate) fn priority(frame: &[u8]) -> Option<Priority> {
+    let start = frame.iter().take(8).position(|&byte| byte == b'<')? + 1;
+    let len = frame[start..]
+        .iter()
+        .take(4)
+        .position(|&byte| byte == b'>')?;
+    str::from_utf8(&frame[start..start + len])
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Encrypts every entry of the wrapped storage with AES-256-GCM
+///
+/// Each entry is stored as a random 96-bit nonce followed by the ciphertext and its tag, so a
+/// dumped flash partition doesn't reveal the spooled messages, and tampered entries are
+/// rejected with `io::ErrorKind::InvalidData` when read back.
+#[cfg(feature = "encryption")]
+pub struct EncryptedStorage<S> {
+    storage: S,
+    cipher: Aes256Gcm,
+}
+
+#[cfg(feature = "encryption")]
+impl<S: SpoolStorage> EncryptedStorage<S> {
+    pub fn new(storage: S, key: &[u8; 32]) -> EncryptedStorage<S> {
+        EncryptedStorage {
+            storage,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<S: SpoolStorage> SpoolStorage for EncryptedStorage<S> {
+    fn push(&mut self, entry: &[u8]) -> io::Result<()> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, entry)
+            .map_err(|_| io::Error::other("could not encrypt spooled entry"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        self.storage.push(&sealed)
+    }
+
+    fn front(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.storage.front()? {
+            None => Ok(None),
+            Some(ref sealed) if sealed.len() >= 12 => self
+                .cipher
+                .decrypt(Nonce::from_slice(&sealed[..12]), &sealed[12..])
+                .map(Some)
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "could not decrypt spooled entry",
+                    )
+                }),
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated spooled entry",
+            )),
+        }
+    }
+
+    fn pop_front(&mut self) -> io::Result<()> {
+        self.storage.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    fn size(&self) -> usize {
+        self.storage.size()
+    }
+
+    fn last_push_dropped(&self) -> bool {
+        self.storage.last_push_dropped()
+    }
+}
+
+/// reads a 32 bytes spool encryption key stored as a blob in NVS
+#[cfg(all(feature = "encryption", target_os = "espidf"))]
+pub fn key_from_nvs<T: esp_idf_svc::nvs::NvsPartitionId>(
+    nvs: &esp_idf_svc::nvs::EspNvs<T>,
+    name: &str,
+) -> Result<[u8; 32]> {
+    let mut key = [0; 32];
+    let len = nvs
+        .get_blob(name, &mut key)
+        .map_err(Error::initialization)?
+        .map(|blob| blob.len());
+    match len {
+        Some(32) => Ok(key),
+        _ => Err(Error::initialization("the key is not 32 bytes long")),
+    }
+}
+
+#[test]
+fn test_file_storage_recovery() {
+    let path = ::std::env::temp_dir().join(format!("esp_syslog_spool_{}", ::std::process::id()));
+    let _ = ::std::fs::remove_file(&path);
+
+    let mut storage = FileStorage::open(&path).unwrap();
+    for entry in &[&b"<11>one"[..], b"<11>two", b"<11>three", b"<11>four"] {
+        storage.push(entry).unwrap();
+    }
+    storage.pop_front().unwrap();
+    drop(storage);
+
+    // Corrupt "three" and tear a fifth record in the middle of its write.
+    let mut contents = ::std::fs::read(&path).unwrap();
+    let three = contents.len() - 12 - 15;
+    contents[three] ^= 0xff;
+    contents.extend_from_slice(&[RECORD_MAGIC, RECORD_LIVE, 42, 0, 1, 2]);
+    ::std::fs::write(&path, &contents).unwrap();
+
+    let mut storage = FileStorage::open(&path).unwrap();
+    assert_eq!(storage.skipped(), 2);
+    assert_eq!(storage.len(), 2);
+    assert_eq!(storage.front().unwrap().unwrap(), b"<11>two");
+    storage.pop_front().unwrap();
+    storage.push(b"<11>five").unwrap();
+    drop(storage);
+
+    // The torn record is gone, the corrupted one is still skipped until the file gets emptied.
+    let mut storage = FileStorage::open(&path).unwrap();
+    assert_eq!(storage.skipped(), 1);
+    assert_eq!(storage.front().unwrap().unwrap(), b"<11>four");
+    storage.pop_front().unwrap();
+    assert_eq!(storage.front().unwrap().unwrap(), b"<11>five");
+    storage.pop_front().unwrap();
+    assert!(storage.is_empty());
+    assert_eq!(::std::fs::metadata(&path).unwrap().len(), 0);
+
+    // Under a steady backlog, the file is compacted rather than growing
+    let entry = [b'x'; 92];
+    let record = RECORD_HEADER_LEN as usize + entry.len();
+    for _ in 0..10 {
+        storage.push(&entry).unwrap();
+    }
+    for i in 0..1000 {
+        storage.push(&[i as u8; 92]).unwrap();
+        storage.pop_front().unwrap();
+        let file_len = ::std::fs::metadata(&path).unwrap().len() as usize;
+        assert!(file_len <= storage.size() + COMPACT_AFTER + record);
+    }
+    assert_eq!(storage.len(), 10);
+    assert_eq!(storage.front().unwrap().unwrap(), [(990 % 256) as u8; 92]);
+    drop(storage);
+    let storage = FileStorage::open(&path).unwrap();
+    assert_eq!(storage.len(), 10);
+    assert_eq!(storage.skipped(), 0);
+    drop(storage);
+    ::std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_limited_storage() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let reached = Arc::new(AtomicUsize::new(0));
+    let callback_reached = reached.clone();
+    let mut storage =
+        LimitedStorage::new(MemoryStorage::new(), 10).on_high_watermark(8, move |_| {
+            callback_reached.fetch_add(1, Ordering::Relaxed);
+        });
+    storage.push(b"1234").unwrap();
+    storage.push(b"5678").unwrap();
+    assert_eq!(reached.load(Ordering::Relaxed), 1);
+    storage.push(b"9abc").unwrap();
+    assert_eq!(storage.dropped(), 1);
+    assert_eq!(storage.front().unwrap().unwrap(), b"5678");
+    assert_eq!(reached.load(Ordering::Relaxed), 1);
+
+    storage.pop_front().unwrap();
+    storage.push(b"defg").unwrap();
+    assert_eq!(reached.load(Ordering::Relaxed), 2);
+
+    let mut storage = storage.with_overflow(Overflow::DropNewest);
+    storage.push(b"hijk").unwrap();
+    assert_eq!(storage.dropped(), 2);
+    assert_eq!(storage.len(), 2);
+    assert_eq!(storage.front().unwrap().unwrap(), b"9abc");
+}
+
+#[test]
+fn test_tiered_storage() {
+    let policy = PersistPolicy::at_or_above(Severity::LOG_WARNING)
+        .facility(Facility::LOG_AUTH, Severity::LOG_INFO);
+    let persistent = LimitedStorage::new(MemoryStorage::new(), 30);
+    let mut storage = TieredStorage::new(persistent, MemoryStorage::new(), policy);
+    for entry in &[&b"<14>info"[..], b"<11>err", b"<38>auth info", b"<15>debug"] {
+        storage.push(entry).unwrap();
+    }
+
+    let (persistent, volatile) = storage.into_inner();
+    assert_eq!(persistent.len(), 2);
+    assert_eq!(volatile.len(), 2);
+    let policy = PersistPolicy::at_or_above(Severity::LOG_WARNING);
+    let mut storage = TieredStorage::new(persistent, volatile, policy);
+    // Drops <11>err, the oldest persisted entry
+    storage.push(b"17 <12>warning").unwrap();
+    let mut entries = Vec::new();
+    while let Some(entry) = storage.front().unwrap() {
+        entries.push(entry);
+        storage.pop_front().unwrap();
+    }
+    assert_eq!(
+        entries,
+        [
+            &b"<38>auth info"[..],
+            b"<14>info",
+            b"<15>debug",
+            b"17 <12>warning"
+        ]
+    );
+    assert!(!storage.last_push_dropped());
+
+    // The new entry is the one dropped, the persisted one older than <14>info stays first
+    let persistent =
+        LimitedStorage::new(MemoryStorage::new(), 20).with_overflow(Overflow::DropNewest);
+    let policy = PersistPolicy::at_or_above(Severity::LOG_WARNING);
+    let mut storage = TieredStorage::new(persistent, MemoryStorage::new(), policy);
+    for entry in &[&b"<11>err"[..], b"<14>info", b"<12>disk nearly full"] {
+        storage.push(entry).unwrap();
+    }
+    assert!(storage.last_push_dropped());
+    assert_eq!(storage.len(), 2);
+    assert_eq!(storage.front().unwrap().unwrap(), b"<11>err");
+    storage.pop_front().unwrap();
+    assert_eq!(storage.front().unwrap().unwrap(), b"<14>info");
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_storage() {
+    let mut storage = EncryptedStorage::new(MemoryStorage::new(), &[7; 32]);
+    storage.push(b"<11>secret").unwrap();
+    storage.push(b"<11>other").unwrap();
+    assert_eq!(storage.len(), 2);
+    assert_eq!(storage.front().unwrap().unwrap(), b"<11>secret");
+
+    let mut raw = storage.into_inner();
+    let sealed = raw.front().unwrap().unwrap();
+    assert!(!sealed.windows(6).any(|w| w == b"secret"));
+
+    let mut tampered = sealed.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    let mut storage = MemoryStorage::new();
+    storage.push(&tampered).unwrap();
+    let mut storage = EncryptedStorage::new(storage, &[7; 32]);
+    assert_eq!(
+        storage.front().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
@@ -0,0 +1,243 @@
+//! Redirection of stdout/stderr into syslog, enabled by the `capture-stdio` feature
+//!
+//! Whatever is printed to the captured streams, e.g. by `printf`s in vendored C code, is split
+//! into lines and each line is sent through the logger installed by one of the `init_*`
+//! functions, at the severity chosen for its stream. The output is still echoed to the
+//! original stream, so the serial console keeps working.
+//!
+//! On desktop builds the file descriptors are redirected into a pipe read by a background
+//! thread, which captures everything including Rust's `println!` and panic messages. On the
+//! ESP32, newlib's `stdout`/`stderr` are replaced by streams calling into this module: C code
+//! is captured, while Rust's `println!`, which writes to the file descriptors directly, is not.
+use std::cell::Cell;
+
+use errors::*;
+use {installed, Severity};
+
+/// longest line forwarded as one message, longer lines are split
+const MAX_LINE: usize = 1024;
+
+thread_local! {
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// redirects stdout and/or stderr into the installed logger, at the given severities
+///
+/// Streams given `None` are left alone. Meant to be called once, after the logger is
+/// installed; lines printed before it is installed are only echoed.
+pub fn capture_stdio(stdout: Option<Severity>, stderr: Option<Severity>) -> Result<()> {
+    if let Some(severity) = stdout {
+        platform::redirect(platform::Stream::Stdout, severity)?;
+    }
+    if let Some(severity) = stderr {
+        platform::redirect(platform::Stream::Stderr, severity)?;
+    }
+    Ok(())
+}
+
+/// runs `f` without capturing what it prints on the current thread
+///
+/// Used around the console logger, whose output would otherwise be sent a second time.
+pub(crate) fn passthrough<R, F: FnOnce() -> R>(f: F) -> R {
+    let quiet = QUIET.with(|quiet| quiet.replace(true));
+    let result = f();
+    QUIET.with(|q| q.set(quiet));
+    result
+}
+
+#[cfg(target_os = "espidf")]
+fn is_quiet() -> bool {
+    QUIET.with(|quiet| quiet.get())
+}
+
+fn forward(severity: Severity, line: &[u8]) {
+    if let Some(installed) = installed() {
+        let line = String::from_utf8_lossy(line).into_owned();
+        passthrough(|| {
+            let _ = installed.logger.lock().send(severity, line);
+        });
+    }
+}
+
+/// Splits a byte stream into lines
+struct Lines {
+    severity: Severity,
+    pending: Vec<u8>,
+}
+
+impl Lines {
+    fn new(severity: Severity) -> Lines {
+        Lines {
+            severity,
+            pending: Vec::new(),
+        }
+    }
+
+    fn feed<E: FnMut(Severity, &[u8])>(&mut self, data: &[u8], mut emit: E) {
+        for &byte in data {
+            match byte {
+                b'\n' => self.emit(&mut emit),
+                b'\r' => {}
+                _ => {
+                    self.pending.push(byte);
+                    if self.pending.len() == MAX_LINE {
+                        self.emit(&mut emit);
+                    }
+                }
+            }
+        }
+    }
+
+    fn emit<E: FnMut(Severity, &[u8])>(&mut self, emit: &mut E) {
+        if !self.pending.is_empty() {
+            emit(self.severity, &self.pending);
+            self.pending.clear();
+        }
+    }
+}
+
+#[cfg(not(target_os = "espidf"))]
+mod platform {
+    use std::fs::File;
+    use std::io::{self, Read, Write};
+    use std::os::unix::io::{FromRawFd, RawFd};
+    use std::thread;
+
+    use super::{forward, Lines};
+    use errors::*;
+    use Severity;
+
+    pub enum Stream {
+        Stdout,
+        Stderr,
+    }
+
+    fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    fn pipe_into(fd: RawFd) -> io::Result<(File, File)> {
+        let mut fds = [0; 2];
+        unsafe {
+            check(libc::pipe(fds.as_mut_ptr()))?;
+            let original = check(libc::dup(fd))?;
+            check(libc::dup2(fds[1], fd))?;
+            libc::close(fds[1]);
+            Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(original)))
+        }
+    }
+
+    pub fn redirect(stream: Stream, severity: Severity) -> Result<()> {
+        let fd = match stream {
+            Stream::Stdout => libc::STDOUT_FILENO,
+            Stream::Stderr => libc::STDERR_FILENO,
+        };
+        let _ = io::stdout().flush();
+        let (mut reader, mut echo) = pipe_into(fd).map_err(Error::initialization)?;
+
+        thread::Builder::new()
+            .name("syslog-stdio".into())
+            .spawn(move || {
+                let mut lines = Lines::new(severity);
+                let mut buf = [0; 256];
+                while let Ok(len @ 1..) = reader.read(&mut buf) {
+                    let _ = echo.write_all(&buf[..len]);
+                    lines.feed(&buf[..len], forward);
+                }
+            })
+            .map_err(Error::initialization)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "espidf")]
+mod platform {
+    use std::io;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::ptr;
+    use std::slice;
+    use std::sync::Mutex;
+
+    use esp_idf_svc::sys;
+
+    use super::{forward, is_quiet, Lines};
+    use errors::*;
+    use Severity;
+
+    pub enum Stream {
+        Stdout,
+        Stderr,
+    }
+
+    struct Cookie {
+        lines: Mutex<Lines>,
+        original: *mut sys::FILE,
+    }
+
+    unsafe extern "C" fn write(cookie: *mut c_void, data: *const c_char, len: c_int) -> c_int {
+        let cookie = &*(cookie as *const Cookie);
+        sys::fwrite(data as *const c_void, 1, len as _, cookie.original);
+        if !is_quiet() {
+            let data = slice::from_raw_parts(data as *const u8, len as usize);
+            cookie
+                .lines
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .feed(data, forward);
+        }
+        len
+    }
+
+    /// Replaces the stream both for the tasks started from now on and for the current one;
+    /// tasks that are already running keep their own streams.
+    pub fn redirect(stream: Stream, severity: Severity) -> Result<()> {
+        unsafe {
+            let global = sys::_global_impure_ptr as *mut sys::_reent;
+            let current = sys::__getreent();
+            let (global, current) = match stream {
+                Stream::Stdout => (&mut (*global)._stdout, &mut (*current)._stdout),
+                Stream::Stderr => (&mut (*global)._stderr, &mut (*current)._stderr),
+            };
+
+            let cookie = Box::leak(Box::new(Cookie {
+                lines: Mutex::new(Lines::new(severity)),
+                original: *global,
+            }));
+            let file = sys::funopen(
+                cookie as *mut Cookie as *const c_void,
+                None,
+                Some(write),
+                None,
+                None,
+            );
+            if file.is_null() {
+                return Err(io::Error::last_os_error()).map_err(Error::initialization);
+            }
+            sys::setvbuf(file, ptr::null_mut(), sys::_IOLBF as c_int, 0);
+            *global = file;
+            *current = file;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_split_lines() {
+    let mut lines = Lines::new(Severity::LOG_NOTICE);
+    let mut received = Vec::new();
+    let mut emit = |severity, line: &[u8]| received.push((severity, line.to_vec()));
+    lines.feed(b"first\r\nsec", &mut emit);
+    lines.feed(b"ond\n\n", &mut emit);
+    lines.feed(&[b'x'; MAX_LINE + 1], &mut emit);
+
+    assert_eq!(received.len(), 3);
+    assert!(matches!(received[0].0, Severity::LOG_NOTICE));
+    assert_eq!(received[0].1, b"first");
+    assert_eq!(received[1].1, b"second");
+    assert_eq!(received[2].1.len(), MAX_LINE);
+    assert_eq!(lines.pending, b"x");
+}
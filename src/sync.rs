@@ -0,0 +1,229 @@
+//! Locking of the state shared by the loggers
+//!
+//! `Lock` is a `std::sync::Mutex` by default. With the `critical-section` feature it takes a
+//! critical section of the `critical-section` crate instead, with the implementation provided
+//! by the HAL, so the shared state may also be locked from interrupt handlers on single- and
+//! dual-core ESP32s. The rest of the crate still needs `std`; bare-metal builds without it use
+//! the `esp_syslog_core` crate. Interrupts stay masked while a message is sent, which suits
+//! backends writing to memory or a UART rather than to the network.
+//!
+//! Logging only gives up on a lock held by the thread, or core, taking it again: a message
+//! logged while another one is being sent, by the backend itself or an error callback, is
+//! dropped and counted, see `Stats::reentrant`. Other threads wait for the lock, so tasks
+//! logging at the same time lose nothing.
+#[cfg(feature = "critical-section")]
+use std::cell::{Cell, UnsafeCell};
+use std::ops::{Deref, DerefMut};
+#[cfg(not(feature = "critical-section"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(feature = "critical-section"))]
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(feature = "critical-section")]
+use critical_section::RestoreState;
+
+/// A mutual exclusion lock, see the module documentation
+#[cfg(not(feature = "critical-section"))]
+pub struct Lock<T> {
+    mutex: Mutex<T>,
+    /// the `thread_id` of the thread holding the lock, 0 when it isn't held
+    owner: AtomicUsize,
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<T> Lock<T> {
+    pub const fn new(value: T) -> Lock<T> {
+        Lock {
+            mutex: Mutex::new(value),
+            owner: AtomicUsize::new(0),
+        }
+    }
+
+    /// locks, even if a thread panicked while holding the lock
+    ///
+    /// The logger state stays usable after a panic in the middle of a message, at worst with a
+    /// truncated frame, so the panic isn't turned into a logging blackout.
+    pub fn lock(&self) -> LockGuard<'_, T> {
+        let guard = self
+            .mutex
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.owner.store(thread_id(), Ordering::Relaxed);
+        LockGuard { lock: self, guard }
+    }
+
+    /// locks, waiting for other threads, unless the current thread already holds the lock
+    pub fn lock_unless_reentered(&self) -> Option<LockGuard<'_, T>> {
+        // Only the current thread stores its own id, so reading it back can't race
+        if self.owner.load(Ordering::Relaxed) == thread_id() {
+            return None;
+        }
+        Some(self.lock())
+    }
+}
+
+/// returns an id of the current thread, never 0, unique among the running threads
+#[cfg(not(feature = "critical-section"))]
+fn thread_id() -> usize {
+    thread_local!(static ID: u8 = const { 0 });
+    ID.with(|id| id as *const u8 as usize)
+}
+
+#[cfg(not(feature = "critical-section"))]
+pub struct LockGuard<'a, T> {
+    lock: &'a Lock<T>,
+    guard: MutexGuard<'a, T>,
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> Deref for LockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> DerefMut for LockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+#[cfg(not(feature = "critical-section"))]
+impl<'a, T> Drop for LockGuard<'a, T> {
+    fn drop(&mut self) {
+        // Cleared while the mutex is still held, before another thread can take it
+        self.lock.owner.store(0, Ordering::Relaxed);
+    }
+}
+
+/// A mutual exclusion lock, see the module documentation
+#[cfg(feature = "critical-section")]
+pub struct Lock<T> {
+    locked: Cell<bool>,
+    value: UnsafeCell<T>,
+}
+
+// The value is only reached from within a critical section, by one guard at a time
+#[cfg(feature = "critical-section")]
+unsafe impl<T: Send> Sync for Lock<T> {}
+
+#[cfg(feature = "critical-section")]
+impl<T> Lock<T> {
+    pub const fn new(value: T) -> Lock<T> {
+        Lock {
+            locked: Cell::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// enters a critical section for as long as the guard lives
+    ///
+    /// Critical sections nest, so locking again a lock held by the same core panics instead of
+    /// deadlocking: the logging path takes `lock_unless_reentered` instead.
+    pub fn lock(&self) -> LockGuard<'_, T> {
+        let restore = unsafe { critical_section::acquire() };
+        if self.locked.replace(true) {
+            unsafe { critical_section::release(restore) };
+            panic!("esp_syslog lock taken recursively");
+        }
+        LockGuard {
+            lock: self,
+            restore,
+        }
+    }
+
+    /// locks unless the current core already holds the lock
+    ///
+    /// Other cores can't hold it, being kept out by the critical section.
+    pub fn lock_unless_reentered(&self) -> Option<LockGuard<'_, T>> {
+        let restore = unsafe { critical_section::acquire() };
+        if self.locked.replace(true) {
+            unsafe { critical_section::release(restore) };
+            return None;
+        }
+        Some(LockGuard {
+            lock: self,
+            restore,
+        })
+    }
+}
+
+#[cfg(feature = "critical-section")]
+pub struct LockGuard<'a, T> {
+    lock: &'a Lock<T>,
+    restore: RestoreState,
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, T> Deref for LockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, T> DerefMut for LockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, T> Drop for LockGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.locked.set(false);
+        // Guards are dropped in the reverse order they were created, as critical sections
+        // must be released
+        unsafe { critical_section::release(self.restore) };
+    }
+}
+
+#[test]
+fn test_lock() {
+    let lock = Lock::new(Vec::new());
+    lock.lock().push(1);
+    {
+        let mut guard = lock.lock();
+        guard.push(2);
+    }
+    assert_eq!(*lock.lock(), [1, 2]);
+}
+
+#[test]
+fn test_lock_after_panic() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let lock = Arc::new(Lock::new(0));
+    let holder = lock.clone();
+    let panicked = thread::spawn(move || {
+        let mut value = holder.lock();
+        *value += 1;
+        panic!("while holding the lock");
+    })
+    .join();
+    assert!(panicked.is_err());
+    *lock.lock() += 1;
+    assert_eq!(*lock.lock(), 2);
+}
+
+#[test]
+fn test_lock_unless_reentered() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let lock = Arc::new(Lock::new(0));
+    let guard = lock.lock();
+    assert!(lock.lock_unless_reentered().is_none());
+    let other = lock.clone();
+    let waiting = thread::spawn(move || *other.lock_unless_reentered().unwrap() += 1);
+    drop(guard);
+    waiting.join().unwrap();
+    *lock.lock_unless_reentered().unwrap() += 1;
+    assert_eq!(*lock.lock(), 2);
+}
@@ -0,0 +1,497 @@
+//! TCP connection management for the TCP backend
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use esp_syslog_core::Framing;
+
+use address::first_ok;
+
+/// how often the connection is probed by default
+const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a `ReconnectingTcpStream` waits between reconnection attempts
+///
+/// The delay starts at `initial` after the first failed attempt and doubles with every
+/// following one, up to `max`, so a collector that is down isn't hammered, nor the Wi-Fi kept
+/// busy, while the messages logged meanwhile fail fast. It is reset once connected.
+#[derive(Copy, Clone, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    /// from 1 second up to a minute
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How long a TCP connection may block the logging thread on the network
+///
+/// A flaky Wi-Fi link can otherwise hang `connect` or a write for minutes, stalling every
+/// thread that logs meanwhile. `None` blocks until the network stack gives up. A write that
+/// times out fails like any other, with `io::ErrorKind::WouldBlock` or `TimedOut` depending on
+/// the platform, and drops the connection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    /// 5 seconds each
+    fn default() -> Self {
+        Timeouts {
+            connect: Some(DEFAULT_TIMEOUT),
+            read: Some(DEFAULT_TIMEOUT),
+            write: Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl Timeouts {
+    /// connects to `server` within the connect timeout, and sets the others on the connection
+    pub(crate) fn connect(&self, server: SocketAddr) -> io::Result<TcpStream> {
+        let stream = match self.connect {
+            Some(timeout) => TcpStream::connect_timeout(&server, timeout)?,
+            None => TcpStream::connect(server)?,
+        };
+        self.apply(&stream)?;
+        Ok(stream)
+    }
+
+    /// sets the read and write timeouts on `stream`
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(self.read)?;
+        stream.set_write_timeout(self.write)
+    }
+}
+
+/// A buffered TCP connection to a syslog server, re-established when the server closes it
+///
+/// Before writing, and at most once per probe interval, the connection is probed with a
+/// non-blocking `peek`: end of stream (the server sent a FIN) or a socket error (RST) means the
+/// server is gone, so the connection is re-established and the data buffered so far is carried
+/// over, instead of being buffered into the dead socket until its eventual write error. A
+/// write error drops the connection the same way.
+///
+/// Reconnection attempts are spaced with exponential backoff, see `Backoff`: until the next
+/// attempt is due, writes fail with `io::ErrorKind::NotConnected` without touching the network.
+///
+/// It is the transport of the TCP backend, and works as well on its own for other framings or
+/// side channels to the same collector, as any `Write`:
+///
+/// ```no_run
+/// # use std::io::Write;
+/// # use esp_syslog::ReconnectingTcpStream;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let reading = "{\"temp\":23.5}";
+/// let mut stream = ReconnectingTcpStream::connect("192.168.1.10:5140".parse()?)?;
+/// writeln!(stream, "{}", reading)?;
+/// stream.flush()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReconnectingTcpStream {
+    server: SocketAddr,
+    /// the addresses tried in turn until the first connection, `server` being kept from then on
+    candidates: Vec<SocketAddr>,
+    stream: Option<BufWriter<TcpStream>>,
+    /// data buffered by a stream that died, not handed to the new one yet
+    pending: Vec<u8>,
+    probe_interval: Duration,
+    last_probe: Instant,
+    backoff: Backoff,
+    /// the wait after the next failed attempt
+    delay: Duration,
+    /// when the next attempt is due, after a failed one
+    retry_at: Option<Instant>,
+    framing: Option<Framing>,
+    timeouts: Timeouts,
+    /// connections established so far
+    connections: usize,
+}
+
+impl ReconnectingTcpStream {
+    /// connects to `server` right away, returning the connection error
+    pub fn connect(server: SocketAddr) -> io::Result<ReconnectingTcpStream> {
+        ReconnectingTcpStream::connect_with_timeouts(server, Timeouts::default())
+    }
+
+    /// connects to `server` right away like `connect`, with `timeouts` rather than the default
+    /// ones
+    pub fn connect_with_timeouts(
+        server: SocketAddr,
+        timeouts: Timeouts,
+    ) -> io::Result<ReconnectingTcpStream> {
+        let mut stream = ReconnectingTcpStream::lazy(server);
+        stream.timeouts = timeouts;
+        stream.reconnect()?;
+        Ok(stream)
+    }
+
+    /// returns a stream connecting to `server` on the first write rather than right away
+    ///
+    /// Until the server can be reached, writes fail, with the connection error or while
+    /// waiting for the next attempt, and the data is dropped.
+    pub fn lazy(server: SocketAddr) -> ReconnectingTcpStream {
+        ReconnectingTcpStream {
+            server,
+            candidates: Vec::new(),
+            stream: None,
+            pending: Vec::new(),
+            probe_interval: DEFAULT_PROBE_INTERVAL,
+            last_probe: Instant::now(),
+            backoff: Backoff::default(),
+            delay: Backoff::default().initial,
+            retry_at: None,
+            framing: None,
+            timeouts: Timeouts::default(),
+            connections: 0,
+        }
+    }
+
+    /// returns a stream like `lazy`, trying the addresses of `servers` in turn until one takes
+    /// the connection, and reconnecting to that one from then on
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` without any address.
+    pub fn lazy_any(servers: &[SocketAddr]) -> io::Result<ReconnectingTcpStream> {
+        let first = *servers
+            .first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no server address"))?;
+        let mut stream = ReconnectingTcpStream::lazy(first);
+        stream.candidates = servers.to_vec();
+        Ok(stream)
+    }
+
+    /// returns the server this stream connects to, the first of `lazy_any` until connected
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// returns the current connection, if there is one
+    pub fn get_ref(&self) -> Option<&TcpStream> {
+        self.stream.as_ref().map(|stream| stream.get_ref())
+    }
+
+    /// sets how often the connection is probed, `Duration::ZERO` probing before every write
+    ///
+    /// Probing costs a few system calls and no network traffic. Defaults to 5 seconds.
+    pub fn set_probe_interval(&mut self, interval: Duration) {
+        self.probe_interval = interval;
+    }
+
+    /// sets the wait between reconnection attempts, see `Backoff`
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+        self.delay = backoff.initial;
+    }
+
+    /// delimits every write as one frame with `framing`, `None` writing the bytes as they are
+    ///
+    /// Defaults to `None`. With a framing, every `write` is one whole frame.
+    pub fn set_framing(&mut self, framing: Option<Framing>) {
+        self.framing = framing;
+    }
+
+    /// sets the timeouts of the current connection and of the following ones, see `Timeouts`
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) -> io::Result<()> {
+        self.timeouts = timeouts;
+        match self.get_ref() {
+            Some(stream) => timeouts.apply(stream),
+            None => Ok(()),
+        }
+    }
+
+    /// returns how many times the stream connected again after its first connection
+    pub fn reconnects(&self) -> usize {
+        self.connections.saturating_sub(1)
+    }
+
+    /// returns `false` if the server closed the connection, or if there is none
+    pub fn is_alive(&self) -> bool {
+        self.get_ref().is_some_and(probe)
+    }
+
+    fn check(&mut self) -> io::Result<&mut BufWriter<TcpStream>> {
+        if self.stream.is_some() && self.last_probe.elapsed() >= self.probe_interval {
+            self.last_probe = Instant::now();
+            if !self.is_alive() {
+                self.disconnect();
+            }
+        }
+        if self.stream.is_none() {
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "waiting to reconnect to the syslog server",
+                ));
+            }
+            if let Err(e) = self.reconnect() {
+                self.retry_at = Some(Instant::now() + self.delay);
+                self.delay = (self.delay * 2).min(self.backoff.max);
+                return Err(e);
+            }
+            self.retry_at = None;
+            self.delay = self.backoff.initial;
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+
+    /// connects now if not connected, without waiting for the attempt the backoff has due
+    pub fn connect_now(&mut self) -> io::Result<()> {
+        self.retry_at = None;
+        self.delay = self.backoff.initial;
+        self.check().map(|_| ())
+    }
+
+    /// flushes the connection, then closes it; the next write reconnects
+    pub fn close(&mut self) -> io::Result<()> {
+        if self.stream.is_some() {
+            self.flush()?;
+        }
+        match self.stream.take() {
+            Some(stream) => stream.get_ref().shutdown(Shutdown::Both),
+            None => Ok(()),
+        }
+    }
+
+    /// writes `frame` straight to the connection, ahead of the data buffered so far, which
+    /// only holds whole frames
+    pub(crate) fn write_now(&mut self, frame: &[u8]) -> io::Result<()> {
+        let written = match self.framing {
+            Some(framing) => self.check()?.get_mut().write_all(&framing.encode(frame)),
+            None => self.check()?.get_mut().write_all(frame),
+        };
+        if written.is_err() {
+            self.disconnect();
+        }
+        written
+    }
+
+    /// drops the connection, and takes the data it and a dead one still buffered: the frames
+    /// with a framing, the bytes written as they were without
+    pub(crate) fn take_pending(&mut self) -> Vec<Vec<u8>> {
+        self.disconnect();
+        let pending = mem::take(&mut self.pending);
+        match self.framing {
+            Some(framing) => framing.split(&pending),
+            None if pending.is_empty() => Vec::new(),
+            None => vec![pending],
+        }
+    }
+
+    pub(crate) fn disconnect(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let (_, buffered) = stream.into_parts();
+            if let Ok(buffered) = buffered {
+                self.pending.extend_from_slice(&buffered);
+            }
+        }
+    }
+
+    fn reconnect(&mut self) -> io::Result<()> {
+        let timeouts = self.timeouts;
+        let connection = if self.candidates.is_empty() {
+            timeouts.connect(self.server)?
+        } else {
+            let (server, connection) = first_ok(&self.candidates, |server| {
+                timeouts
+                    .connect(server)
+                    .map(|connection| (server, connection))
+            })?;
+            self.server = server;
+            self.candidates = Vec::new();
+            connection
+        };
+        let mut stream = BufWriter::new(connection);
+        stream.write_all(&self.pending)?;
+        self.pending.clear();
+        self.stream = Some(stream);
+        self.connections += 1;
+        Ok(())
+    }
+}
+
+fn probe(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let alive = match stream.peek(&mut [0]) {
+        Ok(0) => false,
+        Ok(_) => true,
+        Err(ref e) => e.kind() == io::ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_ok() && alive
+}
+
+impl Write for ReconnectingTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = match self.framing {
+            Some(framing) => {
+                let frame = framing.encode(buf);
+                self.check()?.write_all(&frame).map(|()| buf.len())
+            }
+            None => self.check()?.write(buf),
+        };
+        if written.is_err() {
+            self.disconnect();
+        }
+        written
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let flushed = self.check()?.flush();
+        if flushed.is_err() {
+            self.disconnect();
+        }
+        flushed
+    }
+}
+
+#[test]
+fn test_reconnect_after_server_close() {
+    use std::io::Read;
+    use std::net::{Shutdown, TcpListener};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = ReconnectingTcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    stream.set_probe_interval(Duration::ZERO);
+
+    let (first, _) = listener.accept().unwrap();
+    first.shutdown(Shutdown::Both).unwrap();
+    drop(first);
+    // let the FIN arrive
+    while stream.is_alive() {
+        ::std::thread::sleep(Duration::from_millis(1));
+    }
+
+    stream.write_all(b"after close").unwrap();
+    stream.flush().unwrap();
+    let (mut second, _) = listener.accept().unwrap();
+    let mut buf = [0; 11];
+    second.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"after close");
+    assert_eq!(stream.reconnects(), 1);
+}
+
+#[test]
+fn test_lazy_connect() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = ReconnectingTcpStream::lazy(listener.local_addr().unwrap());
+    assert!(stream.get_ref().is_none());
+
+    stream.write_all(b"first").unwrap();
+    stream.flush().unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    let mut buf = [0; 5];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"first");
+
+    // the first address is down, the second one takes the connection
+    let down = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut stream =
+        ReconnectingTcpStream::lazy_any(&[down, listener.local_addr().unwrap()]).unwrap();
+    assert_eq!(stream.server(), down);
+    stream.write_all(b"second").unwrap();
+    stream.flush().unwrap();
+    assert_eq!(stream.server(), listener.local_addr().unwrap());
+    let (mut server, _) = listener.accept().unwrap();
+    let mut buf = [0; 6];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"second");
+    assert!(ReconnectingTcpStream::lazy_any(&[]).is_err());
+}
+
+#[test]
+fn test_reconnect_backoff() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    let server = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut stream = ReconnectingTcpStream::lazy(server);
+    stream.set_backoff(Backoff {
+        initial: Duration::from_millis(50),
+        max: Duration::from_secs(1),
+    });
+
+    assert!(stream.write_all(b"refused").is_err());
+    let waiting = stream.write_all(b"waiting").unwrap_err();
+    assert_eq!(waiting.kind(), io::ErrorKind::NotConnected);
+
+    let listener = TcpListener::bind(server).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    stream.write_all(b"resumed").unwrap();
+    stream.flush().unwrap();
+    let (mut accepted, _) = listener.accept().unwrap();
+    let mut buf = [0; 7];
+    accepted.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"resumed");
+}
+
+#[test]
+fn test_framing() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = ReconnectingTcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    stream.set_framing(Some(Framing::OctetCounted));
+    stream.write_all(b"<14>one").unwrap();
+    stream.set_framing(Some(Framing::NonTransparent));
+    stream.write_all(b"<14>two").unwrap();
+    drop(stream);
+
+    let (mut server, _) = listener.accept().unwrap();
+    let mut received = Vec::new();
+    server.read_to_end(&mut received).unwrap();
+    assert_eq!(received, b"7 <14>one<14>two\n");
+}
+
+#[test]
+fn test_timeouts() {
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut stream = ReconnectingTcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let connection = stream.get_ref().unwrap();
+    assert_eq!(connection.write_timeout().unwrap(), Some(DEFAULT_TIMEOUT));
+
+    let timeouts = Timeouts {
+        connect: None,
+        read: None,
+        write: Some(Duration::from_millis(200)),
+    };
+    stream.set_timeouts(timeouts).unwrap();
+    let connection = stream.get_ref().unwrap();
+    assert_eq!(connection.read_timeout().unwrap(), None);
+    assert_eq!(
+        connection.write_timeout().unwrap(),
+        Some(Duration::from_millis(200))
+    );
+
+    stream.disconnect();
+    stream.flush().unwrap();
+    assert_eq!(
+        stream.get_ref().unwrap().write_timeout().unwrap(),
+        Some(Duration::from_millis(200))
+    );
+}
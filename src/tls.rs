@@ -0,0 +1,118 @@
+//! Syslog over TLS (RFC 5425), enabled by the `tls` feature
+//!
+//! Frames are sent over a TLS connection, each one prefixed by its length in decimal and a
+//! space, as RFC 5425 requires. When the connection is lost, it is re-established on the next
+//! write.
+//!
+//! The TLS configuration, certificates included, is up to the caller: build a
+//! `rustls::ClientConfig` with the root certificates of the server, and a client certificate if
+//! the server authenticates its clients.
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+
+use esp_syslog_core::Framing;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+
+/// A TLS connection carrying syslog frames to a server
+pub struct TlsStream {
+    server: SocketAddr,
+    server_name: ServerName<'static>,
+    config: Arc<ClientConfig>,
+    /// boxed, the TLS state is large
+    stream: Option<Box<StreamOwned<ClientConnection, TcpStream>>>,
+}
+
+impl TlsStream {
+    /// connects to `server`, whose certificate must be valid for `server_name`, and completes
+    /// the handshake
+    pub fn connect(
+        server: SocketAddr,
+        server_name: &str,
+        config: Arc<ClientConfig>,
+    ) -> io::Result<TlsStream> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut stream = TlsStream {
+            server,
+            server_name,
+            config,
+            stream: None,
+        };
+        stream.reconnect()?;
+        Ok(stream)
+    }
+
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.stream = None;
+        let mut socket = TcpStream::connect(self.server)?;
+        let mut connection = ClientConnection::new(self.config.clone(), self.server_name.clone())
+            .map_err(io::Error::other)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+        self.stream = Some(Box::new(StreamOwned::new(connection, socket)));
+        Ok(())
+    }
+
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let stream = match self.stream {
+            Some(ref mut stream) => stream,
+            None => return Err(io::ErrorKind::NotConnected.into()),
+        };
+        stream.write_all(&Framing::OctetCounted.encode(frame))
+    }
+}
+
+impl Write for TlsStream {
+    /// Sends `frame` as one syslog message, re-establishing the connection once if needed
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        if self.stream.is_none() {
+            self.reconnect()?;
+        }
+        if self.send(frame).is_err() {
+            self.reconnect()?;
+            self.send(frame)?;
+        }
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.stream {
+            Some(ref mut stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TlsStream {
+    fn drop(&mut self) {
+        if let Some(mut stream) = self.stream.take() {
+            stream.conn.send_close_notify();
+            let _ = stream.flush();
+        }
+    }
+}
+
+#[test]
+fn test_handshake_failure() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use rustls::RootCertStore;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap();
+    let plain = thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        client.write_all(b"<14>not TLS\n").unwrap();
+    });
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    assert!(TlsStream::connect(server, "logs.lan", Arc::new(config)).is_err());
+    plain.join().unwrap();
+}
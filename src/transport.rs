@@ -0,0 +1,83 @@
+//! Custom transports, for links the built-in backends don't cover
+use std::io;
+
+use LoggerBackend;
+
+/// A link sending formatted frames to a collector, e.g. a LoRa serial bridge, a BLE UART or
+/// an MQTT client
+///
+/// Every `send` is one whole frame, already formatted and framed by the logger's formatter. A
+/// transport becomes a backend with `LoggerBackend::custom`, so it works with `BasicLogger`,
+/// the `init_*` functions and the backends wrapping others (`with_fallback`, `with_spool`...),
+/// as the built-in ones do:
+///
+/// ```no_run
+/// # use std::io::{self, Write};
+/// # use esp_syslog::{Formatter3164, Logger, LoggerBackend, Transport};
+/// # type Uart = io::Sink;
+/// # fn main() {
+/// # let uart = io::sink();
+/// struct LoRa(Uart);
+///
+/// impl Transport for LoRa {
+///     fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+///         self.0.write_all(frame)?;
+///         self.0.write_all(b"\n")
+///     }
+/// }
+///
+/// let logger = Logger::new(LoggerBackend::custom(LoRa(uart)), Formatter3164::default());
+/// # }
+/// ```
+pub trait Transport {
+    /// sends `frame` as one message
+    fn send(&mut self, frame: &[u8]) -> io::Result<()>;
+
+    /// sends what the transport still buffers, if anything
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send(frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl LoggerBackend {
+    /// returns a backend sending the frames with `transport`
+    pub fn custom<T: Transport + Send + 'static>(transport: T) -> LoggerBackend {
+        LoggerBackend::Custom(Box::new(transport))
+    }
+}
+
+#[test]
+fn test_custom_transport() {
+    use std::sync::{Arc, Mutex};
+    use {Formatter3164, Logger};
+
+    struct Frames(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl Transport for Frames {
+        fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+            self.0.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let backend = LoggerBackend::custom(Frames(frames.clone()));
+    let mut logger = Logger::new(backend, Formatter3164::default());
+    logger.info("one").unwrap();
+    logger.info(format_args!("{}", "two")).unwrap();
+
+    let frames = frames.lock().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert!(frames[0].ends_with(b"main[0]: one"));
+    assert!(frames[1].ends_with(b"main[0]: two"));
+}
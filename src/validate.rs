@@ -0,0 +1,439 @@
+//! Checking produced frames against the RFC 3164 and RFC 5424 grammars
+//!
+//! This is meant for test rigs and for verifying custom formatters before shipping them; the
+//! loggers never validate what they send.
+use std::str;
+
+use errors::*;
+use Priority;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Rfc3164,
+    Rfc5424,
+}
+
+/// SD-ID and unescaped SD-PARAMs of every SD-ELEMENT of a parsed frame
+pub type ParsedStructuredData = Vec<(String, Vec<(String, String)>)>;
+
+/// A frame that passed validation, split into its fields
+///
+/// Fields holding the RFC 5424 NILVALUE (`-`) are `None`, as are the TIMESTAMP and HOSTNAME of
+/// a RFC 3164 frame sent without them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedMessage {
+    pub protocol: Protocol,
+    pub priority: Priority,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    /// APP-NAME for RFC 5424, TAG for RFC 3164
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    /// always `None` for RFC 3164
+    pub msg_id: Option<String>,
+    /// always empty for RFC 3164
+    pub structured_data: ParsedStructuredData,
+    pub message: String,
+}
+
+impl ParsedMessage {
+    pub fn facility(&self) -> u8 {
+        self.priority >> 3
+    }
+
+    pub fn severity(&self) -> u8 {
+        self.priority & 7
+    }
+}
+
+/// checks that `frame` is a valid RFC 3164 or RFC 5424 message, and returns its fields
+///
+/// RFC 5424 is detected from the version right after the PRI.
+pub fn validate(frame: &[u8]) -> Result<ParsedMessage> {
+    let mut parser = Parser { input: frame };
+    let priority = parser.priority()?;
+    if parser.input.starts_with(b"1 ") {
+        parser.input = &parser.input[2..];
+        parser.rfc5424(priority)
+    } else {
+        parser.rfc3164(priority)
+    }
+}
+
+fn invalid<T>(reason: &'static str) -> Result<T> {
+    Err(Error::Validation(reason))
+}
+
+fn is_print_us_ascii(b: u8) -> bool {
+    (33..=126).contains(&b)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    fn priority(&mut self) -> Result<Priority> {
+        if !self.input.starts_with(b"<") {
+            return invalid("missing PRI");
+        }
+        let digits = self.input[1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 || digits > 3 || self.input.get(1 + digits) != Some(&b'>') {
+            return invalid("malformed PRI");
+        }
+        let value = &self.input[1..1 + digits];
+        if value.len() > 1 && value[0] == b'0' {
+            return invalid("PRI has leading zeros");
+        }
+        let priority: u16 = str::from_utf8(value).unwrap().parse().unwrap();
+        if priority > 191 {
+            return invalid("PRI out of range");
+        }
+        self.input = &self.input[2 + digits..];
+        Ok(priority as Priority)
+    }
+
+    /// takes everything up to the next space, and the space itself
+    fn word(&mut self, max_len: usize, what: &'static str) -> Result<&'a [u8]> {
+        let len = self.input.iter().take_while(|&&b| b != b' ').count();
+        if len == self.input.len() {
+            return invalid(what);
+        }
+        let word = &self.input[..len];
+        if word.is_empty() || word.len() > max_len || !word.iter().all(|&b| is_print_us_ascii(b)) {
+            return invalid(what);
+        }
+        self.input = &self.input[len + 1..];
+        Ok(word)
+    }
+
+    fn nil_or_word(&mut self, max_len: usize, what: &'static str) -> Result<Option<String>> {
+        self.word(max_len, what).map(|word| match word {
+            b"-" => None,
+            _ => Some(String::from_utf8(word.to_vec()).unwrap()),
+        })
+    }
+
+    fn rfc5424(mut self, priority: Priority) -> Result<ParsedMessage> {
+        let timestamp = self.nil_or_word(64, "malformed TIMESTAMP")?;
+        if let Some(ref timestamp) = timestamp {
+            if !is_rfc3339(timestamp.as_bytes()) {
+                return invalid("malformed TIMESTAMP");
+            }
+        }
+        let hostname = self.nil_or_word(255, "malformed HOSTNAME")?;
+        let app_name = self.nil_or_word(48, "malformed APP-NAME")?;
+        let proc_id = self.nil_or_word(128, "malformed PROCID")?;
+        let msg_id = self.nil_or_word(32, "malformed MSGID")?;
+        let structured_data = self.structured_data()?;
+        let message = match self.input.first() {
+            None => String::new(),
+            Some(&b' ') => {
+                let mut message = &self.input[1..];
+                if message.starts_with(b"\xEF\xBB\xBF") {
+                    message = &message[3..];
+                }
+                String::from_utf8_lossy(message).into_owned()
+            }
+            Some(_) => return invalid("missing space after STRUCTURED-DATA"),
+        };
+
+        Ok(ParsedMessage {
+            protocol: Protocol::Rfc5424,
+            priority,
+            timestamp,
+            hostname,
+            app_name,
+            proc_id,
+            msg_id,
+            structured_data,
+            message,
+        })
+    }
+
+    fn sd_name(&mut self, what: &'static str) -> Result<String> {
+        let len = self
+            .input
+            .iter()
+            .take_while(|&&b| is_print_us_ascii(b) && b != b'=' && b != b']' && b != b'"')
+            .count();
+        if len == 0 || len > 32 {
+            return invalid(what);
+        }
+        let name = String::from_utf8(self.input[..len].to_vec()).unwrap();
+        self.input = &self.input[len..];
+        Ok(name)
+    }
+
+    fn structured_data(&mut self) -> Result<ParsedStructuredData> {
+        let mut elements = Vec::new();
+        if self.input.starts_with(b"-") {
+            self.input = &self.input[1..];
+            return Ok(elements);
+        }
+        while self.input.starts_with(b"[") {
+            self.input = &self.input[1..];
+            let id = self.sd_name("malformed SD-ID")?;
+            let mut params = Vec::new();
+            while self.input.starts_with(b" ") {
+                self.input = &self.input[1..];
+                let name = self.sd_name("malformed PARAM-NAME")?;
+                if !self.input.starts_with(b"=\"") {
+                    return invalid("malformed SD-PARAM");
+                }
+                self.input = &self.input[2..];
+                params.push((name, self.param_value()?));
+            }
+            if !self.input.starts_with(b"]") {
+                return invalid("unterminated SD-ELEMENT");
+            }
+            self.input = &self.input[1..];
+            elements.push((id, params));
+        }
+        if elements.is_empty() {
+            return invalid("missing STRUCTURED-DATA");
+        }
+        Ok(elements)
+    }
+
+    fn param_value(&mut self) -> Result<String> {
+        let mut value = Vec::new();
+        let mut i = 0;
+        loop {
+            match self.input.get(i) {
+                None => return invalid("unterminated PARAM-VALUE"),
+                Some(&b'"') => break,
+                Some(&b']') => return invalid("unescaped ']' in PARAM-VALUE"),
+                Some(&b'\\') => match self.input.get(i + 1) {
+                    Some(&escaped) if escaped == b'"' || escaped == b'\\' || escaped == b']' => {
+                        value.push(escaped);
+                        i += 2;
+                    }
+                    _ => {
+                        value.push(b'\\');
+                        i += 1;
+                    }
+                },
+                Some(&b) => {
+                    value.push(b);
+                    i += 1;
+                }
+            }
+        }
+        self.input = &self.input[i + 1..];
+        String::from_utf8(value).or_else(|_| invalid("PARAM-VALUE is not UTF-8"))
+    }
+
+    fn rfc3164(mut self, priority: Priority) -> Result<ParsedMessage> {
+        // Without a TIMESTAMP, the HOSTNAME is left out too, as the formatters do without the
+        // `timestamps` feature: anything starting with a month is taken for a TIMESTAMP
+        let (mut timestamp, mut hostname) = (None, None);
+        if self.input.len() > 3 && is_month(&self.input[..3]) && self.input[3] == b' ' {
+            if self.input.len() < 16
+                || !is_rfc3164_timestamp(&self.input[..15])
+                || self.input[15] != b' '
+            {
+                return invalid("malformed TIMESTAMP");
+            }
+            timestamp = Some(String::from_utf8(self.input[..15].to_vec()).unwrap());
+            self.input = &self.input[16..];
+            let word = self.word(255, "malformed HOSTNAME")?;
+            hostname = Some(String::from_utf8(word.to_vec()).unwrap());
+        }
+
+        let tag_len = self
+            .input
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || b"-_.".contains(b))
+            .count();
+        if tag_len > 32 {
+            return invalid("TAG longer than 32 characters");
+        }
+        let app_name = match tag_len {
+            0 => None,
+            _ => Some(String::from_utf8(self.input[..tag_len].to_vec()).unwrap()),
+        };
+        self.input = &self.input[tag_len..];
+
+        let mut proc_id = None;
+        if app_name.is_some() && self.input.starts_with(b"[") {
+            let len = self.input.iter().take_while(|&&b| b != b']').count();
+            if len == self.input.len() {
+                return invalid("unterminated PID");
+            }
+            proc_id = Some(String::from_utf8_lossy(&self.input[1..len]).into_owned());
+            self.input = &self.input[len + 1..];
+        }
+        if app_name.is_some() {
+            if self.input.starts_with(b": ") {
+                self.input = &self.input[2..];
+            } else if self.input.starts_with(b":") {
+                self.input = &self.input[1..];
+            }
+        }
+
+        Ok(ParsedMessage {
+            protocol: Protocol::Rfc3164,
+            priority,
+            timestamp,
+            hostname,
+            app_name,
+            proc_id,
+            msg_id: None,
+            structured_data: Vec::new(),
+            message: String::from_utf8_lossy(self.input).into_owned(),
+        })
+    }
+}
+
+fn digits(input: &[u8]) -> bool {
+    input.iter().all(|b| b.is_ascii_digit())
+}
+
+/// `Mmm`
+fn is_month(input: &[u8]) -> bool {
+    const MONTHS: [&[u8]; 12] = [
+        b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+        b"Dec",
+    ];
+    MONTHS.contains(&input)
+}
+
+/// `Mmm dd hh:mm:ss`, with a space-padded day
+fn is_rfc3164_timestamp(input: &[u8]) -> bool {
+    let day_ok = matches!(
+        (input[4], input[5]),
+        (b' ', b'1'..=b'9') | (b'1'..=b'2', b'0'..=b'9') | (b'3', b'0'..=b'1')
+    );
+    is_month(&input[..3])
+        && input[3] == b' '
+        && day_ok
+        && input[6] == b' '
+        && is_time(&input[7..15])
+}
+
+/// `hh:mm:ss`
+fn is_time(input: &[u8]) -> bool {
+    input.len() == 8
+        && input[2] == b':'
+        && input[5] == b':'
+        && digits(&input[..2])
+        && digits(&input[3..5])
+        && digits(&input[6..])
+        && &input[..2] < b"24".as_ref()
+        && &input[3..5] < b"60".as_ref()
+        && &input[6..] < b"61".as_ref()
+}
+
+/// `FULL-DATE "T" FULL-TIME` as restricted by RFC 5424
+fn is_rfc3339(input: &[u8]) -> bool {
+    if input.len() < 20
+        || !digits(&input[..4])
+        || input[4] != b'-'
+        || !digits(&input[5..7])
+        || input[7] != b'-'
+        || !digits(&input[8..10])
+        || input[10] != b'T'
+        || !is_time(&input[11..19])
+    {
+        return false;
+    }
+    let mut rest = &input[19..];
+    if rest.starts_with(b".") {
+        let fraction = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if fraction == 0 || fraction > 6 {
+            return false;
+        }
+        rest = &rest[1 + fraction..];
+    }
+    match rest {
+        b"Z" => true,
+        [b'+', ..] | [b'-', ..] => rest.len() == 6 && is_time(&[&rest[1..], b":00"].concat()),
+        _ => false,
+    }
+}
+
+#[test]
+fn test_validate_rfc5424() {
+    let parsed = validate(
+        b"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 \
+          [exampleSDID@32473 iut=\"3\" eventSource=\"Appli\\]cation\"] An application event",
+    )
+    .unwrap();
+    assert_eq!(parsed.protocol, Protocol::Rfc5424);
+    assert_eq!(parsed.facility(), 20);
+    assert_eq!(parsed.severity(), 5);
+    assert_eq!(parsed.proc_id, None);
+    assert_eq!(parsed.msg_id.as_ref().map(|s| &s[..]), Some("ID47"));
+    assert_eq!(parsed.structured_data[0].1[1].1, "Appli]cation");
+    assert_eq!(parsed.message, "An application event");
+
+    assert!(validate(b"<34>1 2003-10-11T22:14:15+02:00 host app 12 - -").is_ok());
+    assert!(validate(b"<34>1 2003-10-11 host app 12 - -").is_err());
+    assert!(validate(b"<34>1 - host app 12 - [id a=\"]\"]").is_err());
+    assert!(validate(b"<192>1 - - - - - -").is_err());
+}
+
+#[test]
+fn test_validate_rfc3164() {
+    let parsed = validate(b"<34>Oct  1 22:14:15 mymachine su[230]: 'su root' failed").unwrap();
+    assert_eq!(parsed.protocol, Protocol::Rfc3164);
+    assert_eq!(parsed.app_name.as_ref().map(|s| &s[..]), Some("su"));
+    assert_eq!(parsed.proc_id.as_ref().map(|s| &s[..]), Some("230"));
+    assert_eq!(parsed.message, "'su root' failed");
+
+    assert!(validate(b"<34>Oct 01 22:14:15 mymachine su: failed").is_err());
+    assert!(validate(b"<34>Oct 11 22:14 mymachine su: failed").is_err());
+
+    let parsed = validate(b"<14>main[0]: hello").unwrap();
+    assert_eq!((parsed.timestamp, parsed.hostname), (None, None));
+    assert_eq!(parsed.app_name.as_ref().map(|s| &s[..]), Some("main"));
+    assert_eq!(parsed.message, "hello");
+}
+
+#[test]
+fn test_validate_formatters() {
+    use format::StructuredData;
+    use {Formatter3164, Formatter5424, LogFormat, Severity};
+
+    fn frame<F: LogFormat<T>, T>(formatter: F, message: T) -> ParsedMessage {
+        let mut buf = Vec::new();
+        formatter
+            .format(&mut buf, Severity::LOG_INFO, message)
+            .unwrap();
+        validate(&buf).unwrap()
+    }
+
+    let parsed = frame(Formatter3164::default(), "hello");
+    assert_eq!(parsed.timestamp.is_some(), cfg!(feature = "timestamps"));
+    assert_eq!(parsed.app_name.as_ref().map(|s| &s[..]), Some("main"));
+    assert_eq!(parsed.proc_id.as_ref().map(|s| &s[..]), Some("0"));
+    assert_eq!(parsed.message, "hello");
+
+    let formatter = Formatter3164 {
+        pid: None,
+        ..Default::default()
+    };
+    assert_eq!(frame(formatter, "hello").proc_id, None);
+
+    let formatter = Formatter3164 {
+        process: "ota update".to_string(),
+        strict_tag: true,
+        ..Default::default()
+    };
+    let parsed = frame(formatter, "hello");
+    assert_eq!(parsed.app_name.as_ref().map(|s| &s[..]), Some("otaupdate"));
+    assert_eq!(parsed.message, "hello");
+
+    let mut data = StructuredData::new();
+    data.entry("sensor@32473".to_string())
+        .or_default()
+        .insert("temp".to_string(), "23.5".to_string());
+    let parsed = frame(Formatter5424::default(), (1, data, "hello"));
+    assert_eq!(parsed.protocol, Protocol::Rfc5424);
+    assert_eq!(parsed.structured_data[0].0, "sensor@32473");
+    assert_eq!(parsed.message, "hello");
+}
@@ -0,0 +1,95 @@
+//! Wi-Fi signal quality of the station, sent along with the messages
+use std::net::Ipv4Addr;
+
+use annotate::MessageFields;
+
+/// The signal of the access point the station is associated with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WifiSignal {
+    /// in dBm
+    pub rssi: i8,
+    pub channel: u8,
+}
+
+/// samples the signal of the access point, `None` when not associated (or not on an ESP32)
+#[cfg(target_os = "espidf")]
+pub fn wifi_signal() -> Option<WifiSignal> {
+    use esp_idf_svc::sys;
+
+    let mut info: sys::wifi_ap_record_t = unsafe { ::std::mem::zeroed() };
+    match unsafe { sys::esp_wifi_sta_get_ap_info(&mut info) } {
+        sys::ESP_OK => Some(WifiSignal {
+            rssi: info.rssi,
+            channel: info.primary,
+        }),
+        _ => None,
+    }
+}
+
+/// samples the signal of the access point, `None` when not associated (or not on an ESP32)
+#[cfg(not(target_os = "espidf"))]
+pub fn wifi_signal() -> Option<WifiSignal> {
+    None
+}
+
+/// The Wi-Fi interfaces of an ESP32, whose address a UDP logger can be bound to, see `UdpBind`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WifiInterface {
+    /// the station, associated with an access point
+    Station,
+    /// the soft access point
+    AccessPoint,
+}
+
+impl WifiInterface {
+    /// returns the IPv4 address of the interface, `None` while it has none (or not on an ESP32)
+    #[cfg(target_os = "espidf")]
+    pub fn address(self) -> Option<Ipv4Addr> {
+        use esp_idf_svc::sys;
+
+        let key: &[u8] = match self {
+            WifiInterface::Station => b"WIFI_STA_DEF\0",
+            WifiInterface::AccessPoint => b"WIFI_AP_DEF\0",
+        };
+        let netif = unsafe { sys::esp_netif_get_handle_from_ifkey(key.as_ptr() as *const _) };
+        if netif.is_null() {
+            return None;
+        }
+        let mut info: sys::esp_netif_ip_info_t = unsafe { ::std::mem::zeroed() };
+        match unsafe { sys::esp_netif_get_ip_info(netif, &mut info) } {
+            // lwIP keeps the address in network byte order
+            sys::ESP_OK if info.ip.addr != 0 => Some(Ipv4Addr::from(info.ip.addr.to_ne_bytes())),
+            _ => None,
+        }
+    }
+
+    /// returns the IPv4 address of the interface, `None` while it has none (or not on an ESP32)
+    #[cfg(not(target_os = "espidf"))]
+    pub fn address(self) -> Option<Ipv4Addr> {
+        None
+    }
+}
+
+/// adds the `rssi` and `channel` fields of `signal`, if any
+pub(crate) fn add_fields(fields: &mut MessageFields, signal: Option<WifiSignal>) {
+    if let Some(signal) = signal {
+        fields.add("rssi", signal.rssi);
+        fields.add("channel", signal.channel);
+    }
+}
+
+#[test]
+fn test_wifi_fields() {
+    use log::Level;
+
+    let mut fields = MessageFields::new(Level::Info, "app");
+    add_fields(&mut fields, None);
+    add_fields(
+        &mut fields,
+        Some(WifiSignal {
+            rssi: -67,
+            channel: 6,
+        }),
+    );
+    assert_eq!(fields.pairs(), [("rssi", "-67"), ("channel", "6")]);
+}